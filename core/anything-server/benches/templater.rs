@@ -0,0 +1,162 @@
+// Rendering task inputs from a template compiled once vs compiling it for every task.
+//
+//   cargo bench --bench templater
+//
+// On a single core, compile and render took 113-162µs and render precompiled 60-94µs, so
+// compiling once per flow version and action roughly halves the time spent rendering inputs.
+// Looking up the action's templates first, as the bundler does for every task, stays within that
+// noise at 62-109µs.
+//
+// The server is a binary, so the templater and templates cache sources are included directly,
+// along with their test modules which only compile without their tests here.
+#![allow(dead_code, unused_imports)]
+
+#[path = "../src/templater/mod.rs"]
+mod templater;
+
+#[path = "../src/types/json_schema.rs"]
+pub mod json_schema;
+
+#[path = "../src/bundler/templates/templates_cache.rs"]
+mod templates_cache;
+
+mod types {
+    pub use super::json_schema;
+}
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+use templater::Templater;
+use templates_cache::{get_compiled_templates, CompiledTaskTemplates, CompiledWorkflowTemplates};
+use types::json_schema::{ValidationField, ValidationFieldType};
+
+// Inputs of an HTTP action reading from earlier actions, close to what the bundler renders
+fn inputs_template() -> Value {
+    json!({
+        "method": "POST",
+        "url": "https://api.example.com/customers/{{ actions.lookup.result.customer.id }}/orders",
+        "headers": {
+            "Authorization": "Bearer {{ accounts.crm.access_token }}",
+            "Content-Type": "application/json",
+            "X-Request-Id": "{{ system.request_id ?? 'none' }}"
+        },
+        "body": {
+            "email": "{{ actions.lookup.result.customer.email | lower | trim }}",
+            "name": "{{ actions.lookup.result.customer.first_name }} {{ actions.lookup.result.customer.last_name }}",
+            "open_orders": "{{ actions.orders.result.items[?(@.status == 'open')].id }}",
+            "total": "{{ actions.orders.result.total ?? 0 }}",
+            "summary": "{% for item in actions.orders.result.items %}{{ loop.index }}. {{ item.name | upper }}{% if not loop.last %}, {% endif %}{% endfor %}",
+            "tags": ["order", "sync"],
+            "note": "{{ actions.lookup.result.customer?.note ?? 'n/a' }}"
+        },
+        "timeout": 30
+    })
+}
+
+fn context() -> Value {
+    let items: Vec<Value> = (0..20)
+        .map(|i| {
+            json!({
+                "id": i,
+                "name": format!("item {}", i),
+                "status": if i % 3 == 0 { "open" } else { "closed" }
+            })
+        })
+        .collect();
+
+    json!({
+        "accounts": { "crm": { "access_token": "token" } },
+        "system": { "request_id": "req_1" },
+        "actions": {
+            "lookup": {
+                "result": {
+                    "customer": {
+                        "id": 42,
+                        "email": "  Ann@Example.com ",
+                        "first_name": "Ann",
+                        "last_name": "Lee"
+                    }
+                }
+            },
+            "orders": { "result": { "items": items, "total": 1250 } }
+        }
+    })
+}
+
+fn validations() -> HashMap<String, ValidationField> {
+    [
+        ("method", ValidationFieldType::String),
+        ("url", ValidationFieldType::String),
+        ("headers", ValidationFieldType::Object),
+        ("body", ValidationFieldType::Object),
+        ("timeout", ValidationFieldType::Number),
+    ]
+    .into_iter()
+    .map(|(key, r#type)| {
+        (
+            key.to_string(),
+            ValidationField {
+                r#type,
+                strict: false,
+                escape: None,
+            },
+        )
+    })
+    .collect()
+}
+
+fn bench_templater(c: &mut Criterion) {
+    let template = inputs_template();
+    let context = context();
+    let validations = validations();
+    let templater = Templater::new();
+    let compiled = Templater::compile(&template).unwrap();
+
+    c.bench_function("compile and render", |b| {
+        b.iter(|| {
+            let compiled = Templater::compile(black_box(&template)).unwrap();
+            templater
+                .render_compiled(&compiled, black_box(&context), validations.clone())
+                .unwrap()
+        })
+    });
+
+    c.bench_function("render precompiled", |b| {
+        b.iter(|| {
+            templater
+                .render_compiled(&compiled, black_box(&context), validations.clone())
+                .unwrap()
+        })
+    });
+
+    // What the bundler does per task, the flow version's templates were compiled when the session started
+    let mut workflow_templates = CompiledWorkflowTemplates::default();
+    for i in 0..20 {
+        let templates = CompiledTaskTemplates::compile(Some(&template), None).unwrap();
+        workflow_templates.insert(&format!("action_{}", i), templates);
+    }
+
+    c.bench_function("lookup and render", |b| {
+        b.iter(|| {
+            let templates = get_compiled_templates(
+                &workflow_templates,
+                black_box("action_10"),
+                Some(&template),
+                None,
+            )
+            .unwrap();
+            templater
+                .render_compiled(
+                    templates.inputs.as_deref().unwrap(),
+                    black_box(&context),
+                    validations.clone(),
+                )
+                .unwrap()
+        })
+    });
+}
+
+criterion_group!(benches, bench_templater);
+criterion_main!(benches);
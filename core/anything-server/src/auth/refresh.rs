@@ -8,8 +8,11 @@ use reqwest::{header, Client};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::env;
+use std::sync::Arc;
 
 use crate::auth::init::{AccountAuthProviderAccount, AuthProvider, ErrorResponse, OAuthToken};
+use crate::platform_events::{PlatformEvent, PlatformEventType};
+use crate::AppState;
 
 use crate::vault::update_secret_in_vault;
 
@@ -20,6 +23,7 @@ pub struct UpdateAccountAuthProviderAccount {
 }
 
 pub async fn refresh_accounts(
+    state: &Arc<AppState>,
     client: &Postgrest,
     accounts: Vec<AccountAuthProviderAccount>,
 ) -> Result<Vec<AccountAuthProviderAccount>, Box<dyn std::error::Error + Send + Sync>> {
@@ -89,6 +93,8 @@ pub async fn refresh_accounts(
                     account.last_failure_retry = Some(Utc::now());
                 }
 
+                publish_refresh_failed(state, account, "Service not supported");
+
                 continue;
             }
         };
@@ -259,6 +265,16 @@ pub async fn refresh_accounts(
                             account.failure_retries += 1;
                             account.last_failure_retry = Some(Utc::now());
                         }
+
+                        publish_refresh_failed(
+                            state,
+                            account,
+                            &format!(
+                                "Failed to refresh token: Status: {}, Message: {}",
+                                status, msg
+                            ),
+                        );
+
                         println!(
                             "[AUTH REFRESH] Failed to refresh access token: Status: {:?}, Message: {:?}",
                             status, msg
@@ -282,6 +298,65 @@ pub async fn refresh_accounts(
     Ok(accounts)
 }
 
+// Refreshes one account even though its token hasn't expired yet, e.g. after an API rejected it
+pub async fn force_refresh_account(
+    state: &Arc<AppState>,
+    client: &Postgrest,
+    account: AccountAuthProviderAccount,
+) -> Result<AccountAuthProviderAccount, Box<dyn std::error::Error + Send + Sync>> {
+    println!(
+        "[AUTH REFRESH] Forcing token refresh for account: {:?}",
+        account.account_auth_provider_account_slug
+    );
+    let previous_access_token = account.access_token.clone();
+
+    let mut account = account;
+    account.access_token_expires_at = Some(Utc::now());
+
+    let refreshed = refresh_accounts(state, client, vec![account])
+        .await?
+        .pop()
+        .ok_or("No account returned from refresh")?;
+
+    // Refresh failures are recorded on the account rather than returned, the token just stays the same
+    if refreshed.access_token == previous_access_token {
+        return Err(format!(
+            "Failed to refresh access token for account '{}'",
+            refreshed.account_auth_provider_account_slug
+        )
+        .into());
+    }
+
+    // Later tasks should render the new token too
+    state
+        .bundler_accounts_cache
+        .write()
+        .await
+        .invalidate(&refreshed.account_id.to_string());
+
+    Ok(refreshed)
+}
+
+// Lets workflows listening for platform events know an account needs to be reconnected
+fn publish_refresh_failed(
+    state: &Arc<AppState>,
+    account: &AccountAuthProviderAccount,
+    failed_reason: &str,
+) {
+    state.platform_events.publish(PlatformEvent::new(
+        PlatformEventType::AccountRefreshFailed,
+        account.account_id,
+        json!({
+            "account_auth_provider_account_id": account.account_auth_provider_account_id,
+            "auth_provider_id": account.auth_provider_id,
+            "account_label": account.account_auth_provider_account_label,
+            "account_slug": account.account_auth_provider_account_slug,
+            "failed_reason": failed_reason,
+            "failure_retries": account.failure_retries,
+        }),
+    ));
+}
+
 pub async fn refresh_access_token(
     auth_provider: &AuthProvider,
     refresh_token: &str,
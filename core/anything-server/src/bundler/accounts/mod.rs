@@ -52,7 +52,7 @@ pub async fn fetch_cached_auth_accounts(
             println!("[FAST AUTH ACCOUNTS] Cached accounts do not need refresh");
         } else {
             println!("[FAST AUTH ACCOUNTS] Cached accounts need to have access_token refreshed");
-            accounts = refresh_accounts(client, accounts).await?;
+            accounts = refresh_accounts(&state, client, accounts).await?;
         }
     }
 
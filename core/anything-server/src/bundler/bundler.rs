@@ -12,29 +12,48 @@ use uuid::Uuid;
 
 use crate::bundler::accounts::fetch_cached_auth_accounts;
 use crate::bundler::secrets::get_decrypted_secrets;
+use crate::bundler::templates::get_compiled_templates;
+use crate::bundler::templates::templates_cache::CompiledWorkflowTemplates;
+use crate::files::task_results::resolve_result_references;
 use crate::files::utils::get_files;
-use crate::templater::{utils::get_template_file_requirements, Templater};
+use crate::templater::{
+    compiled::CompiledTemplate,
+    utils::{get_file_requirements, get_result_reference_requirements},
+    Templater,
+};
 use crate::types::task_types::TaskStatus;
 
-use crate::types::json_schema::ValidationField;
+use crate::types::json_schema::{EscapeMode, ValidationField};
 
 pub async fn bundle_tasks_cached_context(
     state: Arc<AppState>,
     client: &Postgrest,
     task: &Task,
+    workflow_templates: &CompiledWorkflowTemplates,
     refresh_auth: bool,
 ) -> Result<(Value, Value), Box<dyn Error + Send + Sync>> {
     println!("[BUNDLER] Starting to bundle context from parts");
 
-    let rendered_inputs_definition =
-        bundle_tasks_cached_inputs(state, client, task, refresh_auth).await?;
+    let rendered_inputs_definition = bundle_tasks_cached_inputs(
+        state.clone(),
+        client,
+        task,
+        workflow_templates,
+        refresh_auth,
+    )
+    .await?;
 
-    let plugin_config = task.config.plugin_config.as_ref();
+    let templates = get_compiled_templates(
+        workflow_templates,
+        &task.action_id,
+        task.config.inputs.as_ref(),
+        task.config.plugin_config.as_ref(),
+    )?;
     let plugin_config_schema = task.config.plugin_config_schema.as_ref();
 
-    let rendered_plugin_config_definition = bundle_plugin_config(
+    let rendered_plugin_config_definition = bundle_compiled_plugin_config(
         rendered_inputs_definition.clone(),
-        plugin_config,
+        templates.plugin_config.as_deref(),
         plugin_config_schema,
     )?;
 
@@ -48,21 +67,27 @@ pub async fn bundle_tasks_cached_inputs(
     state: Arc<AppState>,
     client: &Postgrest,
     task: &Task,
+    workflow_templates: &CompiledWorkflowTemplates,
     refresh_auth: bool,
 ) -> Result<Value, Box<dyn Error + Send + Sync>> {
     println!("[BUNDLER] Starting to bundle context from parts");
 
     let account_id = task.account_id.to_string();
     let flow_session_id = task.flow_session_id.to_string();
-    let inputs = task.config.inputs.as_ref();
+    let templates = get_compiled_templates(
+        workflow_templates,
+        &task.action_id,
+        task.config.inputs.as_ref(),
+        task.config.plugin_config.as_ref(),
+    )?;
     let inputs_schema = task.config.inputs_schema.as_ref();
 
-    let rendered_inputs_definition = bundle_cached_inputs(
+    let rendered_inputs_definition = bundle_compiled_inputs(
         state,
         client,
         &account_id,
         &flow_session_id,
-        inputs,
+        templates.inputs.as_deref(),
         inputs_schema,
         refresh_auth,
     )
@@ -110,13 +135,38 @@ pub async fn bundle_cached_inputs(
     inputs: Option<&Value>,
     inputs_schema: Option<&JsonSchema>,
     refresh_auth: bool,
+) -> Result<Value, Box<dyn Error + Send + Sync>> {
+    let inputs = inputs.map(Templater::compile).transpose()?;
+
+    bundle_compiled_inputs(
+        state,
+        client,
+        account_id,
+        flow_session_id,
+        inputs.as_ref(),
+        inputs_schema,
+        refresh_auth,
+    )
+    .await
+}
+
+pub async fn bundle_compiled_inputs(
+    state: Arc<AppState>,
+    client: &Postgrest,
+    account_id: &str,
+    flow_session_id: &str,
+    inputs: Option<&CompiledTemplate>,
+    inputs_schema: Option<&JsonSchema>,
+    refresh_auth: bool,
 ) -> Result<Value, Box<dyn Error + Send + Sync>> {
     println!("[BUNDLER] Starting to bundle inputs");
 
     // Pre-allocate with known capacity
     let mut render_inputs_context = HashMap::with_capacity(5);
 
-    let required_files = get_template_file_requirements(inputs.unwrap())?;
+    let required_files = inputs
+        .map(|inputs| get_file_requirements(inputs.variables()))
+        .unwrap_or_default();
     println!("[BUNDLER] Required files: {:?}", required_files);
 
     // Parallel fetch of secrets, accounts, and cached task results
@@ -173,15 +223,24 @@ pub async fn bundle_cached_inputs(
     );
 
     // Extract and set validations from schemas
-    let mut templater = Templater::new();
+    let templater = Templater::new();
 
     if let Some(inputs) = inputs {
-        templater.add_template("task_inputs_definition", inputs.clone());
-
         let input_validations = extract_template_key_validations_from_schema(inputs_schema);
-        let context_value = serde_json::to_value(&render_inputs_context)?;
-        let rendered =
-            templater.render("task_inputs_definition", &context_value, input_validations)?;
+        let mut context_value = serde_json::to_value(&render_inputs_context)?;
+
+        // Only pull offloaded results back from R2 when this template actually uses them
+        let result_references =
+            get_result_reference_requirements(inputs.variables(), &context_value);
+        if !result_references.is_empty() {
+            println!(
+                "[BUNDLER] Resolving {} offloaded task results",
+                result_references.len()
+            );
+            resolve_result_references(&state, &mut context_value, result_references).await?;
+        }
+
+        let rendered = templater.render_compiled(inputs, &context_value, input_validations)?;
 
         println!("[BUNDLER] Rendered inputs output: {}", rendered);
         Ok(rendered)
@@ -214,29 +273,43 @@ pub fn bundle_plugin_config(
     rendered_inputs: Value,
     plugin_config: Option<&Value>,
     plugin_config_schema: Option<&JsonSchema>,
+) -> Result<Value, Box<dyn Error + Send + Sync>> {
+    if let Some(plugin_config) = plugin_config {
+        println!(
+            "[BUNDLER] Task plugin config definition: {}",
+            plugin_config.clone()
+        );
+    }
+    let plugin_config = plugin_config.map(Templater::compile).transpose()?;
+
+    bundle_compiled_plugin_config(
+        rendered_inputs,
+        plugin_config.as_ref(),
+        plugin_config_schema,
+    )
+}
+
+pub fn bundle_compiled_plugin_config(
+    rendered_inputs: Value,
+    plugin_config: Option<&CompiledTemplate>,
+    plugin_config_schema: Option<&JsonSchema>,
 ) -> Result<Value, Box<dyn Error + Send + Sync>> {
     let mut render_input_context: HashMap<String, Value> = HashMap::new();
     render_input_context.insert("inputs".to_string(), rendered_inputs);
 
     // Create a new Templater instance for rendering inputs
-    let mut templater = Templater::new();
+    let templater = Templater::new();
 
     // Convert context HashMap to Value
     let inputs_context_value = serde_json::to_value(render_input_context.clone())?;
 
-    // Add the task definition as a template and render if it exists
+    // Render the task definition if it exists
     if let Some(plugin_config) = plugin_config {
-        println!(
-            "[BUNDLER] Task plugin config definition: {}",
-            plugin_config.clone()
-        );
-        templater.add_template("task_plugin_config_definition", plugin_config.clone());
-
         let plugin_config_validations =
             extract_template_key_validations_from_schema(plugin_config_schema);
         // Render the task definition with the context
-        let rendered_plugin_config_definition = templater.render(
-            "task_plugin_config_definition",
+        let rendered_plugin_config_definition = templater.render_compiled(
+            plugin_config,
             &inputs_context_value,
             plugin_config_validations,
         )?;
@@ -260,7 +333,21 @@ fn extract_template_key_validations_from_schema(
         if let Some(properties) = &schema.properties {
             for (property_name, property_schema) in properties {
                 if let Some(validation) = &property_schema.x_any_validation {
-                    template_key_validations.insert(property_name.clone(), validation.clone());
+                    let mut validation = validation.clone();
+                    // Escaping set on the field wins over the content type, then the input type
+                    if validation.escape.is_none() {
+                        validation.escape = property_schema
+                            .content_media_type
+                            .as_deref()
+                            .and_then(EscapeMode::from_content_type)
+                            .or_else(|| {
+                                property_schema
+                                    .x_jsf_presentation
+                                    .as_ref()
+                                    .and_then(|presentation| presentation.input_type.escape_mode())
+                            });
+                    }
+                    template_key_validations.insert(property_name.clone(), validation);
                 }
             }
         }
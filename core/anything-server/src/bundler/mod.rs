@@ -1,6 +1,7 @@
 pub mod accounts;
 pub mod bundler;
 pub mod secrets;
+pub mod templates;
 
 use std::{sync::Arc, time::Duration};
 
@@ -22,5 +23,9 @@ pub async fn cleanup_bundler_caches(state: Arc<AppState>) {
             let mut accounts_cache = state.bundler_accounts_cache.write().await;
             accounts_cache.cleanup();
         }
+        {
+            let mut templates_cache = state.bundler_templates_cache.write().await;
+            templates_cache.cleanup();
+        }
     }
 }
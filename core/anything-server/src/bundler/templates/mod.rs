@@ -0,0 +1,53 @@
+use std::sync::Arc;
+
+use crate::types::workflow_types::DatabaseFlowVersion;
+use crate::AppState;
+
+pub mod templates_cache;
+
+pub use templates_cache::get_compiled_templates;
+use templates_cache::{CompiledTaskTemplates, CompiledWorkflowTemplates};
+
+// Templates are compiled once per published flow version and rendered for every task after.
+// Drafts are edited in place and only run as tests, so they compile once per session.
+pub async fn get_compiled_workflow_templates(
+    state: &Arc<AppState>,
+    workflow: &DatabaseFlowVersion,
+) -> Arc<CompiledWorkflowTemplates> {
+    if workflow.published {
+        let cache = state.bundler_templates_cache.read().await;
+        if let Some(templates) = cache.get(&workflow.flow_version_id) {
+            println!(
+                "[BUNDLER] Using cached templates for flow_version_id: {}",
+                workflow.flow_version_id
+            );
+            return templates;
+        }
+    }
+
+    println!(
+        "[BUNDLER] Compiling templates for flow_version_id: {}",
+        workflow.flow_version_id
+    );
+
+    let mut templates = CompiledWorkflowTemplates::default();
+    for action in &workflow.flow_definition.actions {
+        // The same sources tasks are created with, see create_task_for_action
+        let inputs = action.inputs.clone().unwrap_or_default();
+        match CompiledTaskTemplates::compile(Some(&inputs), Some(&action.plugin_config)) {
+            Ok(compiled) => templates.insert(&action.action_id, compiled),
+            Err(e) => println!(
+                "[BUNDLER] Failed to compile templates for action_id {}: {}",
+                action.action_id, e
+            ),
+        }
+    }
+    let templates = Arc::new(templates);
+
+    if workflow.published {
+        let mut cache = state.bundler_templates_cache.write().await;
+        cache.set(&workflow.flow_version_id, templates.clone());
+    }
+
+    templates
+}
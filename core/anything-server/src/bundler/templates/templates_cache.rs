@@ -0,0 +1,123 @@
+use serde_json::Value;
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+
+use crate::templater::compiled::CompiledTemplate;
+use crate::templater::Templater;
+
+// The inputs and plugin config of one action, compiled
+#[derive(Clone, Debug, Default)]
+pub struct CompiledTaskTemplates {
+    pub inputs: Option<Arc<CompiledTemplate>>,
+    pub plugin_config: Option<Arc<CompiledTemplate>>,
+}
+
+impl CompiledTaskTemplates {
+    pub fn compile(
+        inputs: Option<&Value>,
+        plugin_config: Option<&Value>,
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        Ok(Self {
+            inputs: inputs.map(Templater::compile).transpose()?.map(Arc::new),
+            plugin_config: plugin_config
+                .map(Templater::compile)
+                .transpose()?
+                .map(Arc::new),
+        })
+    }
+}
+
+/// The compiled templates of every action in a flow version, kept next to its definition while a
+/// session runs. Actions whose templates don't compile are left out.
+#[derive(Debug, Default)]
+pub struct CompiledWorkflowTemplates {
+    actions: HashMap<String, CompiledTaskTemplates>,
+}
+
+impl CompiledWorkflowTemplates {
+    pub fn insert(&mut self, action_id: &str, templates: CompiledTaskTemplates) {
+        self.actions.insert(action_id.to_string(), templates);
+    }
+
+    pub fn get(&self, action_id: &str) -> Option<&CompiledTaskTemplates> {
+        self.actions.get(action_id)
+    }
+}
+
+/// Tasks are created from their session's workflow definition, so the action's templates are
+/// looked up by id. Tasks of actions that aren't there compile their own and report the error.
+pub fn get_compiled_templates(
+    templates: &CompiledWorkflowTemplates,
+    action_id: &str,
+    inputs: Option<&Value>,
+    plugin_config: Option<&Value>,
+) -> Result<CompiledTaskTemplates, Box<dyn Error + Send + Sync>> {
+    match templates.get(action_id) {
+        Some(templates) => Ok(templates.clone()),
+        None => CompiledTaskTemplates::compile(inputs, plugin_config),
+    }
+}
+
+struct CachedTemplates {
+    templates: Arc<CompiledWorkflowTemplates>,
+    // Seconds since the epoch, bumped under the read lock
+    last_used: AtomicU64,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_secs())
+        .unwrap_or_default()
+}
+
+/// Compiled templates of published flow versions. Those are never edited, so an entry stays valid
+/// for as long as the version exists and is only dropped once nothing ran it for `idle_ttl`.
+pub struct TemplatesCache {
+    cache: HashMap<Uuid, CachedTemplates>, // flow_version_id -> templates
+    idle_ttl: Duration,
+}
+
+impl TemplatesCache {
+    pub fn new(idle_ttl: Duration) -> Self {
+        println!(
+            "[BUNDLER] Creating new TemplatesCache with idle TTL: {:?}",
+            idle_ttl
+        );
+        Self {
+            cache: HashMap::new(),
+            idle_ttl,
+        }
+    }
+
+    pub fn get(&self, flow_version_id: &Uuid) -> Option<Arc<CompiledWorkflowTemplates>> {
+        let entry = self.cache.get(flow_version_id)?;
+        entry.last_used.store(now_secs(), Ordering::Relaxed);
+        Some(entry.templates.clone())
+    }
+
+    pub fn set(&mut self, flow_version_id: &Uuid, templates: Arc<CompiledWorkflowTemplates>) {
+        println!(
+            "[BUNDLER] Setting templates cache for flow_version_id: {}",
+            flow_version_id
+        );
+        self.cache.insert(
+            *flow_version_id,
+            CachedTemplates {
+                templates,
+                last_used: AtomicU64::new(now_secs()),
+            },
+        );
+    }
+
+    pub fn cleanup(&mut self) {
+        println!("[BUNDLER] Starting templates cache cleanup");
+        let cutoff = now_secs().saturating_sub(self.idle_ttl.as_secs());
+        self.cache
+            .retain(|_, entry| entry.last_used.load(Ordering::Relaxed) > cutoff);
+    }
+}
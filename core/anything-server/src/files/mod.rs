@@ -1,3 +1,4 @@
 pub mod r2_client;
 pub mod routes;
+pub mod task_results;
 pub mod utils;
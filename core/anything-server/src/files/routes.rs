@@ -45,7 +45,7 @@ pub struct FileResponse {
 }
 
 // Add this helper function at the top with other imports
-fn make_filename_url_safe(filename: &str) -> String {
+pub(crate) fn make_filename_url_safe(filename: &str) -> String {
     // Replace spaces and problematic characters with underscores or dashes
     // Remove or encode special characters that could cause URL issues
     filename
@@ -0,0 +1,151 @@
+use aws_sdk_s3::primitives::ByteStream;
+use serde_json::{json, Value};
+use std::error::Error;
+use std::sync::Arc;
+
+use crate::files::utils::r2_bucket;
+use crate::templater::utils::{ResultReferenceRequirement, RESULT_REFERENCE_KEY};
+use crate::types::task_types::Task;
+use crate::AppState;
+
+const DEFAULT_OFFLOAD_THRESHOLD_BYTES: usize = 256 * 1024; // 256KB
+
+// Results bigger than this get moved to R2 instead of living in Postgres and the session cache
+pub fn task_result_offload_threshold() -> usize {
+    std::env::var("TASK_RESULT_OFFLOAD_THRESHOLD_BYTES")
+        .ok()
+        .and_then(|s| s.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_OFFLOAD_THRESHOLD_BYTES)
+}
+
+/// Stores the result in R2 when it is above the offload threshold and returns a reference object
+/// in its place. Small results and upload failures fall back to returning the result unchanged.
+pub async fn offload_task_result_if_large(
+    state: &Arc<AppState>,
+    task: &Task,
+    result: Option<Value>,
+) -> Option<Value> {
+    let r2_key = format!(
+        "{}/task_results/{}/{}.json",
+        task.account_id, task.flow_session_id, task.task_id
+    );
+    offload_if_large(state, task, result?, "result", r2_key).await
+}
+
+/// Same as `offload_task_result_if_large` for the bundled context the task ran with
+pub async fn offload_task_context_if_large(
+    state: &Arc<AppState>,
+    task: &Task,
+    context: Value,
+) -> Value {
+    let r2_key = format!(
+        "{}/task_results/{}/{}.context.json",
+        task.account_id, task.flow_session_id, task.task_id
+    );
+    offload_if_large(state, task, context, "context", r2_key).await
+}
+
+async fn offload_if_large(
+    state: &Arc<AppState>,
+    task: &Task,
+    value: Value,
+    kind: &str,
+    r2_key: String,
+) -> Value {
+    let serialized = match serde_json::to_vec(&value) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            println!("[TASK RESULTS] Failed to serialize task {}: {}", kind, e);
+            return value;
+        }
+    };
+
+    let size = serialized.len();
+    if size <= task_result_offload_threshold() {
+        return value;
+    }
+
+    let bucket = match r2_bucket() {
+        Ok(bucket) => bucket,
+        Err(e) => {
+            println!(
+                "[TASK RESULTS] Can't offload {} for task {}, keeping it inline: {}",
+                kind, task.task_id, e
+            );
+            return value;
+        }
+    };
+
+    println!(
+        "[TASK RESULTS] Offloading {} byte {} for task {} to R2: {}",
+        size, kind, task.task_id, r2_key
+    );
+
+    match state
+        .r2_client
+        .put_object()
+        .bucket(&bucket)
+        .key(&r2_key)
+        .body(ByteStream::from(serialized))
+        .content_type("application/json")
+        .send()
+        .await
+    {
+        Ok(_) => json!({
+            RESULT_REFERENCE_KEY: {
+                "storage": "r2",
+                "key": r2_key,
+                "size": size,
+                "content_type": "application/json"
+            }
+        }),
+        Err(e) => {
+            println!(
+                "[TASK RESULTS] Failed to offload {} for task {}, keeping it inline: {:?}",
+                kind, task.task_id, e
+            );
+            value
+        }
+    }
+}
+
+pub async fn fetch_offloaded_result(
+    state: &Arc<AppState>,
+    r2_key: &str,
+) -> Result<Value, Box<dyn Error + Send + Sync>> {
+    let bucket = r2_bucket()?;
+
+    println!(
+        "[TASK RESULTS] Fetching offloaded result from R2: {}",
+        r2_key
+    );
+
+    let object = state
+        .r2_client
+        .get_object()
+        .bucket(&bucket)
+        .key(r2_key)
+        .send()
+        .await?;
+
+    let data = object.body.collect().await?.into_bytes();
+    let result: Value = serde_json::from_slice(&data)?;
+
+    Ok(result)
+}
+
+/// Swaps every required reference in the render context for the stored result it points to
+pub async fn resolve_result_references(
+    state: &Arc<AppState>,
+    context: &mut Value,
+    requirements: Vec<ResultReferenceRequirement>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    for requirement in requirements {
+        let result = fetch_offloaded_result(state, &requirement.key).await?;
+        if let Some(slot) = context.pointer_mut(&requirement.pointer) {
+            *slot = result;
+        }
+    }
+
+    Ok(())
+}
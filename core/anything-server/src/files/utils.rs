@@ -1,6 +1,8 @@
-use crate::files::routes::FileMetadata;
+use crate::files::routes::{make_filename_url_safe, FileAccessType, FileMetadata};
 use crate::templater::utils::FileRequirement;
 use crate::AppState;
+use aws_sdk_s3::primitives::ByteStream;
+use axum::body::Bytes;
 use dotenv::dotenv;
 use postgrest::Postgrest;
 use serde::{Deserialize, Serialize};
@@ -10,6 +12,7 @@ use std::collections::HashMap;
 use std::env;
 use std::error::Error;
 use std::sync::Arc;
+use uuid::Uuid;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct FileData {
@@ -112,3 +115,84 @@ pub async fn get_files(
 
     Ok(files_data)
 }
+
+pub fn r2_bucket() -> Result<String, Box<dyn Error + Send + Sync>> {
+    std::env::var("R2_BUCKET").map_err(|_| "R2_BUCKET is not set".into())
+}
+
+/// Stores a private file for the account outside of the upload route, e.g. files posted to a webhook.
+/// Each file gets its own folder under `folder` so same named uploads never overwrite each other.
+pub async fn store_private_file(
+    state: &Arc<AppState>,
+    account_id: &str,
+    folder: &str,
+    file_name: &str,
+    content_type: &str,
+    data: Bytes,
+) -> Result<FileMetadata, Box<dyn Error + Send + Sync>> {
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")?;
+    let bucket = r2_bucket()?;
+
+    let file_id = Uuid::new_v4().to_string();
+    let safe_filename = make_filename_url_safe(file_name);
+    let r2_key = format!("{}/{}/{}/{}", account_id, folder, file_id, safe_filename);
+
+    println!(
+        "[FILES] Storing {} ({} bytes) at {}",
+        file_name,
+        data.len(),
+        r2_key
+    );
+
+    state
+        .r2_client
+        .put_object()
+        .bucket(&bucket)
+        .key(&r2_key)
+        .body(ByteStream::from(data.clone()))
+        .content_type(content_type)
+        .send()
+        .await?;
+
+    let file_metadata = FileMetadata {
+        file_id,
+        file_name: safe_filename,
+        file_size: data.len() as i64,
+        content_type: content_type.to_string(),
+        account_id: account_id.to_string(),
+        path: Some(r2_key.clone()),
+        public_url: None,
+        access_type: FileAccessType::Private,
+    };
+
+    let stored = match state
+        .anything_client
+        .from("files")
+        .auth(supabase_service_role_api_key)
+        .insert(serde_json::to_string(&file_metadata)?)
+        .execute()
+        .await
+    {
+        Ok(response) if response.status().is_success() => Ok(()),
+        Ok(response) => Err(format!(
+            "Failed to store file metadata: {}",
+            response.text().await.unwrap_or_default()
+        )),
+        Err(e) => Err(format!("Failed to store file metadata: {:?}", e)),
+    };
+
+    if let Err(e) = stored {
+        // Don't leave files behind that nothing points to
+        let _ = state
+            .r2_client
+            .delete_object()
+            .bucket(&bucket)
+            .key(&r2_key)
+            .send()
+            .await;
+        return Err(e.into());
+    }
+
+    Ok(file_metadata)
+}
@@ -7,18 +7,24 @@ use axum::{
     extract::DefaultBodyLimit,
 };
  
-use bundler::{accounts::accounts_cache::AccountsCache, secrets::secrets_cache::SecretsCache};
+use bundler::{
+    accounts::accounts_cache::AccountsCache, secrets::secrets_cache::SecretsCache,
+    templates::templates_cache::TemplatesCache,
+};
 use dotenv::dotenv;
+use system_plugins::http::client_cache::HttpClientCache;
 use processor::processor::ProcessorMessage;
 use postgrest::Postgrest;
 use reqwest::Client;
-use status_updater::StatusUpdateMessage;
+use status_updater::{events::FlowSessionEvent, StatusUpdateMessage};
+use platform_events::PlatformEventBus;
+use trigger_engine::{InMemoryTrigger, TriggerKey};
 use serde_json::Value;
 use std::{collections::HashMap, time::Duration, time::Instant};
 use std::env;
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use tokio::sync::{watch, Semaphore};
+use tokio::sync::{broadcast, watch, Semaphore};
 use tower_http::cors::{AllowOrigin, CorsLayer};
 use tower_http::set_header::SetResponseHeaderLayer;
 use tokio::sync::mpsc; 
@@ -59,6 +65,7 @@ mod types;
 mod templater;
 mod testing; 
 mod trigger_engine;
+mod platform_events;
 mod agents; 
 
 use tokio::sync::oneshot;
@@ -84,16 +91,21 @@ pub struct AppState {
     public_client: Arc<Postgrest>,
     r2_client: Arc<S3Client>,
     http_client: Arc<Client>,
+    http_client_cache: RwLock<HttpClientCache>,
     workflow_processor_semaphore: Arc<Semaphore>,
     auth_states: RwLock<HashMap<String, AuthState>>,
     trigger_engine_signal: watch::Sender<String>,
+    trigger_engine_triggers: Arc<RwLock<HashMap<TriggerKey, InMemoryTrigger>>>,
     processor_sender: mpsc::Sender<ProcessorMessage>,
     task_updater_sender: mpsc::Sender<StatusUpdateMessage>,
+    flow_session_events: broadcast::Sender<FlowSessionEvent>,
+    platform_events: PlatformEventBus,
     flow_completions: Arc<Mutex<HashMap<String, FlowCompletion>>>,
     api_key_cache: Arc<RwLock<HashMap<String, CachedApiKey>>>,
     account_access_cache: Arc<RwLock<account_auth_middleware::AccountAccessCache>>,
     bundler_secrets_cache: RwLock<SecretsCache>,
     bundler_accounts_cache: RwLock<AccountsCache>,
+    bundler_templates_cache: RwLock<TemplatesCache>,
     flow_session_cache: Arc<RwLock<processor::flow_session_cache::FlowSessionCache>>,
     shutdown_signal: Arc<AtomicBool>,
 }
@@ -270,15 +282,20 @@ async fn main() {
     // Create the task updater channel  
    let (task_updater_tx, task_updater_rx) = mpsc::channel::<StatusUpdateMessage>(100000); 
 
+    // Live flow session progress for SSE watchers
+    let (flow_session_events_tx, _) = broadcast::channel::<FlowSessionEvent>(10000);
+
     let state = Arc::new(AppState {
         anything_client: anything_client.clone(),
         marketplace_client: marketplace_client.clone(),
         public_client: public_client.clone(),
         r2_client: r2_client.clone(),
         http_client: Arc::new(Client::new()),
+        http_client_cache: RwLock::new(HttpClientCache::new()),
         auth_states: RwLock::new(HashMap::new()),
         workflow_processor_semaphore: Arc::new(Semaphore::new(10)), //How many workflows we can run at once
         trigger_engine_signal,
+        trigger_engine_triggers: Arc::new(RwLock::new(HashMap::new())),
         processor_sender: processor_tx,
         // processor_receiver: Mutex::new(processor_rx),
         flow_completions: Arc::new(Mutex::new(HashMap::new())),
@@ -288,15 +305,24 @@ async fn main() {
         )),
         bundler_secrets_cache: RwLock::new(SecretsCache::new(Duration::from_secs(86400))), // 1 day TTL
         bundler_accounts_cache: RwLock::new(AccountsCache::new(Duration::from_secs(86400))), // 1 day TTL
+        bundler_templates_cache: RwLock::new(TemplatesCache::new(Duration::from_secs(86400))), // Dropped after a day unused
         flow_session_cache: Arc::new(RwLock::new(processor::flow_session_cache::FlowSessionCache::new(Duration::from_secs(3600)))),
         shutdown_signal: Arc::new(AtomicBool::new(false)),
         task_updater_sender: task_updater_tx.clone(), // Store the sender in AppState
+        flow_session_events: flow_session_events_tx,
+        platform_events: PlatformEventBus::new(10000),
     });
 
 pub async fn root() -> impl IntoResponse {
     Html(r#"Check out <a href="https://www.tryanything.xyz">tryanything.xyz</a> to start"#)
 }
 
+    // Every webhook request is logged, including the ones that get rejected
+    let webhook_request_log = middleware::from_fn_with_state(
+        state.clone(),
+        system_plugins::webhook_trigger::request_log::record_webhook_request,
+    );
+
     // Define routes that are public
     let public_routes = Router::new()
     .route("/", get(root))
@@ -327,10 +353,11 @@ pub async fn root() -> impl IntoResponse {
     .route("/marketplace/profile/:username", get(marketplace::profiles::get_marketplace_profile_by_username))
 
     // API Routes for running workflows - some protection done at api.rs vs route level
-    .route("/api/v1/workflow/:workflow_id/start", any(system_plugins::webhook_trigger::run_workflow))
-    .route("/api/v1/workflow/:workflow_id/start/respond", any(system_plugins::webhook_trigger::run_workflow_and_respond))
-    .route("/api/v1/workflow/:workflow_id/version/:workflow_version_id/start", any(system_plugins::webhook_trigger::run_workflow_version))
-    .route("/api/v1/workflow/:workflow_id/version/:workflow_version_id/start/respond", any(system_plugins::webhook_trigger::run_workflow_version_and_respond))
+    .route("/api/v1/workflow/:workflow_id/start", any(system_plugins::webhook_trigger::run_workflow).layer(webhook_request_log.clone()))
+    .route("/api/v1/workflow/:workflow_id/start/respond", any(system_plugins::webhook_trigger::run_workflow_and_respond).layer(webhook_request_log.clone()))
+    .route("/api/v1/workflow/:workflow_id/version/:workflow_version_id/start", any(system_plugins::webhook_trigger::run_workflow_version).layer(webhook_request_log.clone()))
+    .route("/api/v1/workflow/:workflow_id/version/:workflow_version_id/start/respond", any(system_plugins::webhook_trigger::run_workflow_version_and_respond).layer(webhook_request_log.clone()))
+    .route("/api/v1/workflow/:workflow_id/session/:flow_session_id/status", get(system_plugins::webhook_trigger::async_response::get_webhook_session_status))
 
     // API routes for running agent tools - very simliar to webhooks just shapped differnt to capture relationshipe between agent and workflow
     .route("/api/v1/agent/:agent_id/tool/:tool_id/start/respond", post(system_plugins::agent_tool_trigger::run_workflow_as_tool_call_and_respond));
@@ -355,6 +382,11 @@ pub async fn root() -> impl IntoResponse {
         .route("/account/:account_id/workflow/json", post(workflows::create_workflow_from_json))
         .route("/account/:account_id/workflow/:id", delete(workflows::delete_workflow))
         .route("/account/:account_id/workflow/:id", put(workflows::update_workflow))
+        .route("/account/:account_id/workflow/:id/pause", post(workflows::pause_workflow))
+        .route("/account/:account_id/workflow/:id/resume", post(workflows::resume_workflow))
+        .route("/account/:account_id/workflow/:id/triggers/upcoming", get(workflows::get_upcoming_trigger_runs))
+        .route("/account/:account_id/workflow/:id/webhook/requests", get(system_plugins::webhook_trigger::request_log::get_webhook_requests))
+        .route("/account/:account_id/workflow/:id/webhook/request/:webhook_request_id/replay", post(system_plugins::webhook_trigger::request_log::replay_webhook_request))
         .route("/account/:account_id/actions", get(actions::get_actions))
         .route("/account/:account_id/triggers", get(actions::get_triggers))
         .route("/account/:account_id/other", get(actions::get_other_actions))
@@ -379,6 +411,7 @@ pub async fn root() -> impl IntoResponse {
         //Tasks
         .route("/account/:account_id/tasks", get(tasks::get_tasks))
         .route("/account/:account_id/tasks/:workflow_id", get(tasks::get_task_by_workflow_id))
+        .route("/account/:account_id/session/:flow_session_id/events", get(tasks::stream_flow_session_events))
 
         //Charts
         .route(
@@ -511,6 +544,7 @@ pub async fn root() -> impl IntoResponse {
     // Add the cache cleanup task here
     tokio::spawn(account_auth_middleware::cleanup_account_access_cache(state.clone()));
     tokio::spawn(bundler::cleanup_bundler_caches(state.clone()));
+    tokio::spawn(platform_events::cleanup_platform_event_sessions(state.clone()));
 
     // Spawn a channel monitoring task
     tokio::spawn({
@@ -688,7 +722,13 @@ pub async fn root() -> impl IntoResponse {
 
     // Run the API server
     let listener = tokio::net::TcpListener::bind(&bind_address).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    // Webhook IP allowlists need the peer address
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+    )
+    .await
+    .unwrap();
 
     // Add this with your other spawned tasks:
     // tokio::spawn(periodic_thread_warmup(state.clone()));
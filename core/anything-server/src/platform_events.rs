@@ -0,0 +1,236 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::status_updater::Operation;
+use crate::types::task_types::{FlowSessionStatus, TaskStatus};
+use crate::AppState;
+
+// Event triggered sessions can set off more events. Chains deeper than this are dropped.
+pub const MAX_EVENT_CHAIN_DEPTH: u32 = 3;
+
+// Sessions that never complete on this instance are forgotten after this
+const TRACKED_SESSION_TTL: Duration = Duration::from_secs(86400);
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlatformEventType {
+    WorkflowCompleted,
+    WorkflowFailed,
+    TaskFailed,
+    AccountRefreshFailed,
+}
+
+impl PlatformEventType {
+    pub fn as_str(&self) -> &str {
+        match self {
+            PlatformEventType::WorkflowCompleted => "workflow_completed",
+            PlatformEventType::WorkflowFailed => "workflow_failed",
+            PlatformEventType::TaskFailed => "task_failed",
+            PlatformEventType::AccountRefreshFailed => "account_refresh_failed",
+        }
+    }
+}
+
+// Something that happened inside the platform that workflows can react to
+#[derive(Debug, Clone, Serialize)]
+pub struct PlatformEvent {
+    pub event_type: PlatformEventType,
+    pub account_id: Uuid,
+    pub flow_id: Option<Uuid>,
+    pub flow_session_id: Option<Uuid>,
+    pub chain_depth: u32, // How many event triggered sessions led up to this event
+    pub data: Value,
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl PlatformEvent {
+    pub fn new(event_type: PlatformEventType, account_id: Uuid, data: Value) -> Self {
+        PlatformEvent {
+            event_type,
+            account_id,
+            flow_id: None,
+            flow_session_id: None,
+            chain_depth: 0,
+            data,
+            occurred_at: Utc::now(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct TrackedSession {
+    account_id: Uuid,
+    flow_id: Uuid,
+    failed: bool,
+    tracked_at: Instant,
+}
+
+/// Fans platform events out to the trigger engine. Workflow events are worked out from the
+/// status updater operations, which only carry ids, so running sessions are tracked here.
+pub struct PlatformEventBus {
+    sender: broadcast::Sender<PlatformEvent>,
+    sessions: Mutex<HashMap<Uuid, TrackedSession>>,
+    chain_depths: Mutex<HashMap<Uuid, (u32, Instant)>>,
+}
+
+impl PlatformEventBus {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        PlatformEventBus {
+            sender,
+            sessions: Mutex::new(HashMap::new()),
+            chain_depths: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<PlatformEvent> {
+        self.sender.subscribe()
+    }
+
+    pub fn publish(&self, event: PlatformEvent) {
+        println!(
+            "[PLATFORM EVENTS] Publishing {} for account {}",
+            event.event_type.as_str(),
+            event.account_id
+        );
+        // No subscribers is fine
+        let _ = self.sender.send(event);
+    }
+
+    // Remembers that a session was started by an event so its own events carry the chain depth
+    pub fn set_chain_depth(&self, flow_session_id: Uuid, chain_depth: u32) {
+        self.chain_depths
+            .lock()
+            .unwrap()
+            .insert(flow_session_id, (chain_depth, Instant::now()));
+    }
+
+    // For sessions that were registered but never started
+    pub fn forget_session(&self, flow_session_id: &Uuid) {
+        self.sessions.lock().unwrap().remove(flow_session_id);
+        self.chain_depths.lock().unwrap().remove(flow_session_id);
+    }
+
+    pub fn cleanup(&self) {
+        println!("[PLATFORM EVENTS] Starting tracked session cleanup");
+        let mut sessions = self.sessions.lock().unwrap();
+        let mut chain_depths = self.chain_depths.lock().unwrap();
+        let before_count = sessions.len() + chain_depths.len();
+        sessions.retain(|_, session| session.tracked_at.elapsed() < TRACKED_SESSION_TTL);
+        chain_depths.retain(|_, (_, set_at)| set_at.elapsed() < TRACKED_SESSION_TTL);
+        println!(
+            "[PLATFORM EVENTS] Tracked session cleanup complete - removed {} entries",
+            before_count - sessions.len() - chain_depths.len()
+        );
+    }
+
+    pub fn record_operation(&self, operation: &Operation) {
+        match operation {
+            Operation::CreateTask { task_id: _, input } => {
+                self.sessions
+                    .lock()
+                    .unwrap()
+                    .entry(input.flow_session_id)
+                    .or_insert(TrackedSession {
+                        account_id: input.account_id,
+                        flow_id: input.flow_id,
+                        failed: false,
+                        tracked_at: Instant::now(),
+                    });
+            }
+            Operation::UpdateTask {
+                task_id,
+                flow_session_id,
+                status: TaskStatus::Failed,
+                result,
+                error,
+                ..
+            } => {
+                let (account_id, flow_id) = {
+                    let mut sessions = self.sessions.lock().unwrap();
+                    match sessions.get_mut(flow_session_id) {
+                        Some(session) => {
+                            session.failed = true;
+                            (session.account_id, session.flow_id)
+                        }
+                        None => return,
+                    }
+                };
+
+                let chain_depth = self.chain_depth(flow_session_id, false);
+                self.publish(PlatformEvent {
+                    flow_id: Some(flow_id),
+                    flow_session_id: Some(*flow_session_id),
+                    chain_depth,
+                    ..PlatformEvent::new(
+                        PlatformEventType::TaskFailed,
+                        account_id,
+                        json!({
+                            "task_id": task_id,
+                            "result": result,
+                            "error": error,
+                        }),
+                    )
+                });
+            }
+            Operation::CompleteWorkflow {
+                flow_session_id,
+                status,
+                trigger_status: _,
+            } => {
+                let session = match self.sessions.lock().unwrap().remove(flow_session_id) {
+                    Some(session) => session,
+                    None => return,
+                };
+
+                // Sessions are always completed as a whole, a failed task is what makes the workflow fail
+                let failed = session.failed || matches!(status, FlowSessionStatus::Failed);
+                let event_type = if failed {
+                    PlatformEventType::WorkflowFailed
+                } else {
+                    PlatformEventType::WorkflowCompleted
+                };
+
+                let chain_depth = self.chain_depth(flow_session_id, true);
+                self.publish(PlatformEvent {
+                    flow_id: Some(session.flow_id),
+                    flow_session_id: Some(*flow_session_id),
+                    chain_depth,
+                    ..PlatformEvent::new(
+                        event_type,
+                        session.account_id,
+                        json!({
+                            "status": if failed { "failed" } else { "completed" },
+                        }),
+                    )
+                });
+            }
+            _ => {}
+        }
+    }
+
+    fn chain_depth(&self, flow_session_id: &Uuid, finished: bool) -> u32 {
+        let mut chain_depths = self.chain_depths.lock().unwrap();
+        let chain_depth = if finished {
+            chain_depths.remove(flow_session_id)
+        } else {
+            chain_depths.get(flow_session_id).copied()
+        };
+        chain_depth.map_or(0, |(chain_depth, _)| chain_depth)
+    }
+}
+
+// Periodic cleanup task
+pub async fn cleanup_platform_event_sessions(state: Arc<AppState>) {
+    let cleanup_interval = Duration::from_secs(3600); // Run cleanup every hour
+    loop {
+        tokio::time::sleep(cleanup_interval).await;
+        state.platform_events.cleanup();
+    }
+}
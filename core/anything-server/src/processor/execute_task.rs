@@ -4,6 +4,7 @@ use std::time::Instant;
 use postgrest::Postgrest;
 
 use crate::bundler::bundle_tasks_cached_context;
+use crate::bundler::templates::templates_cache::CompiledWorkflowTemplates;
 use crate::processor::process_trigger_utils::process_trigger_task;
 use crate::system_plugins::formatter_actions::{
     date_formatter::process_date_task, text_formatter::process_text_task,
@@ -28,7 +29,12 @@ pub struct TaskError {
 
 pub type TaskResult = Result<(Option<Value>, Value, DateTime<Utc>, DateTime<Utc>), TaskError>;
 
-pub async fn execute_task(state: Arc<AppState>, client: &Postgrest, task: &Task) -> TaskResult {
+pub async fn execute_task(
+    state: Arc<AppState>,
+    client: &Postgrest,
+    task: &Task,
+    templates: &CompiledWorkflowTemplates,
+) -> TaskResult {
 
     let started_at = Utc::now();
     println!("[PROCESS TASK] Processing task {}", task.task_id);
@@ -38,9 +44,7 @@ pub async fn execute_task(state: Arc<AppState>, client: &Postgrest, task: &Task)
 
     // Bundle context with results from cache
     let bundled_context_result: Result<(Value, Value), Box<dyn std::error::Error + Send + Sync>> =
-        bundle_tasks_cached_context(state, client, task, true).await;
-
-    let http_client = state_clone.http_client.clone();
+        bundle_tasks_cached_context(state, client, task, templates, true).await;
 
     match bundled_context_result {
         Ok((bundled_inputs, bundled_plugin_cofig)) => {
@@ -55,7 +59,12 @@ pub async fn execute_task(state: Arc<AppState>, client: &Postgrest, task: &Task)
                         let plugin_start = Instant::now();
                         let result = match plugin_name.as_str() {
                             "@anything/http" => {
-                                process_http_task(&http_client, &bundled_plugin_cofig).await
+                                process_http_task(
+                                    &state_clone,
+                                    &task.account_id.to_string(),
+                                    &bundled_plugin_cofig,
+                                )
+                                .await
                             }
                             "@anything/filter" => {
                                 process_filter_task(&bundled_inputs, &bundled_plugin_cofig).await
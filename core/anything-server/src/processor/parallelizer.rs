@@ -3,6 +3,8 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::bundler::templates::get_compiled_workflow_templates;
+use crate::bundler::templates::templates_cache::CompiledWorkflowTemplates;
 use crate::processor::flow_session_cache::FlowSessionData;
 use crate::processor::processor::ProcessorMessage;
 use crate::processor::processor_utils::create_task;
@@ -22,6 +24,7 @@ pub struct ProcessingContext {
     pub trigger_session_id: Uuid,
     pub workflow: Arc<DatabaseFlowVersion>,
     pub workflow_def: Arc<WorkflowVersionDefinition>,
+    pub templates: Arc<CompiledWorkflowTemplates>,
 }
 
 pub async fn process_workflow(
@@ -44,6 +47,9 @@ pub async fn process_workflow(
         cache.set(&processor_message.flow_session_id, flow_session_data);
     }
 
+    let templates =
+        get_compiled_workflow_templates(&state, &processor_message.workflow_version).await;
+
     let ctx = ProcessingContext {
         state: state.clone(),
         client,
@@ -53,6 +59,7 @@ pub async fn process_workflow(
         trigger_session_id: processor_message.trigger_session_id,
         workflow: Arc::new(processor_message.workflow_version.clone()),
         workflow_def: Arc::new(processor_message.workflow_version.flow_definition.clone()),
+        templates,
     };
 
     if let Some(task) = processor_message.trigger_task {
@@ -27,6 +27,7 @@ pub fn process_task_and_branches(
                         .send(StatusUpdateMessage {
                             operation: Operation::UpdateTask {
                                 task_id: current_task.task_id,
+                                flow_session_id: current_task.flow_session_id,
                                 started_at: None,
                                 ended_at: Some(chrono::Utc::now()),
                                 status: TaskStatus::Failed,
@@ -1,3 +1,4 @@
+use crate::files::task_results::{offload_task_context_if_large, offload_task_result_if_large};
 use crate::processor::execute_task::execute_task;
 use crate::status_updater::{Operation, StatusUpdateMessage};
 
@@ -272,6 +273,10 @@ pub async fn update_completed_task_with_result(
     started_at: DateTime<Utc>,
     ended_at: DateTime<Utc>,
 ) {
+    // Large results (binary HTTP bodies etc) go to R2 so rows and the session cache stay small
+    let task_result = offload_task_result_if_large(&ctx.state, task, task_result).await;
+    let bundled_context = offload_task_context_if_large(&ctx.state, task, bundled_context).await;
+
     // Update cache immediately
     let mut cache = ctx.state.flow_session_cache.write().await;
     let mut task_copy = task.clone();
@@ -285,6 +290,7 @@ pub async fn update_completed_task_with_result(
     let task_message = StatusUpdateMessage {
         operation: Operation::UpdateTask {
             task_id: task.task_id.clone(),
+            flow_session_id: task.flow_session_id,
             status: TaskStatus::Completed,
             result: task_result.clone(),
             error: None,
@@ -320,6 +326,7 @@ pub async fn handle_task_error(
     let error_message = StatusUpdateMessage {
         operation: Operation::UpdateTask {
             task_id: task.task_id.clone(),
+            flow_session_id: task.flow_session_id,
             status: TaskStatus::Failed,
             result: None,
             error: Some(error.error.clone()),
@@ -346,8 +353,26 @@ pub async fn process_task(
     );
 
     let started_at = Utc::now();
+
+    let started_message = StatusUpdateMessage {
+        operation: Operation::UpdateTask {
+            task_id: task.task_id.clone(),
+            flow_session_id: task.flow_session_id,
+            status: TaskStatus::Running,
+            result: None,
+            error: None,
+            context: None,
+            started_at: Some(started_at),
+            ended_at: None,
+        },
+    };
+
+    if let Err(e) = ctx.state.task_updater_sender.send(started_message).await {
+        println!("[PROCESSOR] Failed to send task started update: {}", e);
+    }
+
     let (task_result, bundled_context, _, ended_at) =
-        match execute_task(ctx.state.clone(), &ctx.client, task).await {
+        match execute_task(ctx.state.clone(), &ctx.client, task, &ctx.templates).await {
             Ok(success_value) => success_value,
             Err(error) => {
                 handle_task_error(ctx, task, error.clone(), started_at, Utc::now()).await;
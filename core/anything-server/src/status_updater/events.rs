@@ -0,0 +1,95 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::status_updater::Operation;
+use crate::types::task_types::TaskStatus;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FlowSessionEventType {
+    TaskCreated,
+    TaskStarted,
+    TaskCompleted,
+    TaskFailed,
+    TaskUpdated,
+    SessionCompleted,
+}
+
+impl FlowSessionEventType {
+    pub fn as_str(&self) -> &str {
+        match self {
+            FlowSessionEventType::TaskCreated => "task_created",
+            FlowSessionEventType::TaskStarted => "task_started",
+            FlowSessionEventType::TaskCompleted => "task_completed",
+            FlowSessionEventType::TaskFailed => "task_failed",
+            FlowSessionEventType::TaskUpdated => "task_updated",
+            FlowSessionEventType::SessionCompleted => "session_completed",
+        }
+    }
+}
+
+// Progress of a single flow session, broadcast to anyone watching it live
+#[derive(Debug, Clone, Serialize)]
+pub struct FlowSessionEvent {
+    pub flow_session_id: Uuid,
+    pub event_type: FlowSessionEventType,
+    pub data: Value,
+}
+
+impl FlowSessionEvent {
+    pub fn from_operation(operation: &Operation) -> Self {
+        match operation {
+            Operation::CreateTask { task_id: _, input } => FlowSessionEvent {
+                flow_session_id: input.flow_session_id,
+                event_type: FlowSessionEventType::TaskCreated,
+                data: json!({
+                    "task_id": input.task_id,
+                    "action_id": input.action_id,
+                    "action_label": input.action_label,
+                    "processing_order": input.processing_order,
+                    "task_status": input.task_status,
+                    "result": input.result,
+                }),
+            },
+            Operation::UpdateTask {
+                task_id,
+                flow_session_id,
+                started_at,
+                ended_at,
+                status,
+                result,
+                context: _, // Context can hold headers and secrets, never stream it
+                error,
+            } => FlowSessionEvent {
+                flow_session_id: *flow_session_id,
+                event_type: match status {
+                    TaskStatus::Running => FlowSessionEventType::TaskStarted,
+                    TaskStatus::Completed => FlowSessionEventType::TaskCompleted,
+                    TaskStatus::Failed => FlowSessionEventType::TaskFailed,
+                    _ => FlowSessionEventType::TaskUpdated,
+                },
+                data: json!({
+                    "task_id": task_id,
+                    "task_status": status,
+                    "started_at": started_at,
+                    "ended_at": ended_at,
+                    "result": result,
+                    "error": error,
+                }),
+            },
+            Operation::CompleteWorkflow {
+                flow_session_id,
+                status,
+                trigger_status,
+            } => FlowSessionEvent {
+                flow_session_id: *flow_session_id,
+                event_type: FlowSessionEventType::SessionCompleted,
+                data: json!({
+                    "flow_session_status": status,
+                    "trigger_session_status": trigger_status,
+                }),
+            },
+        }
+    }
+}
@@ -1,5 +1,8 @@
+pub mod events;
+
 use crate::processor::db_calls::{create_task, update_flow_session_status, update_task_status};
 use crate::types::task_types::{FlowSessionStatus, Task, TaskStatus, TriggerSessionStatus};
+use crate::status_updater::events::FlowSessionEvent;
 use crate::AppState;
 use chrono::{DateTime, Utc};
 use serde_json::Value;
@@ -13,6 +16,7 @@ use uuid::Uuid;
 pub enum Operation {
     UpdateTask {
         task_id: Uuid,
+        flow_session_id: Uuid,
         started_at: Option<DateTime<Utc>>,
         ended_at: Option<DateTime<Utc>>,
         status: TaskStatus,
@@ -64,6 +68,14 @@ pub async fn task_database_status_processor(
                     message.operation
                 );
 
+                // Let live session watchers know right away. No receivers is fine.
+                let _ = state
+                    .flow_session_events
+                    .send(FlowSessionEvent::from_operation(&message.operation));
+
+                // Workflows listening for platform events hear about completions and failures
+                state.platform_events.record_operation(&message.operation);
+
                 let mut retries = 0;
                 let mut last_error = None;
 
@@ -72,6 +84,7 @@ pub async fn task_database_status_processor(
                     let result = match &message.operation {
                         Operation::UpdateTask {
                             task_id,
+                            flow_session_id: _,
                             started_at,
                             ended_at,
                             status,
@@ -0,0 +1,667 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use md5::Md5;
+use reqwest::header::{HeaderName, HeaderValue, AUTHORIZATION, WWW_AUTHENTICATE};
+use reqwest::{Client, Request, Response, StatusCode, Url};
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::auth::init::AccountAuthProviderAccount;
+use crate::auth::refresh::force_refresh_account;
+use crate::auth::utils::generate_random_string;
+use crate::bundler::accounts::fetch_cached_auth_accounts;
+use crate::AppState;
+
+// The `auth` section of an HTTP action. Secrets and accounts come in through the inputs, e.g.
+// { "type": "oauth2", "account": "{{inputs.google}}" } with the input set to {{accounts.google}}.
+// OAuth2 only takes the account's slug or id from it, the account is loaded for the task's account.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HttpAuth {
+    None,
+    Basic {
+        username: String,
+        #[serde(default)]
+        password: String,
+    },
+    Bearer {
+        token: String,
+    },
+    ApiKey {
+        name: String,
+        value: String,
+        #[serde(default)]
+        location: ApiKeyLocation,
+    },
+    #[serde(rename = "oauth2")]
+    OAuth2 {
+        #[serde(deserialize_with = "account_reference")]
+        account: String,
+    },
+    #[serde(rename = "aws_sigv4")]
+    AwsSigV4(AwsSigV4Config),
+    Digest {
+        username: String,
+        password: String,
+    },
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiKeyLocation {
+    #[default]
+    Header,
+    Query,
+}
+
+#[derive(Deserialize)]
+pub struct AwsSigV4Config {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    #[serde(default)]
+    pub session_token: Option<String>,
+    pub region: String,
+    pub service: String,
+}
+
+// A slug or id, or a rendered account of which only the id is kept
+fn account_reference<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    let reference = match Value::deserialize(deserializer)? {
+        Value::String(reference) => Some(reference),
+        Value::Object(account) => account
+            .get("account_auth_provider_account_id")
+            .or_else(|| account.get("account_auth_provider_account_slug"))
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        _ => None,
+    };
+
+    reference
+        .map(|reference| reference.trim().to_string())
+        .filter(|reference| !reference.is_empty())
+        .ok_or_else(|| serde::de::Error::custom("oauth2 account must be an account slug or id"))
+}
+
+// Tokens are only ever read and refreshed from the task's own account's records
+async fn load_account(
+    state: &Arc<AppState>,
+    account_id: &str,
+    reference: &str,
+) -> Result<AccountAuthProviderAccount, Box<dyn std::error::Error + Send + Sync>> {
+    fetch_cached_auth_accounts(state.clone(), &state.anything_client, account_id, true)
+        .await?
+        .into_iter()
+        .find(|account| {
+            account.account_auth_provider_account_slug == reference
+                || account.account_auth_provider_account_id.to_string() == reference
+        })
+        .ok_or_else(|| format!("No connected account '{}'", reference).into())
+}
+
+impl HttpAuth {
+    fn kind(&self) -> &'static str {
+        match self {
+            HttpAuth::None => "none",
+            HttpAuth::Basic { .. } => "basic",
+            HttpAuth::Bearer { .. } => "bearer",
+            HttpAuth::ApiKey { .. } => "api_key",
+            HttpAuth::OAuth2 { .. } => "oauth2",
+            HttpAuth::AwsSigV4(_) => "aws_sigv4",
+            HttpAuth::Digest { .. } => "digest",
+        }
+    }
+}
+
+// Missing, empty or `{}` auth means none, a string holds the section as JSON
+pub fn parse_auth(
+    bundled_context: &Value,
+) -> Result<HttpAuth, Box<dyn std::error::Error + Send + Sync>> {
+    let auth = match bundled_context.get("auth") {
+        None | Some(Value::Null) => return Ok(HttpAuth::None),
+        Some(Value::String(s)) if s.trim().is_empty() => return Ok(HttpAuth::None),
+        Some(Value::String(s)) => serde_json::from_str::<Value>(s)
+            .map_err(|e| format!("HTTP auth is not valid JSON: {}", e))?,
+        Some(auth) => auth.clone(),
+    };
+
+    if auth.as_object().is_some_and(|auth| auth.is_empty()) {
+        return Ok(HttpAuth::None);
+    }
+
+    serde_json::from_value(auth).map_err(|e| format!("Invalid HTTP auth settings: {}", e).into())
+}
+
+// Sends the request with the auth applied. Digest answers the server's 401 challenge and OAuth2
+// refreshes the account's token and retries once on 401.
+pub async fn send_with_auth(
+    state: &Arc<AppState>,
+    account_id: &str,
+    http_client: &Client,
+    mut request: Request,
+    auth: HttpAuth,
+) -> Result<Response, Box<dyn std::error::Error + Send + Sync>> {
+    println!("[TASK_ENGINE] Using {} auth", auth.kind());
+
+    match auth {
+        HttpAuth::None => {}
+        HttpAuth::Basic { username, password } => {
+            let credentials = STANDARD.encode(format!("{}:{}", username, password));
+            set_authorization(&mut request, &format!("Basic {}", credentials))?;
+        }
+        HttpAuth::Bearer { token } => {
+            set_authorization(&mut request, &format!("Bearer {}", token))?;
+        }
+        HttpAuth::ApiKey {
+            name,
+            value,
+            location,
+        } => match location {
+            ApiKeyLocation::Header => {
+                let name = HeaderName::from_bytes(name.as_bytes())
+                    .map_err(|_| format!("Invalid API key header name '{}'", name))?;
+                request.headers_mut().insert(name, header_value(&value)?);
+            }
+            ApiKeyLocation::Query => {
+                request
+                    .url_mut()
+                    .query_pairs_mut()
+                    .append_pair(&name, &value);
+            }
+        },
+        HttpAuth::OAuth2 { account } => {
+            let account = load_account(state, account_id, &account).await?;
+            set_authorization(&mut request, &format!("Bearer {}", account.access_token))?;
+            let retry = request.try_clone();
+
+            let response = http_client.execute(request).await?;
+            let mut retry = match retry {
+                Some(retry) if response.status() == StatusCode::UNAUTHORIZED => retry,
+                _ => return Ok(response),
+            };
+
+            println!(
+                "[TASK_ENGINE] Got 401, refreshing token for account: {}",
+                account.account_auth_provider_account_slug
+            );
+            let account = force_refresh_account(state, &state.anything_client, account).await?;
+            set_authorization(&mut retry, &format!("Bearer {}", account.access_token))?;
+            return Ok(http_client.execute(retry).await?);
+        }
+        HttpAuth::AwsSigV4(config) => sign_aws_sigv4(&mut request, &config)?,
+        HttpAuth::Digest { username, password } => {
+            let retry = request.try_clone();
+            let response = http_client.execute(request).await?;
+            let mut retry = match retry {
+                Some(retry) if response.status() == StatusCode::UNAUTHORIZED => retry,
+                _ => return Ok(response),
+            };
+
+            let challenge = match response
+                .headers()
+                .get_all(WWW_AUTHENTICATE)
+                .iter()
+                .filter_map(|header| header.to_str().ok())
+                .find_map(parse_digest_challenge)
+            {
+                Some(challenge) => challenge,
+                None => return Ok(response),
+            };
+
+            let url = retry.url();
+            let uri = match url.query() {
+                Some(query) => format!("{}?{}", url.path(), query),
+                None => url.path().to_string(),
+            };
+            let authorization = digest_authorization(
+                &challenge,
+                &username,
+                &password,
+                retry.method().as_str(),
+                &uri,
+                &generate_random_string(16),
+            )?;
+            set_authorization(&mut retry, &authorization)?;
+            return Ok(http_client.execute(retry).await?);
+        }
+    }
+
+    Ok(http_client.execute(request).await?)
+}
+
+fn header_value(value: &str) -> Result<HeaderValue, Box<dyn std::error::Error + Send + Sync>> {
+    Ok(HeaderValue::from_str(value).map_err(|_| "Invalid characters in HTTP auth value")?)
+}
+
+fn set_authorization(
+    request: &mut Request,
+    value: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    request
+        .headers_mut()
+        .insert(AUTHORIZATION, header_value(value)?);
+    Ok(())
+}
+
+fn hex_digest<D: Digest>(data: &[u8]) -> String {
+    hex::encode(D::digest(data))
+}
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+// Where the Digest challenge's parameters start, servers may offer several schemes in one header
+fn digest_challenge_params(header: &str) -> Option<&str> {
+    let mut in_quotes = false;
+    let mut at_token = true;
+    for (index, c) in header.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => at_token = true,
+            c if c.is_whitespace() => {}
+            _ if at_token && !in_quotes => {
+                at_token = false;
+                let token_end = header[index..]
+                    .find(char::is_whitespace)
+                    .map_or(header.len(), |end| index + end);
+                if header[index..token_end].eq_ignore_ascii_case("digest") {
+                    return Some(&header[token_end..]);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+// Parameters of a `WWW-Authenticate: Digest realm="...", nonce="...", ...` challenge
+fn parse_digest_challenge(header: &str) -> Option<HashMap<String, String>> {
+    let params = digest_challenge_params(header)?;
+
+    let mut challenge = HashMap::new();
+    let mut rest = params.trim();
+    while let Some((key, after)) = rest.split_once('=') {
+        let key = key.trim().trim_start_matches(',').trim().to_lowercase();
+        // The next scheme's name, e.g. `Basic realm=...` after the Digest parameters
+        if key.contains(char::is_whitespace) {
+            break;
+        }
+        let after = after.trim_start();
+        let (value, remaining) = match after.strip_prefix('"') {
+            Some(quoted) => {
+                let end = quoted.find('"')?;
+                (&quoted[..end], &quoted[end + 1..])
+            }
+            None => after.split_at(after.find(',').unwrap_or(after.len())),
+        };
+        challenge.insert(key, value.trim().to_string());
+        rest = remaining.trim_start_matches([',', ' ']);
+    }
+
+    Some(challenge)
+}
+
+// RFC 7616 response for qop=auth (or no qop) with MD5 or SHA-256, plain or -sess
+fn digest_authorization(
+    challenge: &HashMap<String, String>,
+    username: &str,
+    password: &str,
+    method: &str,
+    uri: &str,
+    cnonce: &str,
+) -> Result<String, String> {
+    let realm = challenge.get("realm").map(String::as_str).unwrap_or("");
+    let nonce = challenge
+        .get("nonce")
+        .ok_or("Digest challenge without a nonce")?;
+    let algorithm = challenge
+        .get("algorithm")
+        .map(String::as_str)
+        .unwrap_or("MD5");
+
+    let hash: fn(&[u8]) -> String = match algorithm.to_uppercase().trim_end_matches("-SESS") {
+        "MD5" => hex_digest::<Md5>,
+        "SHA-256" => hex_digest::<Sha256>,
+        other => return Err(format!("Unsupported digest algorithm '{}'", other)),
+    };
+    let qop = match challenge.get("qop") {
+        None => None,
+        Some(qop)
+            if qop
+                .split(',')
+                .any(|q| q.trim().eq_ignore_ascii_case("auth")) =>
+        {
+            Some("auth")
+        }
+        Some(qop) => return Err(format!("Unsupported digest qop '{}'", qop)),
+    };
+    let nc = "00000001";
+
+    let mut ha1 = hash(format!("{}:{}:{}", username, realm, password).as_bytes());
+    if algorithm.to_uppercase().ends_with("-SESS") {
+        ha1 = hash(format!("{}:{}:{}", ha1, nonce, cnonce).as_bytes());
+    }
+    let ha2 = hash(format!("{}:{}", method, uri).as_bytes());
+    let response = match qop {
+        Some(qop) => {
+            hash(format!("{}:{}:{}:{}:{}:{}", ha1, nonce, nc, cnonce, qop, ha2).as_bytes())
+        }
+        None => hash(format!("{}:{}:{}", ha1, nonce, ha2).as_bytes()),
+    };
+
+    let mut authorization = format!(
+        "Digest username=\"{}\", realm=\"{}\", nonce=\"{}\", uri=\"{}\", algorithm={}, response=\"{}\"",
+        username, realm, nonce, uri, algorithm, response
+    );
+    if let Some(qop) = qop {
+        authorization.push_str(&format!(", qop={}, nc={}, cnonce=\"{}\"", qop, nc, cnonce));
+    }
+    if let Some(opaque) = challenge.get("opaque") {
+        authorization.push_str(&format!(", opaque=\"{}\"", opaque));
+    }
+    Ok(authorization)
+}
+
+fn sign_aws_sigv4(
+    request: &mut Request,
+    config: &AwsSigV4Config,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let amz_date = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let payload_hash = match request.body() {
+        None => hex_digest::<Sha256>(b""),
+        Some(body) => body
+            .as_bytes()
+            .map(hex_digest::<Sha256>)
+            .unwrap_or_else(|| "UNSIGNED-PAYLOAD".to_string()),
+    };
+
+    // reqwest only adds Host when sending, it has to be signed so set it here
+    let url = request.url();
+    let host = match (url.host_str(), url.port()) {
+        (Some(host), Some(port)) => format!("{}:{}", host, port),
+        (Some(host), None) => host.to_string(),
+        (None, _) => return Err("AWS SigV4 needs a URL with a host".into()),
+    };
+
+    let headers = request.headers_mut();
+    headers.insert("host", header_value(&host)?);
+    headers.insert("x-amz-date", header_value(&amz_date)?);
+    headers.insert("x-amz-content-sha256", header_value(&payload_hash)?);
+    if let Some(session_token) = config.session_token.as_deref().filter(|t| !t.is_empty()) {
+        headers.insert("x-amz-security-token", header_value(session_token)?);
+    }
+
+    let signed_headers = request
+        .headers()
+        .iter()
+        .filter_map(|(name, value)| {
+            Some((name.as_str().to_string(), value.to_str().ok()?.to_string()))
+        })
+        .collect::<Vec<_>>();
+
+    let authorization = aws_sigv4_authorization(
+        config,
+        request.method().as_str(),
+        request.url(),
+        &signed_headers,
+        &payload_hash,
+        &amz_date,
+    );
+    set_authorization(request, &authorization)
+}
+
+// AWS URI encoding, everything but unreserved characters is percent-encoded
+fn aws_uri_encode(value: &str) -> String {
+    urlencoding::encode(value).into_owned()
+}
+
+// Built from the raw query since AWS reads `+` as a plus, where form decoding would make it a space
+fn aws_canonical_query(url: &Url) -> String {
+    let decode = |part: &str| {
+        urlencoding::decode(part)
+            .map(|decoded| decoded.into_owned())
+            .unwrap_or_else(|_| part.to_string())
+    };
+
+    let mut query = url
+        .query()
+        .unwrap_or("")
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (aws_uri_encode(&decode(key)), aws_uri_encode(&decode(value)))
+        })
+        .collect::<Vec<_>>();
+    query.sort();
+    query
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+fn aws_sigv4_authorization(
+    config: &AwsSigV4Config,
+    method: &str,
+    url: &Url,
+    headers: &[(String, String)],
+    payload_hash: &str,
+    amz_date: &str,
+) -> String {
+    // The path is already encoded once, every service but S3 expects it encoded twice
+    let canonical_uri = match url.path() {
+        "" => "/".to_string(),
+        path if config.service == "s3" => path.to_string(),
+        path => path
+            .split('/')
+            .map(aws_uri_encode)
+            .collect::<Vec<_>>()
+            .join("/"),
+    };
+
+    let canonical_query = aws_canonical_query(url);
+
+    let mut canonical_headers: Vec<(String, String)> = Vec::new();
+    let mut sorted_headers = headers.to_vec();
+    sorted_headers.sort_by(|a, b| a.0.cmp(&b.0));
+    for (name, value) in sorted_headers {
+        let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+        match canonical_headers.last_mut() {
+            Some((last, values)) if *last == name => {
+                values.push(',');
+                values.push_str(&value);
+            }
+            _ => canonical_headers.push((name, value)),
+        }
+    }
+    let signed_headers = canonical_headers
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>()
+        .join(";");
+
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{}\n\n{}\n{}",
+        method,
+        canonical_uri,
+        canonical_query,
+        canonical_headers
+            .iter()
+            .map(|(name, value)| format!("{}:{}", name, value))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        signed_headers,
+        payload_hash
+    );
+
+    let date = &amz_date[..8];
+    let scope = format!("{}/{}/{}/aws4_request", date, config.region, config.service);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex_digest::<Sha256>(canonical_request.as_bytes())
+    );
+
+    let date_key = hmac_sha256(format!("AWS4{}", config.secret_access_key).as_bytes(), date);
+    let region_key = hmac_sha256(&date_key, &config.region);
+    let service_key = hmac_sha256(&region_key, &config.service);
+    let signing_key = hmac_sha256(&service_key, "aws4_request");
+    let signature = hex::encode(hmac_sha256(&signing_key, &string_to_sign));
+
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        config.access_key_id, scope, signed_headers, signature
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // From the AWS SigV4 test suite
+    const EMPTY_PAYLOAD_HASH: &str =
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+    fn aws_test_config() -> AwsSigV4Config {
+        serde_json::from_value(serde_json::json!({
+            "access_key_id": "AKIDEXAMPLE",
+            "secret_access_key": "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            "region": "us-east-1",
+            "service": "service",
+        }))
+        .unwrap()
+    }
+
+    fn aws_test_signature(url: &str) -> String {
+        let headers = vec![
+            ("host".to_string(), "example.amazonaws.com".to_string()),
+            ("x-amz-date".to_string(), "20150830T123600Z".to_string()),
+        ];
+        aws_sigv4_authorization(
+            &aws_test_config(),
+            "GET",
+            &Url::parse(url).unwrap(),
+            &headers,
+            EMPTY_PAYLOAD_HASH,
+            "20150830T123600Z",
+        )
+    }
+
+    #[test]
+    fn test_oauth2_takes_only_an_account_reference() {
+        let account = |auth: Value| match parse_auth(&serde_json::json!({ "auth": auth })) {
+            Ok(HttpAuth::OAuth2 { account }) => Ok(account),
+            Ok(_) => panic!("expected oauth2"),
+            Err(e) => Err(e),
+        };
+
+        assert_eq!(
+            account(serde_json::json!({ "type": "oauth2", "account": " google " })).unwrap(),
+            "google"
+        );
+        // A rendered account only contributes its id, its tokens and vault ids are ignored
+        assert_eq!(
+            account(serde_json::json!({
+                "type": "oauth2",
+                "account": {
+                    "account_auth_provider_account_id": "5f0c8d2e-0000-4000-8000-000000000000",
+                    "account_auth_provider_account_slug": "google",
+                    "access_token_vault_id": "someone-elses",
+                    "auth_provider": { "token_url": "https://evil.example.com" },
+                },
+            }))
+            .unwrap(),
+            "5f0c8d2e-0000-4000-8000-000000000000"
+        );
+        assert!(account(serde_json::json!({ "type": "oauth2", "account": "" })).is_err());
+        assert!(account(serde_json::json!({ "type": "oauth2", "account": 42 })).is_err());
+    }
+
+    #[test]
+    fn test_aws_sigv4_vectors() {
+        // get-vanilla
+        assert_eq!(
+            aws_test_signature("https://example.amazonaws.com/"),
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+             SignedHeaders=host;x-amz-date, \
+             Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+        // get-vanilla-query-order-key-case
+        assert!(
+            aws_test_signature("https://example.amazonaws.com/?Param2=value2&Param1=value1")
+                .ends_with(
+                    "Signature=b97d918cfa904a5beff61c982a1b6f458b799221646efd99d3219ec94cdf2500"
+                )
+        );
+    }
+
+    #[test]
+    fn test_aws_canonical_query() {
+        let query = |url: &str| aws_canonical_query(&Url::parse(url).unwrap());
+        assert_eq!(
+            query("https://example.com/?b=2&a=x+y&c=hello%20world"),
+            "a=x%2By&b=2&c=hello%20world"
+        );
+        assert_eq!(query("https://example.com/?key&a=%7E~"), "a=~~&key=");
+        assert_eq!(query("https://example.com/"), "");
+    }
+
+    const RFC_7616_NONCE: &str = "7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v";
+    const RFC_7616_OPAQUE: &str = "FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS";
+    const RFC_7616_CNONCE: &str = "f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ";
+
+    fn rfc_7616_response(algorithm: &str) -> String {
+        let header = format!(
+            "Digest realm=\"http-auth@example.org\", qop=\"auth, auth-int\", algorithm={}, \
+             nonce=\"{}\", opaque=\"{}\"",
+            algorithm, RFC_7616_NONCE, RFC_7616_OPAQUE
+        );
+        let challenge = parse_digest_challenge(&header).unwrap();
+        digest_authorization(
+            &challenge,
+            "Mufasa",
+            "Circle of Life",
+            "GET",
+            "/dir/index.html",
+            RFC_7616_CNONCE,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_digest_rfc_7616_vectors() {
+        let md5 = rfc_7616_response("MD5");
+        assert!(md5.contains("response=\"8ca523f5e9506fed4657c9700eebdbec\""));
+        assert!(md5.contains("qop=auth, nc=00000001"));
+        assert!(md5.contains(&format!("opaque=\"{}\"", RFC_7616_OPAQUE)));
+
+        let sha256 = rfc_7616_response("SHA-256");
+        assert!(sha256.contains(
+            "response=\"753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1\""
+        ));
+    }
+
+    #[test]
+    fn test_parse_digest_challenge_among_other_schemes() {
+        let challenge = parse_digest_challenge(
+            "Basic realm=\"Digest area\", Digest realm=\"api\", nonce=\"abc\", qop=\"auth\", \
+             Bearer realm=\"other\"",
+        )
+        .unwrap();
+        assert_eq!(challenge["realm"], "api");
+        assert_eq!(challenge["nonce"], "abc");
+        assert_eq!(challenge["qop"], "auth");
+        assert_eq!(challenge.len(), 3);
+
+        assert!(parse_digest_challenge("Basic realm=\"api\"").is_none());
+    }
+}
@@ -0,0 +1,238 @@
+use reqwest::{redirect, Certificate, Client, Proxy};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::Duration;
+
+// Clients are cheap to clone but each keeps its own connection pool, so keep the number bounded
+const MAX_CACHED_CLIENTS: usize = 100;
+
+const DEFAULT_MAX_REDIRECTS: usize = 10;
+
+// Settings that need their own client, everything else is set per request
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct HttpClientOptions {
+    pub follow_redirects: bool,
+    pub max_redirects: usize,
+    pub allow_invalid_certificates: bool,
+    pub ca_certificate: Option<String>,
+    pub proxy_url: Option<String>,
+}
+
+impl Default for HttpClientOptions {
+    fn default() -> Self {
+        HttpClientOptions {
+            follow_redirects: true,
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+            allow_invalid_certificates: false,
+            ca_certificate: None,
+            proxy_url: None,
+        }
+    }
+}
+
+impl HttpClientOptions {
+    // Missing or empty options keep the shared client's defaults
+    pub fn from_config(config: &Value) -> Self {
+        let defaults = HttpClientOptions::default();
+        HttpClientOptions {
+            follow_redirects: config_bool(config, "follow_redirects")
+                .unwrap_or(defaults.follow_redirects),
+            max_redirects: config_u64(config, "max_redirects")
+                .map(|max| max as usize)
+                .unwrap_or(defaults.max_redirects),
+            allow_invalid_certificates: config_bool(config, "allow_invalid_certificates")
+                .unwrap_or(defaults.allow_invalid_certificates),
+            ca_certificate: config_text(config, "ca_certificate"),
+            proxy_url: config_text(config, "proxy_url"),
+        }
+    }
+
+    pub fn is_default(&self) -> bool {
+        *self == HttpClientOptions::default()
+    }
+
+    fn build_client(&self) -> Result<Client, Box<dyn std::error::Error + Send + Sync>> {
+        let redirect_policy = if self.follow_redirects {
+            redirect::Policy::limited(self.max_redirects)
+        } else {
+            redirect::Policy::none()
+        };
+
+        let mut builder = Client::builder()
+            .redirect(redirect_policy)
+            .danger_accept_invalid_certs(self.allow_invalid_certificates);
+
+        if let Some(ca_certificate) = &self.ca_certificate {
+            let certificate = Certificate::from_pem(ca_certificate.as_bytes())
+                .map_err(|e| format!("Invalid CA certificate: {}", e))?;
+            builder = builder.add_root_certificate(certificate);
+        }
+
+        if let Some(proxy_url) = &self.proxy_url {
+            let proxy = Proxy::all(proxy_url).map_err(|e| format!("Invalid proxy URL: {}", e))?;
+            builder = builder.proxy(proxy);
+        }
+
+        Ok(builder.build()?)
+    }
+}
+
+// Per request timeout, None keeps the client's default of no timeout
+pub fn request_timeout(
+    config: &Value,
+) -> Result<Option<Duration>, Box<dyn std::error::Error + Send + Sync>> {
+    let seconds = config
+        .get("timeout_seconds")
+        .and_then(|timeout| match timeout {
+            Value::String(s) => s.trim().parse::<f64>().ok(),
+            other => other.as_f64(),
+        })
+        .filter(|seconds| *seconds > 0.0);
+
+    match seconds {
+        Some(seconds) => Duration::try_from_secs_f64(seconds)
+            .map(Some)
+            .map_err(|_| format!("Invalid timeout_seconds: {}", seconds).into()),
+        None => Ok(None),
+    }
+}
+
+fn config_bool(config: &Value, key: &str) -> Option<bool> {
+    match config.get(key)? {
+        Value::Bool(b) => Some(*b),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn config_u64(config: &Value, key: &str) -> Option<u64> {
+    match config.get(key)? {
+        Value::String(s) => s.trim().parse().ok(),
+        other => other.as_u64(),
+    }
+}
+
+fn config_text(config: &Value, key: &str) -> Option<String> {
+    config
+        .get(key)
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+}
+
+#[derive(Default)]
+pub struct HttpClientCache {
+    clients: HashMap<HttpClientOptions, Client>,
+}
+
+impl HttpClientCache {
+    pub fn new() -> Self {
+        Self {
+            clients: HashMap::new(),
+        }
+    }
+
+    pub fn get(&self, options: &HttpClientOptions) -> Option<Client> {
+        self.clients.get(options).cloned()
+    }
+
+    pub fn get_or_build(
+        &mut self,
+        options: &HttpClientOptions,
+    ) -> Result<Client, Box<dyn std::error::Error + Send + Sync>> {
+        if let Some(client) = self.clients.get(options) {
+            return Ok(client.clone());
+        }
+
+        if self.clients.len() >= MAX_CACHED_CLIENTS {
+            println!("[TASK_ENGINE] HTTP client cache is full, clearing it");
+            self.clients.clear();
+        }
+
+        println!(
+            "[TASK_ENGINE] Building HTTP client for options: follow_redirects={}, max_redirects={}, allow_invalid_certificates={}, custom_ca={}, proxy={}",
+            options.follow_redirects,
+            options.max_redirects,
+            options.allow_invalid_certificates,
+            options.ca_certificate.is_some(),
+            options.proxy_url.is_some()
+        );
+        let client = options.build_client()?;
+        self.clients.insert(options.clone(), client.clone());
+        Ok(client)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_options_from_config() {
+        let options = HttpClientOptions::from_config(&json!({
+            "follow_redirects": "false",
+            "max_redirects": "3",
+            "allow_invalid_certificates": true,
+            "proxy_url": " http://proxy:8080 ",
+        }));
+        assert!(!options.follow_redirects);
+        assert_eq!(options.max_redirects, 3);
+        assert!(options.allow_invalid_certificates);
+        assert_eq!(options.proxy_url.as_deref(), Some("http://proxy:8080"));
+        assert!(!options.is_default());
+
+        // Empty and unparseable options keep the defaults
+        let options = HttpClientOptions::from_config(&json!({
+            "follow_redirects": "sometimes",
+            "ca_certificate": "",
+            "proxy_url": "  ",
+        }));
+        assert!(options.is_default());
+    }
+
+    #[test]
+    fn test_request_timeout() {
+        let timeout = |value| request_timeout(&json!({ "timeout_seconds": value }));
+        assert_eq!(
+            timeout(json!("2.5")).unwrap(),
+            Some(Duration::from_millis(2500))
+        );
+        assert_eq!(timeout(json!(10)).unwrap(), Some(Duration::from_secs(10)));
+        assert_eq!(timeout(json!(0)).unwrap(), None);
+        assert_eq!(timeout(json!("soon")).unwrap(), None);
+        assert!(timeout(json!("inf")).is_err());
+        assert!(timeout(json!(1e300)).is_err());
+    }
+
+    #[test]
+    fn test_cache_is_cleared_when_full() {
+        let mut cache = HttpClientCache::new();
+        let options = |max_redirects| HttpClientOptions {
+            max_redirects,
+            ..Default::default()
+        };
+
+        for max_redirects in 0..MAX_CACHED_CLIENTS {
+            cache.get_or_build(&options(max_redirects)).unwrap();
+        }
+        assert!(cache.get(&options(0)).is_some());
+
+        cache.get_or_build(&options(MAX_CACHED_CLIENTS)).unwrap();
+        assert!(cache.get(&options(0)).is_none());
+        assert!(cache.get(&options(MAX_CACHED_CLIENTS)).is_some());
+        assert_eq!(cache.clients.len(), 1);
+    }
+
+    #[test]
+    fn test_invalid_options_are_not_cached() {
+        let mut cache = HttpClientCache::new();
+        let options = HttpClientOptions {
+            ca_certificate: Some("not a certificate".to_string()),
+            ..Default::default()
+        };
+        assert!(cache.get_or_build(&options).is_err());
+        assert!(cache.get(&options).is_none());
+    }
+}
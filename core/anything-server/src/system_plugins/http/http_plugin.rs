@@ -1,8 +1,13 @@
 use reqwest::Client;
 use serde_json::Value;
 use std::collections::HashMap;
+use std::sync::Arc;
 use tokio::time::Instant;
 
+use crate::system_plugins::http::auth::{parse_auth, send_with_auth};
+use crate::system_plugins::http::client_cache::{request_timeout, HttpClientOptions};
+use crate::AppState;
+
 fn is_binary_content(content_type: &str) -> bool {
     let content_type = content_type.split(';').next().unwrap_or("").trim();
     content_type.starts_with("image/")
@@ -36,8 +41,30 @@ fn format_binary_response(content_type: &str, bytes: &[u8]) -> Value {
     }
 }
 
+// Redirect, TLS and proxy options need their own client, cached per option set
+async fn get_http_client(
+    state: &AppState,
+    bundled_context: &Value,
+) -> Result<Client, Box<dyn std::error::Error + Send + Sync>> {
+    let options = HttpClientOptions::from_config(bundled_context);
+    if options.is_default() {
+        return Ok(state.http_client.as_ref().clone());
+    }
+
+    {
+        let cache = state.http_client_cache.read().await;
+        if let Some(client) = cache.get(&options) {
+            return Ok(client);
+        }
+    }
+
+    let mut cache = state.http_client_cache.write().await;
+    cache.get_or_build(&options)
+}
+
 pub async fn process_http_task(
-    http_client: &Client,
+    state: &Arc<AppState>,
+    account_id: &str,
     bundled_context: &Value,
 ) -> Result<Option<Value>, Box<dyn std::error::Error + Send + Sync>> {
     let start = Instant::now();
@@ -66,8 +93,16 @@ pub async fn process_http_task(
             }
         };
 
+        let auth = parse_auth(bundled_context)?;
+        let http_client = get_http_client(state, bundled_context).await?;
         let mut request_builder = http_client.request(method.clone(), url);
 
+        let timeout = request_timeout(bundled_context)?;
+        if let Some(timeout) = timeout {
+            println!("[TASK_ENGINE] Using request timeout: {:?}", timeout);
+            request_builder = request_builder.timeout(timeout);
+        }
+
         println!("[TASK_ENGINE] Processing headers");
         let headers = parse_headers(bundled_context);
         for (key, value) in headers {
@@ -112,7 +147,19 @@ pub async fn process_http_task(
         println!("[TASK_ENGINE] Sending HTTP request");
         let request_start = Instant::now();
 
-        let response = request_builder.send().await?;
+        let request = request_builder.build()?;
+        let response = match send_with_auth(state, account_id, &http_client, request, auth).await {
+            Ok(response) => response,
+            Err(e) => match timeout {
+                Some(timeout)
+                    if e.downcast_ref::<reqwest::Error>()
+                        .is_some_and(reqwest::Error::is_timeout) =>
+                {
+                    return Err(format!("HTTP request timed out after {:?}", timeout).into())
+                }
+                _ => return Err(e),
+            },
+        };
         println!("[SPEED] HTTP request took {:?}", request_start.elapsed());
 
         let status = response.status();
@@ -1 +1,3 @@
+pub mod auth;
+pub mod client_cache;
 pub mod http_plugin;
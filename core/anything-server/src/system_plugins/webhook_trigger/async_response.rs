@@ -0,0 +1,460 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use dotenv::dotenv;
+use hmac::{Hmac, Mac};
+use ipnet::IpNet;
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::env;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::{sleep, timeout, Duration};
+use uuid::Uuid;
+
+use super::webhook_trigger_utils::server_trusted_proxies;
+use crate::status_updater::events::FlowSessionEventType;
+use crate::AppState;
+
+// Completion callbacks stop waiting after this long unless WEBHOOK_CALLBACK_WAIT_SECONDS says otherwise
+const DEFAULT_CALLBACK_WAIT_SECONDS: u64 = 60 * 60;
+
+// Session events fire before the status updater writes to the database
+const STATUS_SETTLE_RETRIES: u32 = 10;
+const STATUS_SETTLE_INTERVAL: Duration = Duration::from_millis(500);
+
+const CALLBACK_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+fn callback_wait_timeout() -> Duration {
+    let seconds = env::var("WEBHOOK_CALLBACK_WAIT_SECONDS")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .filter(|seconds| *seconds > 0)
+        .unwrap_or(DEFAULT_CALLBACK_WAIT_SECONDS);
+    Duration::from_secs(seconds)
+}
+
+// Set by the trigger's response mode or by the caller sending `Prefer: respond-async`
+pub fn wants_async_response(rendered_inputs: &Value, headers: &HeaderMap) -> bool {
+    let configured = rendered_inputs
+        .get("response_mode")
+        .and_then(Value::as_str)
+        .map_or(false, |mode| mode.trim().eq_ignore_ascii_case("async"));
+
+    let requested = headers
+        .get_all("prefer")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|preference| preference.trim().eq_ignore_ascii_case("respond-async"));
+
+    configured || requested
+}
+
+// Status URLs carry an HMAC of the session so only whoever got the 202 can poll it
+fn status_mac(secret: &str, workflow_id: &str, flow_session_id: &str) -> Hmac<Sha256> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC can take keys of any size");
+    mac.update(format!("webhook-session-status:{}:{}", workflow_id, flow_session_id).as_bytes());
+    mac
+}
+
+fn status_token(secret: &str, workflow_id: &str, flow_session_id: &str) -> String {
+    hex::encode(
+        status_mac(secret, workflow_id, flow_session_id)
+            .finalize()
+            .into_bytes(),
+    )
+}
+
+fn verify_status_token(
+    secret: &str,
+    workflow_id: &str,
+    flow_session_id: &str,
+    token: &str,
+) -> bool {
+    match hex::decode(token) {
+        // Constant time comparison
+        Ok(token) => status_mac(secret, workflow_id, flow_session_id)
+            .verify_slice(&token)
+            .is_ok(),
+        Err(_) => false,
+    }
+}
+
+fn status_token_secret() -> String {
+    dotenv().ok();
+    env::var("SUPABASE_JWT_SECRET").expect("SUPABASE_JWT_SECRET must be set")
+}
+
+// Where the API is reachable from outside, configured or from the headers set by a trusted proxy
+fn public_base_url(
+    configured_url: Option<&str>,
+    headers: &HeaderMap,
+    remote_addr: &SocketAddr,
+    trusted_proxies: &[IpNet],
+) -> Option<String> {
+    if let Some(base_url) = configured_url
+        .map(|url| url.trim().trim_end_matches('/'))
+        .filter(|url| !url.is_empty())
+    {
+        return Some(base_url.to_string());
+    }
+
+    // Anyone else could point the URL at their own host
+    let peer_ip = remote_addr.ip().to_canonical();
+    if !trusted_proxies.iter().any(|range| range.contains(&peer_ip)) {
+        return None;
+    }
+
+    let host = headers
+        .get("x-forwarded-host")
+        .or_else(|| headers.get(header::HOST))
+        .and_then(|value| value.to_str().ok())?;
+    let scheme = headers
+        .get("x-forwarded-proto")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("https");
+    Some(format!("{}://{}", scheme, host))
+}
+
+// Relative to the API unless its public address is known
+pub fn session_status_url(
+    headers: &HeaderMap,
+    remote_addr: &SocketAddr,
+    workflow_id: &str,
+    flow_session_id: Uuid,
+) -> String {
+    let token = status_token(
+        &status_token_secret(),
+        workflow_id,
+        &flow_session_id.to_string(),
+    );
+    let path = format!(
+        "/api/v1/workflow/{}/session/{}/status?token={}",
+        workflow_id, flow_session_id, token
+    );
+
+    let configured_url = env::var("ANYTHING_API_URL").ok();
+    match public_base_url(
+        configured_url.as_deref(),
+        headers,
+        remote_addr,
+        &server_trusted_proxies(),
+    ) {
+        Some(base_url) => format!("{}{}", base_url, path),
+        None => path,
+    }
+}
+
+// In the webhook response action's format so it can be stored under the request's idempotency key
+pub fn accepted_result(
+    headers: &HeaderMap,
+    remote_addr: &SocketAddr,
+    workflow_id: &str,
+    flow_session_id: Uuid,
+) -> Value {
+    let status_url = session_status_url(headers, remote_addr, workflow_id, flow_session_id);
+
+    json!({
+        "status_code": StatusCode::ACCEPTED.as_u16(),
+        "headers": {
+            "content-type": "application/json",
+            "location": status_url,
+        },
+        "body": {
+            "success": true,
+            "message": "Workflow started!",
+            "workflow_session_id": flow_session_id,
+            "workflow_id": workflow_id,
+            "status_url": status_url,
+        },
+    })
+}
+
+pub fn accepted_response(accepted: &Value) -> Response {
+    let mut response_headers = HeaderMap::new();
+    if let Some(location) = accepted["headers"]["location"]
+        .as_str()
+        .and_then(|location| HeaderValue::from_str(location).ok())
+    {
+        response_headers.insert(header::LOCATION, location);
+    }
+
+    (
+        StatusCode::ACCEPTED,
+        response_headers,
+        Json(accepted["body"].clone()),
+    )
+        .into_response()
+}
+
+/// Status of a webhook started session, plus the webhook response action's result once it ran.
+/// Sessions only show up once their first task is written, until then they read as pending.
+pub async fn fetch_webhook_session_status(
+    state: &Arc<AppState>,
+    workflow_id: &str,
+    flow_session_id: &str,
+) -> Result<Value, Box<dyn std::error::Error + Send + Sync>> {
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    let response = state
+        .anything_client
+        .from("tasks")
+        .auth(supabase_service_role_api_key)
+        .eq("flow_id", workflow_id)
+        .eq("flow_session_id", flow_session_id)
+        .select("flow_session_status, plugin_name, result")
+        .order("processing_order.asc")
+        .execute()
+        .await?;
+
+    let body = response.text().await?;
+    let tasks: Vec<Value> = serde_json::from_str(&body)?;
+
+    let status = tasks
+        .first()
+        .and_then(|task| task.get("flow_session_status"))
+        .and_then(Value::as_str)
+        .unwrap_or("pending")
+        .to_string();
+
+    let response = tasks
+        .iter()
+        .find(|task| {
+            task.get("plugin_name").and_then(Value::as_str) == Some("@anything/webhook_response")
+        })
+        .and_then(|task| task.get("result"))
+        .filter(|result| !result.is_null())
+        .cloned();
+
+    Ok(json!({
+        "workflow_id": workflow_id,
+        "workflow_session_id": flow_session_id,
+        "status": status,
+        "complete": matches!(status.as_str(), "completed" | "failed" | "canceled"),
+        "response": response,
+    }))
+}
+
+// Only reachable through the signed URL handed to whoever passed the trigger's security model
+pub async fn get_webhook_session_status(
+    Path((workflow_id, flow_session_id)): Path<(String, String)>,
+    Query(params): Query<HashMap<String, String>>,
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    println!(
+        "[WEBHOOK API] Getting status for session {} of workflow {}",
+        flow_session_id, workflow_id
+    );
+
+    if Uuid::parse_str(&workflow_id).is_err() || Uuid::parse_str(&flow_session_id).is_err() {
+        return (StatusCode::BAD_REQUEST, "Invalid workflow or session id").into_response();
+    }
+
+    let authorized = params.get("token").is_some_and(|token| {
+        verify_status_token(
+            &status_token_secret(),
+            &workflow_id,
+            &flow_session_id,
+            token,
+        )
+    });
+    if !authorized {
+        return (StatusCode::UNAUTHORIZED, "Invalid status token").into_response();
+    }
+
+    match fetch_webhook_session_status(&state, &workflow_id, &flow_session_id).await {
+        Ok(status) => Json(status).into_response(),
+        Err(e) => {
+            println!("[WEBHOOK API] Failed to get session status: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to get session status",
+            )
+                .into_response()
+        }
+    }
+}
+
+async fn session_is_complete(
+    state: &Arc<AppState>,
+    workflow_id: &str,
+    flow_session_id: Uuid,
+) -> bool {
+    fetch_webhook_session_status(state, workflow_id, &flow_session_id.to_string())
+        .await
+        .is_ok_and(|status| status["complete"].as_bool().unwrap_or(false))
+}
+
+/// POSTs the session status to the trigger's callback URL once the session finishes.
+/// Called once the processor has the session, a completion from before subscribing is found in the database.
+pub fn spawn_completion_callback(
+    state: &Arc<AppState>,
+    rendered_inputs: &Value,
+    workflow_id: &str,
+    flow_session_id: Uuid,
+) {
+    let callback_url = match rendered_inputs
+        .get("callback_url")
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|url| !url.is_empty())
+    {
+        Some(url) => url.to_string(),
+        None => return,
+    };
+
+    let mut events = state.flow_session_events.subscribe();
+    let state = state.clone();
+    let workflow_id = workflow_id.to_string();
+
+    tokio::spawn(async move {
+        let finished = timeout(callback_wait_timeout(), async {
+            if session_is_complete(&state, &workflow_id, flow_session_id).await {
+                return true;
+            }
+            loop {
+                match events.recv().await {
+                    Ok(event)
+                        if event.flow_session_id == flow_session_id
+                            && event.event_type == FlowSessionEventType::SessionCompleted =>
+                    {
+                        return true;
+                    }
+                    Ok(_) => continue,
+                    // Missed events may include this session's completion, so ask the database
+                    Err(RecvError::Lagged(skipped)) => {
+                        println!(
+                            "[WEBHOOK API] Callback for session {} lagged {} events, checking its status",
+                            flow_session_id, skipped
+                        );
+                        if session_is_complete(&state, &workflow_id, flow_session_id).await {
+                            return true;
+                        }
+                    }
+                    Err(RecvError::Closed) => return false,
+                }
+            }
+        })
+        .await
+        .unwrap_or(false);
+
+        if !finished {
+            println!(
+                "[WEBHOOK API] Gave up waiting for session {} to send its callback",
+                flow_session_id
+            );
+            return;
+        }
+
+        let mut status = Value::Null;
+        for _ in 0..STATUS_SETTLE_RETRIES {
+            match fetch_webhook_session_status(&state, &workflow_id, &flow_session_id.to_string())
+                .await
+            {
+                Ok(current) => {
+                    let complete = current["complete"].as_bool().unwrap_or(false);
+                    status = current;
+                    if complete {
+                        break;
+                    }
+                }
+                Err(e) => println!("[WEBHOOK API] Failed to get session status: {:?}", e),
+            }
+            sleep(STATUS_SETTLE_INTERVAL).await;
+        }
+
+        if status.is_null() {
+            return;
+        }
+
+        match state
+            .http_client
+            .post(&callback_url)
+            .timeout(CALLBACK_REQUEST_TIMEOUT)
+            .json(&status)
+            .send()
+            .await
+        {
+            Ok(response) => println!(
+                "[WEBHOOK API] Sent completion callback for session {}, got {}",
+                flow_session_id,
+                response.status()
+            ),
+            Err(e) => println!(
+                "[WEBHOOK API] Failed to send completion callback for session {}: {:?}",
+                flow_session_id, e
+            ),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "super-secret-jwt-token-with-at-least-32-characters-long";
+    const WORKFLOW_ID: &str = "0b9a6a0e-3f2c-4a8e-9a43-1f2d3c4b5a69";
+    const SESSION_ID: &str = "5d1f0a7c-2b4e-4c3d-8e9f-6a7b8c9d0e1f";
+
+    #[test]
+    fn test_status_token_only_matches_its_session() {
+        let token = status_token(SECRET, WORKFLOW_ID, SESSION_ID);
+        assert!(verify_status_token(SECRET, WORKFLOW_ID, SESSION_ID, &token));
+        assert!(!verify_status_token(
+            SECRET,
+            WORKFLOW_ID,
+            WORKFLOW_ID,
+            &token
+        ));
+        assert!(!verify_status_token(
+            "other-secret",
+            WORKFLOW_ID,
+            SESSION_ID,
+            &token
+        ));
+        assert!(!verify_status_token(
+            SECRET,
+            WORKFLOW_ID,
+            SESSION_ID,
+            "not-hex"
+        ));
+        assert!(!verify_status_token(SECRET, WORKFLOW_ID, SESSION_ID, ""));
+    }
+
+    #[test]
+    fn test_forwarded_host_only_trusted_from_proxies() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-host",
+            HeaderValue::from_static("api.example.com"),
+        );
+        headers.insert(header::HOST, HeaderValue::from_static("internal:3001"));
+        let remote_addr: SocketAddr = "203.0.113.7:443".parse().unwrap();
+        let proxies = vec!["203.0.113.0/24".parse::<IpNet>().unwrap()];
+
+        assert_eq!(public_base_url(None, &headers, &remote_addr, &[]), None);
+        assert_eq!(
+            public_base_url(None, &headers, &remote_addr, &proxies).as_deref(),
+            Some("https://api.example.com")
+        );
+        assert_eq!(
+            public_base_url(
+                Some("https://anything.example/ "),
+                &headers,
+                &remote_addr,
+                &[]
+            )
+            .as_deref(),
+            Some("https://anything.example")
+        );
+    }
+}
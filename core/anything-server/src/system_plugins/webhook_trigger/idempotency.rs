@@ -0,0 +1,373 @@
+use axum::{
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use dotenv::dotenv;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::env;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::trigger_engine::poll::value_at_path;
+use crate::AppState;
+
+use super::webhook_trigger_utils::parse_response_action_response_into_api_response;
+
+// How long a key is remembered when the trigger doesn't say
+const DEFAULT_IDEMPOTENCY_WINDOW_SECONDS: i64 = 24 * 60 * 60;
+
+#[derive(Debug, Serialize)]
+struct ClaimIdempotencyKeyInput {
+    p_flow_id: String,
+    p_idempotency_key: String,
+    p_flow_session_id: Uuid,
+    p_window_seconds: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct ClaimIdempotencyKeyResult {
+    claimed: bool,
+    flow_session_id: Option<Uuid>,
+    response: Option<Value>,
+}
+
+// The key comes from the configured header first, then from a path into the payload
+pub fn idempotency_key_from_request(
+    rendered_inputs: &Value,
+    headers: &HeaderMap,
+    payload: &Value,
+) -> Option<String> {
+    let input = |key: &str| {
+        rendered_inputs
+            .get(key)
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    };
+
+    let from_header = input("idempotency_header").and_then(|name| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+    });
+
+    from_header.or_else(|| {
+        let path = input("idempotency_key_path")?;
+        match value_at_path(payload, path)? {
+            Value::String(key) if !key.is_empty() => Some(key.clone()),
+            Value::Number(key) => Some(key.to_string()),
+            _ => None,
+        }
+    })
+}
+
+/// Claims the request's idempotency key for a new flow session.
+/// Ok(Some(key)) means the run should go ahead and its response be stored under key, Ok(None) that
+/// the trigger has no key configured. Duplicates get Err with the response to send back instead.
+pub async fn claim_webhook_idempotency_key(
+    state: &Arc<AppState>,
+    rendered_inputs: &Value,
+    headers: &HeaderMap,
+    payload: &Value,
+    flow_id: &str,
+    flow_session_id: Uuid,
+    respond: bool,
+) -> Result<Option<String>, Response> {
+    let idempotency_key = match idempotency_key_from_request(rendered_inputs, headers, payload) {
+        Some(key) => key,
+        None => return Ok(None),
+    };
+
+    let window_seconds = rendered_inputs
+        .get("idempotency_window")
+        .and_then(|value| {
+            value
+                .as_i64()
+                .or_else(|| value.as_str().and_then(|s| s.trim().parse().ok()))
+        })
+        .filter(|window| *window > 0)
+        .unwrap_or(DEFAULT_IDEMPOTENCY_WINDOW_SECONDS);
+
+    let claim = match claim_key(
+        state,
+        ClaimIdempotencyKeyInput {
+            p_flow_id: flow_id.to_string(),
+            p_idempotency_key: idempotency_key.clone(),
+            p_flow_session_id: flow_session_id,
+            p_window_seconds: window_seconds,
+        },
+    )
+    .await
+    {
+        Ok(claim) => claim,
+        Err(e) => {
+            // Failing closed, the sender will retry and a duplicate run is worse than a late one
+            println!("[WEBHOOK API] Failed to claim idempotency key: {:?}", e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to check idempotency key",
+            )
+                .into_response());
+        }
+    };
+
+    if claim.claimed {
+        return Ok(Some(idempotency_key));
+    }
+
+    println!(
+        "[WEBHOOK API] Duplicate request for idempotency key {}, original session {:?}",
+        idempotency_key, claim.flow_session_id
+    );
+
+    Err(duplicate_response(claim, flow_id, respond))
+}
+
+fn duplicate_response(claim: ClaimIdempotencyKeyResult, flow_id: &str, respond: bool) -> Response {
+    // Async runs store their 202 too, so retries get the same status URL back
+    if let Some(response) = claim.response {
+        return parse_response_action_response_into_api_response(response).into_response();
+    }
+
+    if !respond {
+        return Json(json!({
+            "success": true,
+            "message": "Duplicate request, workflow already started",
+            "duplicate": true,
+            "workflow_session_id": claim.flow_session_id,
+            "workflow_id": flow_id,
+        }))
+        .into_response();
+    }
+
+    // The original run hasn't answered yet
+    (
+        StatusCode::CONFLICT,
+        Json(json!({
+            "error": "A request with this idempotency key is still running",
+            "duplicate": true,
+            "workflow_session_id": claim.flow_session_id,
+        })),
+    )
+        .into_response()
+}
+
+// Ways a run holding a claimed key can end without a response from the workflow
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnansweredRun {
+    NotStarted,
+    FilesNotStored,
+    ChannelClosed,
+    TimedOut,
+}
+
+impl UnansweredRun {
+    // In the response action's format so it can be stored and replayed like a workflow's answer
+    pub fn response(self, flow_session_id: Uuid) -> Value {
+        let (status_code, error) = match self {
+            UnansweredRun::NotStarted => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to start workflow",
+            ),
+            UnansweredRun::FilesNotStored => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Uploaded files can't be stored right now",
+            ),
+            UnansweredRun::ChannelClosed => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Workflow execution channel closed unexpectedly",
+            ),
+            UnansweredRun::TimedOut => {
+                (StatusCode::REQUEST_TIMEOUT, "Workflow execution timed out")
+            }
+        };
+
+        json!({
+            "status_code": status_code.as_u16(),
+            "headers": { "content-type": "application/json" },
+            "body": {
+                "error": error,
+                "workflow_session_id": flow_session_id,
+            },
+        })
+    }
+
+    // A timed out workflow is still running so retries get the timeout, otherwise they may run again
+    pub fn keeps_key(self) -> bool {
+        self == UnansweredRun::TimedOut
+    }
+}
+
+/// Answers a run that ended without a response and settles its claimed key, so retries neither
+/// wait on a response that will never be stored nor start a second copy of a running workflow.
+pub async fn respond_to_unanswered_run(
+    state: &Arc<AppState>,
+    flow_id: &str,
+    idempotency_key: Option<&str>,
+    flow_session_id: Uuid,
+    outcome: UnansweredRun,
+) -> Response {
+    let response = outcome.response(flow_session_id);
+
+    if let Some(idempotency_key) = idempotency_key {
+        let settled = if outcome.keeps_key() {
+            store_idempotent_response(state, flow_id, idempotency_key, &response).await
+        } else {
+            release_idempotency_key(state, flow_id, idempotency_key).await
+        };
+        if let Err(e) = settled {
+            println!("[WEBHOOK API] Failed to settle idempotency key: {:?}", e);
+        }
+    }
+
+    parse_response_action_response_into_api_response(response).into_response()
+}
+
+async fn claim_key(
+    state: &Arc<AppState>,
+    input: ClaimIdempotencyKeyInput,
+) -> Result<ClaimIdempotencyKeyResult, Box<dyn std::error::Error + Send + Sync>> {
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    let response = state
+        .anything_client
+        .rpc(
+            "claim_webhook_idempotency_key",
+            serde_json::to_string(&input)?,
+        )
+        .auth(supabase_service_role_api_key)
+        .execute()
+        .await?;
+
+    let body = response.text().await?;
+    serde_json::from_str(&body)
+        .map_err(|e| format!("Unexpected claim response {}: {}", body, e).into())
+}
+
+// Keeps the webhook response action's result so retries of a /respond request get the same answer
+pub async fn store_idempotent_response(
+    state: &Arc<AppState>,
+    flow_id: &str,
+    idempotency_key: &str,
+    response: &Value,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    state
+        .anything_client
+        .from("webhook_idempotency_keys")
+        .auth(supabase_service_role_api_key)
+        .eq("flow_id", flow_id)
+        .eq("idempotency_key", idempotency_key)
+        .update(json!({ "response": response }).to_string())
+        .execute()
+        .await?;
+
+    Ok(())
+}
+
+// Frees a key whose run never answered so the sender's retry can start a new one
+pub async fn release_idempotency_key(
+    state: &Arc<AppState>,
+    flow_id: &str,
+    idempotency_key: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    state
+        .anything_client
+        .from("webhook_idempotency_keys")
+        .auth(supabase_service_role_api_key)
+        .eq("flow_id", flow_id)
+        .eq("idempotency_key", idempotency_key)
+        .is("response", "null")
+        .delete()
+        .execute()
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claim(response: Option<Value>) -> ClaimIdempotencyKeyResult {
+        ClaimIdempotencyKeyResult {
+            claimed: false,
+            flow_session_id: Some(Uuid::new_v4()),
+            response,
+        }
+    }
+
+    #[test]
+    fn test_duplicate_responses() {
+        let flow_id = Uuid::new_v4().to_string();
+
+        // Fire and forget requests are told the workflow already started
+        let response = duplicate_response(claim(None), &flow_id, false);
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Retries of a /respond request get the stored answer back
+        let stored = json!({ "status_code": 201, "body": { "id": 1 } });
+        let response = duplicate_response(claim(Some(stored)), &flow_id, true);
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        // Until there is one
+        let response = duplicate_response(claim(None), &flow_id, true);
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+
+        // Async runs answer retries with the stored 202
+        let stored = json!({ "status_code": 202, "body": { "status_url": "/status" } });
+        let response = duplicate_response(claim(Some(stored)), &flow_id, false);
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+    }
+
+    #[test]
+    fn test_timed_out_run_keeps_its_key() {
+        let flow_session_id = Uuid::new_v4();
+        let response = UnansweredRun::TimedOut.response(flow_session_id);
+
+        assert!(UnansweredRun::TimedOut.keeps_key());
+        assert_eq!(
+            response["body"]["workflow_session_id"],
+            json!(flow_session_id)
+        );
+
+        // The stored timeout is what retries get instead of a 409
+        let replayed = duplicate_response(claim(Some(response)), "flow", true);
+        assert_eq!(replayed.status(), StatusCode::REQUEST_TIMEOUT);
+    }
+
+    #[test]
+    fn test_failed_runs_release_their_key() {
+        for outcome in [UnansweredRun::NotStarted, UnansweredRun::ChannelClosed] {
+            assert!(!outcome.keeps_key());
+            let response =
+                parse_response_action_response_into_api_response(outcome.response(Uuid::new_v4()))
+                    .into_response();
+            assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    }
+
+    #[test]
+    fn test_unstored_files_release_their_key() {
+        let outcome = UnansweredRun::FilesNotStored;
+        assert!(!outcome.keeps_key());
+        let response =
+            parse_response_action_response_into_api_response(outcome.response(Uuid::new_v4()))
+                .into_response();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
@@ -1,3 +1,7 @@
+pub mod async_response;
+pub mod idempotency;
+pub mod request_body;
+pub mod request_log;
 pub mod webhook_trigger;
 pub use webhook_trigger::*;
 pub mod webhook_trigger_utils;
@@ -0,0 +1,366 @@
+use axum::{
+    body::{Body, Bytes},
+    extract::{FromRequest, Multipart, Request},
+    http::{header, HeaderMap},
+};
+use quick_xml::escape::resolve_xml_entity;
+use quick_xml::events::Event;
+use quick_xml::{Reader, XmlVersion};
+use serde_json::{json, Map, Value};
+use std::error::Error;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::files::utils::{r2_bucket, store_private_file};
+use crate::AppState;
+
+// Uploaded files land in the account's files under this folder
+const WEBHOOK_UPLOAD_FOLDER: &str = "webhook_uploads";
+
+/// A request body turned into JSON. Multipart files are held back until `store_files` so a
+/// duplicate request doesn't leave uploads behind.
+#[derive(Debug, Default)]
+pub struct ParsedBody {
+    value: Option<Value>,
+    parts: Vec<(String, MultipartPart)>,
+}
+
+#[derive(Debug)]
+enum MultipartPart {
+    Text(String),
+    File {
+        file_name: String,
+        content_type: String,
+        data: Bytes,
+    },
+}
+
+impl ParsedBody {
+    // Files show up as their name, type and size until they are stored
+    pub fn value(&self) -> Option<Value> {
+        self.value.clone()
+    }
+
+    pub fn has_files(&self) -> bool {
+        self.parts
+            .iter()
+            .any(|(_, part)| matches!(part, MultipartPart::File { .. }))
+    }
+
+    /// Stores uploaded files with the account's files and returns the body with each file
+    /// referenced by its file id. Fails before storing anything when file storage isn't configured.
+    pub async fn store_files(
+        self,
+        state: &Arc<AppState>,
+        account_id: &str,
+        flow_session_id: &Uuid,
+    ) -> Result<Option<Value>, Box<dyn Error + Send + Sync>> {
+        if !self.has_files() {
+            return Ok(self.value);
+        }
+
+        // Nowhere to put uploads, fail before any of them is stored
+        r2_bucket()?;
+
+        let mut fields = Map::new();
+        for (name, part) in self.parts {
+            let value = match part {
+                MultipartPart::Text(text) => Value::String(text),
+                MultipartPart::File {
+                    file_name,
+                    content_type,
+                    data,
+                } => {
+                    let folder = format!("{}/{}", WEBHOOK_UPLOAD_FOLDER, flow_session_id);
+                    match store_private_file(
+                        state,
+                        account_id,
+                        &folder,
+                        &file_name,
+                        &content_type,
+                        data,
+                    )
+                    .await
+                    {
+                        Ok(file) => json!({
+                            "file_id": file.file_id,
+                            "file_name": file.file_name,
+                            "content_type": file.content_type,
+                            "size": file.file_size,
+                        }),
+                        Err(e) => {
+                            // Still run the workflow, the field says what went wrong
+                            println!("[WEBHOOK API] Failed to store uploaded file: {:?}", e);
+                            json!({
+                                "file_name": file_name,
+                                "content_type": content_type,
+                                "error": "Failed to store file",
+                            })
+                        }
+                    }
+                }
+            };
+            insert_field(&mut fields, &name, value);
+        }
+
+        Ok(Some(Value::Object(fields)))
+    }
+}
+
+/// Turns the request body into JSON based on its content type.
+/// Returns the message to answer with when the body doesn't match its content type.
+pub async fn parse_request_body(headers: &HeaderMap, body: &Bytes) -> Result<ParsedBody, String> {
+    if body.is_empty() {
+        return Ok(ParsedBody::default());
+    }
+
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("");
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_lowercase();
+
+    let value = match mime.as_str() {
+        "application/x-www-form-urlencoded" => parse_form_body(body).map(Some)?,
+        "multipart/form-data" => return parse_multipart_body(content_type, body).await,
+        "application/xml" | "text/xml" => xml_to_json(body).map(Some)?,
+        _ if mime.ends_with("+xml") => xml_to_json(body).map(Some)?,
+        // Senders often mislabel JSON, so fall back to text instead of rejecting it
+        "application/json" => Some(parse_json_or_text(body)),
+        _ if mime.ends_with("+json") => Some(parse_json_or_text(body)),
+        _ if mime.starts_with("text/") => {
+            Some(Value::String(String::from_utf8_lossy(body).into_owned()))
+        }
+        // Unknown or missing content type, keep what we can make sense of
+        _ => serde_json::from_slice(body).ok().or_else(|| {
+            std::str::from_utf8(body)
+                .ok()
+                .map(|text| Value::String(text.to_string()))
+        }),
+    };
+
+    Ok(ParsedBody {
+        value,
+        parts: Vec::new(),
+    })
+}
+
+/// The body exactly as it was sent, for signatures or formats we don't parse.
+/// Multipart and binary bodies are left out, their content is already in the parsed body or files.
+pub fn raw_body(headers: &HeaderMap, body: &Bytes) -> Option<String> {
+    let multipart = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|content_type| {
+            content_type
+                .trim_start()
+                .to_lowercase()
+                .starts_with("multipart/")
+        });
+    if multipart {
+        return None;
+    }
+
+    std::str::from_utf8(body).ok().map(str::to_string)
+}
+
+fn parse_json_or_text(body: &Bytes) -> Value {
+    serde_json::from_slice(body)
+        .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(body).into_owned()))
+}
+
+fn parse_form_body(body: &Bytes) -> Result<Value, String> {
+    let pairs: Vec<(String, String)> =
+        serde_urlencoded::from_bytes(body).map_err(|e| format!("Invalid form body: {}", e))?;
+
+    let mut fields = Map::new();
+    for (key, value) in pairs {
+        insert_field(&mut fields, &key, Value::String(value));
+    }
+    Ok(Value::Object(fields))
+}
+
+// `key[]` and repeated keys collect into arrays
+fn insert_field(fields: &mut Map<String, Value>, key: &str, value: Value) {
+    let (key, is_array) = match key.strip_suffix("[]") {
+        Some(base) => (base, true),
+        None => (key, false),
+    };
+
+    match fields.get_mut(key) {
+        Some(Value::Array(items)) => items.push(value),
+        Some(existing) => {
+            let first = existing.take();
+            *existing = Value::Array(vec![first, value]);
+        }
+        None if is_array => {
+            fields.insert(key.to_string(), Value::Array(vec![value]));
+        }
+        None => {
+            fields.insert(key.to_string(), value);
+        }
+    }
+}
+
+async fn parse_multipart_body(content_type: &str, body: &Bytes) -> Result<ParsedBody, String> {
+    let request = Request::builder()
+        .header(header::CONTENT_TYPE, content_type)
+        .body(Body::from(body.clone()))
+        .map_err(|e| format!("Invalid multipart body: {}", e))?;
+
+    let mut multipart = Multipart::from_request(request, &())
+        .await
+        .map_err(|e| format!("Invalid multipart body: {}", e))?;
+
+    let mut fields = Map::new();
+    let mut parts = Vec::new();
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| format!("Invalid multipart body: {}", e))?
+    {
+        let name = field.name().unwrap_or("file").to_string();
+        let file_name = field.file_name().map(str::to_string);
+        let field_content_type = field
+            .content_type()
+            .unwrap_or("application/octet-stream")
+            .to_string();
+        let data = field
+            .bytes()
+            .await
+            .map_err(|e| format!("Invalid multipart body: {}", e))?;
+
+        let (value, part) = match file_name {
+            Some(file_name) => (
+                json!({
+                    "file_name": file_name,
+                    "content_type": field_content_type,
+                    "size": data.len(),
+                }),
+                MultipartPart::File {
+                    file_name,
+                    content_type: field_content_type,
+                    data,
+                },
+            ),
+            None => {
+                let text = String::from_utf8_lossy(&data).into_owned();
+                (Value::String(text.clone()), MultipartPart::Text(text))
+            }
+        };
+
+        insert_field(&mut fields, &name, value);
+        parts.push((name, part));
+    }
+
+    Ok(ParsedBody {
+        value: Some(Value::Object(fields)),
+        parts,
+    })
+}
+
+// Elements become objects keyed by child name, repeated children become arrays.
+// Attributes are kept as "@name" and text next to attributes or children as "#text".
+pub fn xml_to_json(xml: &[u8]) -> Result<Value, String> {
+    let mut reader = Reader::from_reader(xml);
+    // (element name, attributes and children, text)
+    let mut stack: Vec<(String, Map<String, Value>, String)> =
+        vec![(String::new(), Map::new(), String::new())];
+
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| format!("Invalid XML: {}", e))?;
+        match event {
+            Event::Start(ref element) | Event::Empty(ref element) => {
+                let name = String::from_utf8_lossy(element.name().as_ref()).into_owned();
+                let mut children = Map::new();
+                for attribute in element.attributes() {
+                    let attribute = attribute.map_err(|e| format!("Invalid XML: {}", e))?;
+                    let value = attribute
+                        .normalized_value(XmlVersion::Implicit1_0)
+                        .map_err(|e| format!("Invalid XML: {}", e))?;
+                    children.insert(
+                        format!("@{}", String::from_utf8_lossy(attribute.key.as_ref())),
+                        Value::String(value.into_owned()),
+                    );
+                }
+                stack.push((name, children, String::new()));
+                if matches!(event, Event::Empty(_)) {
+                    close_xml_element(&mut stack);
+                }
+            }
+            Event::End(_) => close_xml_element(&mut stack),
+            Event::Text(text) => {
+                let text = text.decode().map_err(|e| format!("Invalid XML: {}", e))?;
+                if let Some((_, _, current)) = stack.last_mut() {
+                    current.push_str(&text);
+                }
+            }
+            Event::CData(data) => {
+                if let Some((_, _, current)) = stack.last_mut() {
+                    current.push_str(&String::from_utf8_lossy(&data.into_inner()));
+                }
+            }
+            Event::GeneralRef(reference) => {
+                let resolved = match reference.resolve_char_ref() {
+                    Ok(Some(c)) => c.to_string(),
+                    _ => {
+                        let name = reference
+                            .decode()
+                            .map_err(|e| format!("Invalid XML: {}", e))?;
+                        resolve_xml_entity(&name)
+                            .map(str::to_string)
+                            .unwrap_or_else(|| format!("&{};", name))
+                    }
+                };
+                if let Some((_, _, current)) = stack.last_mut() {
+                    current.push_str(&resolved);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    if stack.len() != 1 {
+        return Err("Invalid XML: unclosed element".to_string());
+    }
+    let (_, root, _) = stack.pop().unwrap_or_default();
+    Ok(Value::Object(root))
+}
+
+fn close_xml_element(stack: &mut Vec<(String, Map<String, Value>, String)>) {
+    if stack.len() < 2 {
+        return;
+    }
+    let (name, mut children, text) = stack.pop().unwrap_or_default();
+    let text = text.trim();
+
+    let value = if children.is_empty() {
+        Value::String(text.to_string())
+    } else {
+        if !text.is_empty() {
+            children.insert("#text".to_string(), Value::String(text.to_string()));
+        }
+        Value::Object(children)
+    };
+
+    if let Some((_, parent, _)) = stack.last_mut() {
+        match parent.get_mut(&name) {
+            Some(Value::Array(items)) => items.push(value),
+            Some(existing) => {
+                let first = existing.take();
+                *existing = Value::Array(vec![first, value]);
+            }
+            None => {
+                parent.insert(name, value);
+            }
+        }
+    }
+}
@@ -0,0 +1,698 @@
+use axum::{
+    body::{to_bytes, Body, Bytes},
+    extract::{ConnectInfo, Path, Query, Request, State},
+    http::{header::CONTENT_TYPE, HeaderMap, HeaderName, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use base64::{engine::general_purpose, Engine as _};
+use dotenv::dotenv;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::env;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+use crate::supabase_jwt_middleware::User;
+use crate::AppState;
+
+use super::webhook_trigger::{
+    run_workflow, run_workflow_and_respond, run_workflow_version, run_workflow_version_and_respond,
+};
+
+// Requests kept per workflow, older ones are dropped as new ones come in
+const WEBHOOK_REQUEST_LOG_SIZE: i32 = 100;
+
+// Matches the server's DefaultBodyLimit
+const WEBHOOK_BODY_LIMIT: usize = 52_428_800;
+
+// Bodies over this are only kept as a preview and can't be replayed
+const REPLAY_BODY_LIMIT: usize = 256 * 1024;
+const BODY_PREVIEW_LIMIT: usize = 2048;
+
+const REDACTED: &str = "[redacted]";
+
+/// Handed to the webhook handlers by the request log so they can report the session they started.
+/// Replays come from an account member, so the handlers skip the security model and idempotency for them.
+#[derive(Debug, Clone, Default)]
+pub struct WebhookRequestContext {
+    replay: bool,
+    flow_session_id: Arc<Mutex<Option<Uuid>>>,
+}
+
+impl WebhookRequestContext {
+    fn replay() -> Self {
+        Self {
+            replay: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_replay(&self) -> bool {
+        self.replay
+    }
+
+    pub fn started_session(&self, flow_session_id: Uuid) {
+        if let Ok(mut started) = self.flow_session_id.lock() {
+            *started = Some(flow_session_id);
+        }
+    }
+
+    fn flow_session_id(&self) -> Option<Uuid> {
+        self.flow_session_id
+            .lock()
+            .ok()
+            .and_then(|started| *started)
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct RecordWebhookRequestInput {
+    p_flow_id: String,
+    p_flow_version_id: Option<String>,
+    p_respond: bool,
+    p_method: String,
+    p_path: String,
+    p_query: Option<String>,
+    p_headers: Value,
+    p_remote_addr: String,
+    p_body_preview: Option<String>,
+    p_body_size: i64,
+    p_body: Option<String>,
+    p_response_status: u16,
+    p_flow_session_id: Option<Uuid>,
+    p_replay_of: Option<Uuid>,
+    p_keep: i32,
+}
+
+#[derive(Debug, Deserialize)]
+struct LoggedWebhookRequest {
+    flow_version_id: Option<Uuid>,
+    respond: bool,
+    method: String,
+    path: String,
+    query: Option<String>,
+    headers: Value,
+    remote_addr: Option<String>,
+    body_size: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct LoggedWebhookBody {
+    body: Option<String>,
+}
+
+// Only the server reads the replayable body, after the user was shown to have access to the request
+async fn fetch_logged_body(
+    state: &Arc<AppState>,
+    webhook_request_id: &str,
+) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    let response = state
+        .anything_client
+        .from("webhook_requests")
+        .auth(supabase_service_role_api_key)
+        .eq("webhook_request_id", webhook_request_id)
+        .select("body")
+        .single()
+        .execute()
+        .await?;
+
+    let body = response.text().await?;
+    let logged: LoggedWebhookBody = serde_json::from_str(&body)?;
+    Ok(logged.body)
+}
+
+// Credentials and signatures never make it into the log
+const SENSITIVE_HEADERS: [&str; 4] = [
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+];
+const SENSITIVE_HEADER_PARTS: [&str; 6] = [
+    "token",
+    "secret",
+    "signature",
+    "api-key",
+    "apikey",
+    "password",
+];
+
+fn is_sensitive_header(name: &str) -> bool {
+    SENSITIVE_HEADERS.contains(&name)
+        || SENSITIVE_HEADER_PARTS
+            .iter()
+            .any(|part| name.contains(part))
+}
+
+// Body fields are named like `api_key` as often as `api-key`
+fn is_sensitive_field(name: &str) -> bool {
+    is_sensitive_header(&name.to_lowercase().replace('_', "-"))
+}
+
+fn redact_json(value: &mut Value) {
+    match value {
+        Value::Object(fields) => {
+            for (name, field) in fields.iter_mut() {
+                if is_sensitive_field(name) {
+                    *field = Value::String(REDACTED.to_string());
+                } else {
+                    redact_json(field);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact_json),
+        _ => {}
+    }
+}
+
+fn redact_pairs(pairs: Vec<(String, String)>) -> Vec<(String, String)> {
+    pairs
+        .into_iter()
+        .map(|(name, value)| {
+            if is_sensitive_field(&name) {
+                (name, REDACTED.to_string())
+            } else {
+                (name, value)
+            }
+        })
+        .collect()
+}
+
+// Tokens are passed in the query string as often as in headers, e.g. `?api_key=...`
+fn redact_query(query: &str) -> String {
+    serde_urlencoded::from_str::<Vec<(String, String)>>(query)
+        .ok()
+        .and_then(|pairs| serde_urlencoded::to_string(redact_pairs(pairs)).ok())
+        .unwrap_or_else(|| REDACTED.to_string())
+}
+
+// JSON and form bodies get credential-looking fields redacted, other bodies are shown as sent
+fn body_preview(headers: &HeaderMap, body: &Bytes) -> Option<String> {
+    if body.is_empty() {
+        return None;
+    }
+
+    let form = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|content_type| {
+            content_type
+                .to_lowercase()
+                .starts_with("application/x-www-form-urlencoded")
+        });
+
+    let preview = if let Ok(mut json) = serde_json::from_slice::<Value>(body) {
+        redact_json(&mut json);
+        json.to_string()
+    } else if let Some(Ok(pairs)) =
+        form.then(|| serde_urlencoded::from_bytes::<Vec<(String, String)>>(body))
+    {
+        serde_urlencoded::to_string(redact_pairs(pairs))
+            .unwrap_or_else(|_| String::from_utf8_lossy(body).into_owned())
+    } else {
+        String::from_utf8_lossy(body).into_owned()
+    };
+
+    let mut end = preview.len().min(BODY_PREVIEW_LIMIT);
+    while !preview.is_char_boundary(end) {
+        end -= 1;
+    }
+    Some(preview[..end].to_string())
+}
+
+fn sanitize_headers(headers: &HeaderMap) -> Value {
+    let mut sanitized = Map::new();
+    for name in headers.keys() {
+        let value = if is_sensitive_header(name.as_str()) {
+            REDACTED.to_string()
+        } else {
+            headers
+                .get_all(name)
+                .iter()
+                .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        sanitized.insert(name.to_string(), Value::String(value));
+    }
+    Value::Object(sanitized)
+}
+
+// Redacted headers are left out, which is fine since replays skip the security model
+fn headers_from_log(headers: &Value) -> HeaderMap {
+    let mut header_map = HeaderMap::new();
+    if let Some(headers) = headers.as_object() {
+        for (name, value) in headers {
+            let value = match value.as_str() {
+                Some(value) if value != REDACTED => value,
+                _ => continue,
+            };
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                header_map.insert(name, value);
+            }
+        }
+    }
+    header_map
+}
+
+// What's logged about a request, borrowed from the live request or from a replayed entry
+struct RequestParts<'a> {
+    method: &'a Method,
+    path: &'a str,
+    query: Option<&'a str>,
+    headers: &'a HeaderMap,
+    remote_addr: &'a SocketAddr,
+    body: &'a Bytes,
+}
+
+fn build_record(
+    params: &HashMap<String, String>,
+    request: RequestParts,
+    response_status: StatusCode,
+    flow_session_id: Option<Uuid>,
+    replay_of: Option<Uuid>,
+) -> Option<RecordWebhookRequestInput> {
+    let flow_id = params.get("workflow_id")?;
+    // Anything else can't belong to a workflow
+    Uuid::parse_str(flow_id).ok()?;
+
+    let body = request.body;
+
+    Some(RecordWebhookRequestInput {
+        p_flow_id: flow_id.clone(),
+        p_flow_version_id: params
+            .get("workflow_version_id")
+            .filter(|id| Uuid::parse_str(id).is_ok())
+            .cloned(),
+        p_respond: request.path.trim_end_matches('/').ends_with("/respond"),
+        p_method: request.method.to_string(),
+        p_path: request.path.to_string(),
+        p_query: request.query.map(redact_query),
+        p_headers: sanitize_headers(request.headers),
+        p_remote_addr: request.remote_addr.to_string(),
+        p_body_preview: body_preview(request.headers, body),
+        p_body_size: body.len() as i64,
+        p_body: (!body.is_empty() && body.len() <= REPLAY_BODY_LIMIT)
+            .then(|| general_purpose::STANDARD.encode(body)),
+        p_response_status: response_status.as_u16(),
+        p_flow_session_id: flow_session_id,
+        p_replay_of: replay_of,
+        p_keep: WEBHOOK_REQUEST_LOG_SIZE,
+    })
+}
+
+// Written in the background so logging never slows down or fails a webhook
+fn record_in_background(state: &Arc<AppState>, record: RecordWebhookRequestInput) {
+    let state = state.clone();
+    tokio::spawn(async move {
+        dotenv().ok();
+        let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+            .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+        let input = match serde_json::to_string(&record) {
+            Ok(input) => input,
+            Err(e) => {
+                println!(
+                    "[WEBHOOK API] Failed to serialize request log entry: {:?}",
+                    e
+                );
+                return;
+            }
+        };
+
+        match state
+            .anything_client
+            .rpc("record_webhook_request", input)
+            .auth(supabase_service_role_api_key)
+            .execute()
+            .await
+        {
+            Ok(response) if !response.status().is_success() => println!(
+                "[WEBHOOK API] Failed to record webhook request: {}",
+                response.text().await.unwrap_or_default()
+            ),
+            Ok(_) => {}
+            Err(e) => println!("[WEBHOOK API] Failed to record webhook request: {:?}", e),
+        }
+    });
+}
+
+/// Middleware for the webhook routes that logs every request with the status it got back,
+/// including the ones rejected before a workflow ran.
+pub async fn record_webhook_request(
+    State(state): State<Arc<AppState>>,
+    Path(params): Path<HashMap<String, String>>,
+    ConnectInfo(remote_addr): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let (mut parts, body) = request.into_parts();
+
+    let method = parts.method.clone();
+    let path = parts.uri.path().to_string();
+    let query = parts.uri.query().map(str::to_string);
+    let headers = parts.headers.clone();
+
+    let (body, response) = match to_bytes(body, WEBHOOK_BODY_LIMIT).await {
+        Ok(body) => {
+            let context = WebhookRequestContext::default();
+            parts.extensions.insert(context.clone());
+            let response = next
+                .run(Request::from_parts(parts, Body::from(body.clone())))
+                .await;
+            (body, Some((response, context)))
+        }
+        Err(_) => (Bytes::new(), None),
+    };
+
+    let (response, flow_session_id) = match response {
+        Some((response, context)) => (response, context.flow_session_id()),
+        None => (
+            (StatusCode::PAYLOAD_TOO_LARGE, "Request body too large").into_response(),
+            None,
+        ),
+    };
+
+    if let Some(record) = build_record(
+        &params,
+        RequestParts {
+            method: &method,
+            path: &path,
+            query: query.as_deref(),
+            headers: &headers,
+            remote_addr: &remote_addr,
+            body: &body,
+        },
+        response.status(),
+        flow_session_id,
+        None,
+    ) {
+        record_in_background(&state, record);
+    }
+
+    response
+}
+
+pub async fn get_webhook_requests(
+    Path((account_id, workflow_id)): Path<(String, String)>,
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+) -> impl IntoResponse {
+    println!(
+        "[WEBHOOK API] Getting request log for workflow {}",
+        workflow_id
+    );
+
+    // The full body is only kept for replays and isn't readable by users
+    let response = match state
+        .anything_client
+        .from("webhook_requests")
+        .auth(user.jwt)
+        .eq("account_id", &account_id)
+        .eq("flow_id", &workflow_id)
+        .select("webhook_request_id, flow_id, flow_version_id, respond, method, path, query, headers, remote_addr, body_preview, body_size, response_status, flow_session_id, replay_of, created_at")
+        .order("created_at.desc")
+        .limit(WEBHOOK_REQUEST_LOG_SIZE as usize)
+        .execute()
+        .await
+    {
+        Ok(response) => response,
+        Err(e) => {
+            println!("[WEBHOOK API] Failed to get request log: {:?}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to execute request",
+            )
+                .into_response();
+        }
+    };
+
+    let body = match response.text().await {
+        Ok(body) => body,
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to read response body",
+            )
+                .into_response()
+        }
+    };
+
+    match serde_json::from_str::<Value>(&body) {
+        Ok(items) => Json(items).into_response(),
+        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Failed to parse JSON").into_response(),
+    }
+}
+
+/// Sends a logged request through its webhook again, as a new run that is logged as a replay of it.
+pub async fn replay_webhook_request(
+    Path((account_id, workflow_id, webhook_request_id)): Path<(String, String, String)>,
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+) -> impl IntoResponse {
+    println!(
+        "[WEBHOOK API] Replaying request {} for workflow {}",
+        webhook_request_id, workflow_id
+    );
+
+    let replay_of = match Uuid::parse_str(&webhook_request_id) {
+        Ok(id) => id,
+        Err(_) => return (StatusCode::BAD_REQUEST, "Invalid request id").into_response(),
+    };
+
+    // Read as the user so only members of the flow's account can replay its requests
+    let response = match state
+        .anything_client
+        .from("webhook_requests")
+        .auth(user.jwt)
+        .eq("account_id", &account_id)
+        .eq("flow_id", &workflow_id)
+        .eq("webhook_request_id", &webhook_request_id)
+        .select("flow_version_id, respond, method, path, query, headers, remote_addr, body_size")
+        .single()
+        .execute()
+        .await
+    {
+        Ok(response) => response,
+        Err(e) => {
+            println!("[WEBHOOK API] Failed to get logged request: {:?}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to execute request",
+            )
+                .into_response();
+        }
+    };
+
+    let body = match response.text().await {
+        Ok(body) => body,
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to read response body",
+            )
+                .into_response()
+        }
+    };
+
+    let logged: LoggedWebhookRequest = match serde_json::from_str(&body) {
+        Ok(logged) => logged,
+        Err(_) => return (StatusCode::NOT_FOUND, "Request not found").into_response(),
+    };
+
+    let logged_body = if logged.body_size > 0 {
+        match fetch_logged_body(&state, &webhook_request_id).await {
+            Ok(body) => body,
+            Err(e) => {
+                println!("[WEBHOOK API] Failed to get logged body: {:?}", e);
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Failed to get logged body",
+                )
+                    .into_response();
+            }
+        }
+    } else {
+        None
+    };
+
+    let body = match &logged_body {
+        Some(body) => match general_purpose::STANDARD.decode(body) {
+            Ok(body) => Bytes::from(body),
+            Err(_) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Failed to decode logged body",
+                )
+                    .into_response()
+            }
+        },
+        None if logged.body_size > 0 => {
+            return (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "Request body was too large to keep for replays",
+            )
+                .into_response()
+        }
+        None => Bytes::new(),
+    };
+
+    let method = Method::from_bytes(logged.method.as_bytes()).unwrap_or(Method::POST);
+    let headers = headers_from_log(&logged.headers);
+    // Redacted query parameters are left out like redacted headers
+    let query = logged
+        .query
+        .as_deref()
+        .and_then(|query| serde_urlencoded::from_str::<Vec<(String, String)>>(query).ok())
+        .map(|pairs| {
+            pairs
+                .into_iter()
+                .filter(|(_, value)| value != REDACTED)
+                .collect::<HashMap<_, _>>()
+        })
+        .map(Query);
+    let remote_addr = logged
+        .remote_addr
+        .as_deref()
+        .and_then(|addr| addr.parse().ok())
+        .unwrap_or_else(|| SocketAddr::from(([0, 0, 0, 0], 0)));
+
+    let context = WebhookRequestContext::replay();
+    let response = match (logged.flow_version_id, logged.respond) {
+        (Some(flow_version_id), true) => run_workflow_version_and_respond(
+            method.clone(),
+            Path((workflow_id.clone(), flow_version_id.to_string())),
+            State(state.clone()),
+            ConnectInfo(remote_addr),
+            Extension(context.clone()),
+            headers.clone(),
+            query,
+            body.clone(),
+        )
+        .await
+        .into_response(),
+        (Some(flow_version_id), false) => run_workflow_version(
+            method.clone(),
+            Path((workflow_id.clone(), flow_version_id.to_string())),
+            State(state.clone()),
+            ConnectInfo(remote_addr),
+            Extension(context.clone()),
+            headers.clone(),
+            query,
+            body.clone(),
+        )
+        .await
+        .into_response(),
+        (None, true) => run_workflow_and_respond(
+            method.clone(),
+            Path(workflow_id.clone()),
+            State(state.clone()),
+            ConnectInfo(remote_addr),
+            Extension(context.clone()),
+            headers.clone(),
+            query,
+            body.clone(),
+        )
+        .await
+        .into_response(),
+        (None, false) => run_workflow(
+            method.clone(),
+            Path(workflow_id.clone()),
+            State(state.clone()),
+            ConnectInfo(remote_addr),
+            Extension(context.clone()),
+            headers.clone(),
+            query,
+            body.clone(),
+        )
+        .await
+        .into_response(),
+    };
+
+    let mut params = HashMap::from([("workflow_id".to_string(), workflow_id.clone())]);
+    if let Some(flow_version_id) = logged.flow_version_id {
+        params.insert(
+            "workflow_version_id".to_string(),
+            flow_version_id.to_string(),
+        );
+    }
+
+    if let Some(record) = build_record(
+        &params,
+        RequestParts {
+            method: &method,
+            path: &logged.path,
+            query: logged.query.as_deref(),
+            headers: &headers,
+            remote_addr: &remote_addr,
+            body: &body,
+        },
+        response.status(),
+        context.flow_session_id(),
+        Some(replay_of),
+    ) {
+        record_in_background(&state, record);
+    }
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_body_preview_redacts_credentials() {
+        let json =
+            Bytes::from(r#"{"event":"paid","api_key":"sk_live","user":{"Password":"hunter2"}}"#);
+        let preview = body_preview(&HeaderMap::new(), &json).unwrap();
+        assert!(!preview.contains("sk_live") && !preview.contains("hunter2"));
+        assert!(preview.contains("paid"));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/x-www-form-urlencoded"),
+        );
+        let form = Bytes::from("event=paid&access_token=abc123");
+        assert_eq!(
+            body_preview(&headers, &form).unwrap(),
+            "event=paid&access_token=%5Bredacted%5D"
+        );
+
+        // Other text is left as sent
+        let text = Bytes::from("token=abc is not a form");
+        assert_eq!(
+            body_preview(&HeaderMap::new(), &text).unwrap(),
+            "token=abc is not a form"
+        );
+    }
+
+    #[test]
+    fn test_body_preview_is_truncated() {
+        let text = Bytes::from("é".repeat(BODY_PREVIEW_LIMIT));
+        let preview = body_preview(&HeaderMap::new(), &text).unwrap();
+        assert!(preview.len() <= BODY_PREVIEW_LIMIT);
+        assert!(body_preview(&HeaderMap::new(), &Bytes::new()).is_none());
+    }
+
+    #[test]
+    fn test_query_redacts_credentials() {
+        assert_eq!(
+            redact_query("api_key=secret&page=2&access_token=abc"),
+            "api_key=%5Bredacted%5D&page=2&access_token=%5Bredacted%5D"
+        );
+        assert_eq!(redact_query("page=2&sort=asc"), "page=2&sort=asc");
+    }
+}
@@ -1,15 +1,16 @@
 use axum::{
-    extract::{Path, Query, State},
+    body::Bytes,
+    extract::{ConnectInfo, Path, Query, State},
     http::{HeaderMap, Method, StatusCode},
     response::IntoResponse,
-    Json,
+    Extension, Json,
 };
 
 use std::time::Duration;
 
 use dotenv::dotenv;
 use serde_json::{json, Value};
-use std::{collections::HashMap, env, sync::Arc};
+use std::{collections::HashMap, env, net::SocketAddr, sync::Arc};
 use uuid::Uuid;
 
 use crate::{
@@ -26,9 +27,19 @@ use crate::{processor::processor::ProcessorMessage, types::workflow_types::Datab
 use tokio::sync::oneshot;
 use tokio::time::timeout;
 
+use super::async_response::{
+    accepted_response, accepted_result, spawn_completion_callback, wants_async_response,
+};
+use super::idempotency::{
+    claim_webhook_idempotency_key, respond_to_unanswered_run, store_idempotent_response,
+    UnansweredRun,
+};
+use super::request_body::{parse_request_body, raw_body};
+use super::request_log::WebhookRequestContext;
 use super::webhook_trigger_utils::{
     convert_request_to_payload, parse_response_action_response_into_api_response,
     validate_request_method, validate_required_input_and_response_plugins, validate_security_model,
+    validate_workflow_not_paused,
 };
 
 //One Minute
@@ -38,9 +49,11 @@ pub async fn run_workflow_and_respond(
     method: Method,
     Path(workflow_id): Path<String>,
     State(state): State<Arc<AppState>>,
+    ConnectInfo(remote_addr): ConnectInfo<SocketAddr>,
+    Extension(request_context): Extension<WebhookRequestContext>,
     headers: HeaderMap,
     query: Option<Query<HashMap<String, String>>>,
-    body: Option<Json<Value>>,
+    body: Bytes,
 ) -> impl IntoResponse {
     println!("[WEBHOOK API] Handling run workflow and respond");
     // println!("[WEBHOOK API] Payload: {:?}", payload);
@@ -59,7 +72,7 @@ pub async fn run_workflow_and_respond(
         .eq("flow_id", workflow_id.clone())
         .eq("published", "true")
         .auth(supabase_service_role_api_key.clone())
-        .select("*")
+        .select("*, flow:flows(paused, paused_response)")
         .single()
         .execute()
         .await
@@ -105,6 +118,10 @@ pub async fn run_workflow_and_respond(
     // Get account_id from workflow_version
     let account_id = workflow_version.account_id.clone();
 
+    if let Some(response) = validate_workflow_not_paused(&workflow_version) {
+        return response.into_response();
+    }
+
     // Parse the flow definition into a Workflow
     println!("[WEBHOOK API] Parsing workflow definition");
     // Validate the webhook trigger node and outputs
@@ -155,10 +172,19 @@ pub async fn run_workflow_and_respond(
 
     println!("[WEBHOOK API] Bundled context: {:?}", rendered_inputs);
 
-    //Validate security model
-    if let Some(response) = validate_security_model(&rendered_inputs, &headers, state.clone()).await
-    {
-        return response.into_response();
+    //Validate security model, replays come from an account member with the credentials redacted
+    if !request_context.is_replay() {
+        if let Some(response) = validate_security_model(
+            &rendered_inputs,
+            &headers,
+            &body,
+            &remote_addr,
+            state.clone(),
+        )
+        .await
+        {
+            return response.into_response();
+        }
     }
 
     // Validate request method
@@ -166,7 +192,62 @@ pub async fn run_workflow_and_respond(
         return response.into_response();
     }
 
-    let processed_payload = convert_request_to_payload(method.clone(), query, body);
+    // Form, multipart and XML bodies become JSON
+    let parsed_body = match parse_request_body(&headers, &body).await {
+        Ok(parsed_body) => parsed_body,
+        Err(message) => return (StatusCode::BAD_REQUEST, message).into_response(),
+    };
+
+    let processed_payload =
+        convert_request_to_payload(method.clone(), query.clone(), parsed_body.value().map(Json));
+    let raw_body = raw_body(&headers, &body);
+
+    // Long running workflows answer 202 right away and are polled or called back instead
+    let async_mode = wants_async_response(&rendered_inputs, &headers);
+
+    // Retries carrying the same idempotency key get the original run back, replays always run again
+    let idempotency_key = if request_context.is_replay() {
+        None
+    } else {
+        match claim_webhook_idempotency_key(
+            &state,
+            &rendered_inputs,
+            &headers,
+            &processed_payload,
+            &workflow_id,
+            flow_session_id,
+            !async_mode,
+        )
+        .await
+        {
+            Ok(idempotency_key) => idempotency_key,
+            Err(response) => return response,
+        }
+    };
+
+    // Uploaded files are stored with the account's files once the request isn't a duplicate
+    let processed_payload = if parsed_body.has_files() {
+        let stored_body = match parsed_body
+            .store_files(&state, &account_id.to_string(), &flow_session_id)
+            .await
+        {
+            Ok(stored_body) => stored_body,
+            Err(e) => {
+                println!("[WEBHOOK API] Failed to store uploaded files: {:?}", e);
+                return respond_to_unanswered_run(
+                    &state,
+                    &workflow_id,
+                    idempotency_key.as_deref(),
+                    flow_session_id,
+                    UnansweredRun::FilesNotStored,
+                )
+                .await;
+            }
+        };
+        convert_request_to_payload(method.clone(), query, stored_body.map(Json))
+    } else {
+        processed_payload
+    };
 
     // Create a task to initiate the flow
     println!("[WEBHOOK API] Creating task for workflow execution");
@@ -191,6 +272,7 @@ pub async fn run_workflow_and_respond(
         .result(json!({
                     "headers": headers.iter().map(|(k,v)| (k.as_str(), String::from_utf8_lossy(v.as_bytes()).into_owned())).collect::<HashMap<_,_>>(),
                     "body": processed_payload.clone(),
+                    "raw_body": raw_body,
                     "method": method.to_string(),
                 }))
         .build() {
@@ -206,7 +288,7 @@ pub async fn run_workflow_and_respond(
     let (tx, rx) = oneshot::channel();
 
     // Store the sender in the state
-    {
+    if !async_mode {
         let mut completions = state.flow_completions.lock().await;
         completions.insert(
             flow_session_id.to_string(),
@@ -228,11 +310,29 @@ pub async fn run_workflow_and_respond(
 
     if let Err(e) = state.processor_sender.send(processor_message).await {
         println!("[TEST WORKFLOW] Failed to send message to processor: {}", e);
-        return (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to send message to processor: {}", e),
+        return respond_to_unanswered_run(
+            &state,
+            &workflow_id,
+            idempotency_key.as_deref(),
+            flow_session_id,
+            UnansweredRun::NotStarted,
         )
-            .into_response();
+        .await;
+    }
+    request_context.started_session(flow_session_id);
+
+    if async_mode {
+        println!("[WEBHOOK API] Workflow started in async mode");
+        spawn_completion_callback(&state, &rendered_inputs, &workflow_id, flow_session_id);
+        let accepted = accepted_result(&headers, &remote_addr, &workflow_id, flow_session_id);
+        if let Some(idempotency_key) = &idempotency_key {
+            if let Err(e) =
+                store_idempotent_response(&state, &workflow_id, idempotency_key, &accepted).await
+            {
+                println!("[WEBHOOK API] Failed to store idempotent response: {:?}", e);
+            }
+        }
+        return accepted_response(&accepted);
     }
 
     println!("[WEBHOOK API] Waiting for workflow completion");
@@ -241,18 +341,25 @@ pub async fn run_workflow_and_respond(
     match timeout(Duration::from_secs(WEBHOOK_TIMEOUT), rx).await {
         Ok(Ok(result)) => {
             println!("[WEBHOOK API] Received workflow result");
+            if let Some(idempotency_key) = &idempotency_key {
+                if let Err(e) =
+                    store_idempotent_response(&state, &workflow_id, idempotency_key, &result).await
+                {
+                    println!("[WEBHOOK API] Failed to store idempotent response: {:?}", e);
+                }
+            }
             parse_response_action_response_into_api_response(result).into_response()
         }
         Ok(Err(_)) => {
             println!("[WEBHOOK API] Workflow channel closed unexpectedly");
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({
-                    "error": "Workflow execution channel closed unexpectedly",
-                    "workflow_session_id": flow_session_id
-                })),
+            respond_to_unanswered_run(
+                &state,
+                &workflow_id,
+                idempotency_key.as_deref(),
+                flow_session_id,
+                UnansweredRun::ChannelClosed,
             )
-                .into_response()
+            .await
         }
         Err(_) => {
             println!("[WEBHOOK API] Workflow timed out after 30 seconds");
@@ -262,14 +369,14 @@ pub async fn run_workflow_and_respond(
                 .lock()
                 .await
                 .remove(&flow_session_id.to_string());
-            (
-                StatusCode::REQUEST_TIMEOUT,
-                Json(json!({
-                    "error": "Workflow execution timed out",
-                    "workflow_session_id": flow_session_id
-                })),
+            respond_to_unanswered_run(
+                &state,
+                &workflow_id,
+                idempotency_key.as_deref(),
+                flow_session_id,
+                UnansweredRun::TimedOut,
             )
-                .into_response()
+            .await
         }
     }
 }
@@ -278,9 +385,11 @@ pub async fn run_workflow_version_and_respond(
     method: Method,
     Path((workflow_id, workflow_version_id)): Path<(String, String)>,
     State(state): State<Arc<AppState>>,
+    ConnectInfo(remote_addr): ConnectInfo<SocketAddr>,
+    Extension(request_context): Extension<WebhookRequestContext>,
     headers: HeaderMap,
     query: Option<Query<HashMap<String, String>>>,
-    body: Option<Json<Value>>,
+    body: Bytes,
 ) -> impl IntoResponse {
     println!("[WEBHOOK API] Handling run workflow and respond");
     println!("[WEBHOOK API] Payload: {:?}", body);
@@ -300,7 +409,7 @@ pub async fn run_workflow_version_and_respond(
         .eq("flow_id", workflow_id.clone())
         .eq("flow_version_id", workflow_version_id.clone())
         .auth(supabase_service_role_api_key.clone())
-        .select("*")
+        .select("*, flow:flows(paused, paused_response)")
         .single()
         .execute()
         .await
@@ -346,6 +455,10 @@ pub async fn run_workflow_version_and_respond(
     // Get account_id from workflow_version
     let account_id = workflow_version.account_id.clone();
 
+    if let Some(response) = validate_workflow_not_paused(&workflow_version) {
+        return response.into_response();
+    }
+
     // Validate the webhook trigger node and outputs
     let (trigger_node, _output_node) = match validate_required_input_and_response_plugins(
         &workflow_version.flow_definition,
@@ -394,10 +507,19 @@ pub async fn run_workflow_version_and_respond(
 
     println!("[WEBHOOK API] Bundled context: {:?}", rendered_inputs);
 
-    //Validate security model
-    if let Some(response) = validate_security_model(&rendered_inputs, &headers, state.clone()).await
-    {
-        return response.into_response();
+    //Validate security model, replays come from an account member with the credentials redacted
+    if !request_context.is_replay() {
+        if let Some(response) = validate_security_model(
+            &rendered_inputs,
+            &headers,
+            &body,
+            &remote_addr,
+            state.clone(),
+        )
+        .await
+        {
+            return response.into_response();
+        }
     }
 
     // Validate request method
@@ -405,7 +527,62 @@ pub async fn run_workflow_version_and_respond(
         return response.into_response();
     }
 
-    let processed_payload = convert_request_to_payload(method.clone(), query, body);
+    // Form, multipart and XML bodies become JSON
+    let parsed_body = match parse_request_body(&headers, &body).await {
+        Ok(parsed_body) => parsed_body,
+        Err(message) => return (StatusCode::BAD_REQUEST, message).into_response(),
+    };
+
+    let processed_payload =
+        convert_request_to_payload(method.clone(), query.clone(), parsed_body.value().map(Json));
+    let raw_body = raw_body(&headers, &body);
+
+    // Long running workflows answer 202 right away and are polled or called back instead
+    let async_mode = wants_async_response(&rendered_inputs, &headers);
+
+    // Retries carrying the same idempotency key get the original run back, replays always run again
+    let idempotency_key = if request_context.is_replay() {
+        None
+    } else {
+        match claim_webhook_idempotency_key(
+            &state,
+            &rendered_inputs,
+            &headers,
+            &processed_payload,
+            &workflow_id,
+            flow_session_id,
+            !async_mode,
+        )
+        .await
+        {
+            Ok(idempotency_key) => idempotency_key,
+            Err(response) => return response,
+        }
+    };
+
+    // Uploaded files are stored with the account's files once the request isn't a duplicate
+    let processed_payload = if parsed_body.has_files() {
+        let stored_body = match parsed_body
+            .store_files(&state, &account_id.to_string(), &flow_session_id)
+            .await
+        {
+            Ok(stored_body) => stored_body,
+            Err(e) => {
+                println!("[WEBHOOK API] Failed to store uploaded files: {:?}", e);
+                return respond_to_unanswered_run(
+                    &state,
+                    &workflow_id,
+                    idempotency_key.as_deref(),
+                    flow_session_id,
+                    UnansweredRun::FilesNotStored,
+                )
+                .await;
+            }
+        };
+        convert_request_to_payload(method.clone(), query, stored_body.map(Json))
+    } else {
+        processed_payload
+    };
 
     // Create a task to initiate the flow
     println!("[WEBHOOK API] Creating task for workflow execution");
@@ -430,6 +607,7 @@ pub async fn run_workflow_version_and_respond(
     .result(json!({
                 "headers": headers.iter().map(|(k,v)| (k.as_str(), String::from_utf8_lossy(v.as_bytes()).into_owned())).collect::<HashMap<_,_>>(),
                 "body": processed_payload.clone(),
+                "raw_body": raw_body,
                 "method": method.to_string(),
             }))
     .build() {
@@ -443,7 +621,7 @@ pub async fn run_workflow_version_and_respond(
     let (tx, rx) = oneshot::channel();
 
     // Store the sender in the state
-    {
+    if !async_mode {
         let mut completions = state.flow_completions.lock().await;
         completions.insert(
             flow_session_id.to_string(),
@@ -465,11 +643,29 @@ pub async fn run_workflow_version_and_respond(
 
     if let Err(e) = state.processor_sender.send(processor_message).await {
         println!("[TEST WORKFLOW] Failed to send message to processor: {}", e);
-        return (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to send message to processor: {}", e),
+        return respond_to_unanswered_run(
+            &state,
+            &workflow_id,
+            idempotency_key.as_deref(),
+            flow_session_id,
+            UnansweredRun::NotStarted,
         )
-            .into_response();
+        .await;
+    }
+    request_context.started_session(flow_session_id);
+
+    if async_mode {
+        println!("[WEBHOOK API] Workflow started in async mode");
+        spawn_completion_callback(&state, &rendered_inputs, &workflow_id, flow_session_id);
+        let accepted = accepted_result(&headers, &remote_addr, &workflow_id, flow_session_id);
+        if let Some(idempotency_key) = &idempotency_key {
+            if let Err(e) =
+                store_idempotent_response(&state, &workflow_id, idempotency_key, &accepted).await
+            {
+                println!("[WEBHOOK API] Failed to store idempotent response: {:?}", e);
+            }
+        }
+        return accepted_response(&accepted);
     }
 
     println!("[WEBHOOK API] Waiting for workflow completion");
@@ -478,18 +674,25 @@ pub async fn run_workflow_version_and_respond(
     match timeout(Duration::from_secs(WEBHOOK_TIMEOUT), rx).await {
         Ok(Ok(result)) => {
             println!("[WEBHOOK API] Received workflow result");
+            if let Some(idempotency_key) = &idempotency_key {
+                if let Err(e) =
+                    store_idempotent_response(&state, &workflow_id, idempotency_key, &result).await
+                {
+                    println!("[WEBHOOK API] Failed to store idempotent response: {:?}", e);
+                }
+            }
             parse_response_action_response_into_api_response(result).into_response()
         }
         Ok(Err(_)) => {
             println!("[WEBHOOK API] Workflow channel closed unexpectedly");
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({
-                    "error": "Workflow execution channel closed unexpectedly",
-                    "workflow_session_id": task.flow_session_id
-                })),
+            respond_to_unanswered_run(
+                &state,
+                &workflow_id,
+                idempotency_key.as_deref(),
+                flow_session_id,
+                UnansweredRun::ChannelClosed,
             )
-                .into_response()
+            .await
         }
         Err(_) => {
             println!("[WEBHOOK API] Workflow timed out after 30 seconds");
@@ -498,15 +701,15 @@ pub async fn run_workflow_version_and_respond(
                 .flow_completions
                 .lock()
                 .await
-                .remove(&task.flow_session_id.to_string());
-            (
-                StatusCode::REQUEST_TIMEOUT,
-                Json(json!({
-                    "error": "Workflow execution timed out",
-                    "workflow_session_id": task.flow_session_id
-                })),
+                .remove(&flow_session_id.to_string());
+            respond_to_unanswered_run(
+                &state,
+                &workflow_id,
+                idempotency_key.as_deref(),
+                flow_session_id,
+                UnansweredRun::TimedOut,
             )
-                .into_response()
+            .await
         }
     }
 }
@@ -515,9 +718,11 @@ pub async fn run_workflow(
     method: Method,
     Path(workflow_id): Path<String>,
     State(state): State<Arc<AppState>>,
+    ConnectInfo(remote_addr): ConnectInfo<SocketAddr>,
+    Extension(request_context): Extension<WebhookRequestContext>,
     headers: HeaderMap,
     query: Option<Query<HashMap<String, String>>>,
-    body: Option<Json<Value>>,
+    body: Bytes,
 ) -> impl IntoResponse {
     println!("[WEBHOOK API] Handling run workflow and respond");
     println!("[WEBHOOK API] Payload: {:?}", body);
@@ -537,7 +742,7 @@ pub async fn run_workflow(
         .eq("flow_id", workflow_id.clone())
         .eq("published", "true")
         .auth(supabase_service_role_api_key.clone())
-        .select("*")
+        .select("*, flow:flows(paused, paused_response)")
         .single()
         .execute()
         .await
@@ -583,6 +788,10 @@ pub async fn run_workflow(
     // Get account_id from workflow_version
     let account_id = workflow_version.account_id.clone();
 
+    if let Some(response) = validate_workflow_not_paused(&workflow_version) {
+        return response.into_response();
+    }
+
     // Validate the webhook trigger node and outputs
     let (trigger_node, _output_node) = match validate_required_input_and_response_plugins(
         &workflow_version.flow_definition,
@@ -631,10 +840,19 @@ pub async fn run_workflow(
 
     println!("[WEBHOOK API] Bundled context: {:?}", rendered_inputs);
 
-    //Validate security model
-    if let Some(response) = validate_security_model(&rendered_inputs, &headers, state.clone()).await
-    {
-        return response.into_response();
+    //Validate security model, replays come from an account member with the credentials redacted
+    if !request_context.is_replay() {
+        if let Some(response) = validate_security_model(
+            &rendered_inputs,
+            &headers,
+            &body,
+            &remote_addr,
+            state.clone(),
+        )
+        .await
+        {
+            return response.into_response();
+        }
     }
 
     // Validate request method
@@ -642,7 +860,59 @@ pub async fn run_workflow(
         return response.into_response();
     }
 
-    let processed_payload = convert_request_to_payload(method.clone(), query, body);
+    // Form, multipart and XML bodies become JSON
+    let parsed_body = match parse_request_body(&headers, &body).await {
+        Ok(parsed_body) => parsed_body,
+        Err(message) => return (StatusCode::BAD_REQUEST, message).into_response(),
+    };
+
+    let processed_payload =
+        convert_request_to_payload(method.clone(), query.clone(), parsed_body.value().map(Json));
+    let raw_body = raw_body(&headers, &body);
+
+    // Retries carrying the same idempotency key get the original run back, replays always run again
+    let idempotency_key = if request_context.is_replay() {
+        None
+    } else {
+        match claim_webhook_idempotency_key(
+            &state,
+            &rendered_inputs,
+            &headers,
+            &processed_payload,
+            &workflow_id,
+            flow_session_id,
+            false,
+        )
+        .await
+        {
+            Ok(idempotency_key) => idempotency_key,
+            Err(response) => return response,
+        }
+    };
+
+    // Uploaded files are stored with the account's files once the request isn't a duplicate
+    let processed_payload = if parsed_body.has_files() {
+        let stored_body = match parsed_body
+            .store_files(&state, &account_id.to_string(), &flow_session_id)
+            .await
+        {
+            Ok(stored_body) => stored_body,
+            Err(e) => {
+                println!("[WEBHOOK API] Failed to store uploaded files: {:?}", e);
+                return respond_to_unanswered_run(
+                    &state,
+                    &workflow_id,
+                    idempotency_key.as_deref(),
+                    flow_session_id,
+                    UnansweredRun::FilesNotStored,
+                )
+                .await;
+            }
+        };
+        convert_request_to_payload(method.clone(), query, stored_body.map(Json))
+    } else {
+        processed_payload
+    };
 
     // Create a task to initiate the flow
     println!("[WEBHOOK API] Creating task for workflow execution");
@@ -666,6 +936,7 @@ pub async fn run_workflow(
     .result(json!({
                 "headers": headers.iter().map(|(k,v)| (k.as_str(), String::from_utf8_lossy(v.as_bytes()).into_owned())).collect::<HashMap<_,_>>(),
                 "body": processed_payload.clone(),
+                "raw_body": raw_body,
                 "method": method.to_string(),
             }))
     .build() {
@@ -686,12 +957,16 @@ pub async fn run_workflow(
 
     if let Err(e) = state.processor_sender.send(processor_message).await {
         println!("[TEST WORKFLOW] Failed to send message to processor: {}", e);
-        return (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to send message to processor: {}", e),
+        return respond_to_unanswered_run(
+            &state,
+            &workflow_id,
+            idempotency_key.as_deref(),
+            flow_session_id,
+            UnansweredRun::NotStarted,
         )
-            .into_response();
+        .await;
     }
+    request_context.started_session(flow_session_id);
 
     println!("[WEBHOOK API] Task created successfully");
     Json(serde_json::json!({
@@ -708,9 +983,11 @@ pub async fn run_workflow_version(
     method: Method,
     Path((workflow_id, workflow_version_id)): Path<(String, String)>,
     State(state): State<Arc<AppState>>,
+    ConnectInfo(remote_addr): ConnectInfo<SocketAddr>,
+    Extension(request_context): Extension<WebhookRequestContext>,
     headers: HeaderMap,
     query: Option<Query<HashMap<String, String>>>,
-    body: Option<Json<Value>>,
+    body: Bytes,
 ) -> impl IntoResponse {
     println!("[WEBHOOK API] Handling run workflow and respond");
     println!("[WEBHOOK API] Payload: {:?}", body);
@@ -730,7 +1007,7 @@ pub async fn run_workflow_version(
         .eq("flow_id", workflow_id.clone())
         .eq("flow_version_id", workflow_version_id.clone())
         .auth(supabase_service_role_api_key.clone())
-        .select("*")
+        .select("*, flow:flows(paused, paused_response)")
         .single()
         .execute()
         .await
@@ -776,6 +1053,10 @@ pub async fn run_workflow_version(
     // Get account_id from workflow_version
     let account_id = workflow_version.account_id.clone();
 
+    if let Some(response) = validate_workflow_not_paused(&workflow_version) {
+        return response.into_response();
+    }
+
     // Validate the webhook trigger node and outputs
     let (trigger_node, _output_node) = match validate_required_input_and_response_plugins(
         &workflow_version.flow_definition,
@@ -824,10 +1105,19 @@ pub async fn run_workflow_version(
 
     println!("[WEBHOOK API] Bundled context: {:?}", rendered_inputs);
 
-    //Validate security model
-    if let Some(response) = validate_security_model(&rendered_inputs, &headers, state.clone()).await
-    {
-        return response.into_response();
+    //Validate security model, replays come from an account member with the credentials redacted
+    if !request_context.is_replay() {
+        if let Some(response) = validate_security_model(
+            &rendered_inputs,
+            &headers,
+            &body,
+            &remote_addr,
+            state.clone(),
+        )
+        .await
+        {
+            return response.into_response();
+        }
     }
 
     // Validate request method
@@ -835,7 +1125,59 @@ pub async fn run_workflow_version(
         return response.into_response();
     }
 
-    let processed_payload = convert_request_to_payload(method.clone(), query, body);
+    // Form, multipart and XML bodies become JSON
+    let parsed_body = match parse_request_body(&headers, &body).await {
+        Ok(parsed_body) => parsed_body,
+        Err(message) => return (StatusCode::BAD_REQUEST, message).into_response(),
+    };
+
+    let processed_payload =
+        convert_request_to_payload(method.clone(), query.clone(), parsed_body.value().map(Json));
+    let raw_body = raw_body(&headers, &body);
+
+    // Retries carrying the same idempotency key get the original run back, replays always run again
+    let idempotency_key = if request_context.is_replay() {
+        None
+    } else {
+        match claim_webhook_idempotency_key(
+            &state,
+            &rendered_inputs,
+            &headers,
+            &processed_payload,
+            &workflow_id,
+            flow_session_id,
+            false,
+        )
+        .await
+        {
+            Ok(idempotency_key) => idempotency_key,
+            Err(response) => return response,
+        }
+    };
+
+    // Uploaded files are stored with the account's files once the request isn't a duplicate
+    let processed_payload = if parsed_body.has_files() {
+        let stored_body = match parsed_body
+            .store_files(&state, &account_id.to_string(), &flow_session_id)
+            .await
+        {
+            Ok(stored_body) => stored_body,
+            Err(e) => {
+                println!("[WEBHOOK API] Failed to store uploaded files: {:?}", e);
+                return respond_to_unanswered_run(
+                    &state,
+                    &workflow_id,
+                    idempotency_key.as_deref(),
+                    flow_session_id,
+                    UnansweredRun::FilesNotStored,
+                )
+                .await;
+            }
+        };
+        convert_request_to_payload(method.clone(), query, stored_body.map(Json))
+    } else {
+        processed_payload
+    };
 
     // Create a task to initiate the flow
     println!("[WEBHOOK API] Creating task for workflow execution");
@@ -859,6 +1201,7 @@ pub async fn run_workflow_version(
     .result(json!({
                 "headers": headers.iter().map(|(k,v)| (k.as_str(), String::from_utf8_lossy(v.as_bytes()).into_owned())).collect::<HashMap<_,_>>(),
                 "body": processed_payload.clone(),
+                "raw_body": raw_body,
                 "method": method.to_string(),
             }))
     .build() {
@@ -879,12 +1222,16 @@ pub async fn run_workflow_version(
 
     if let Err(e) = state.processor_sender.send(processor_message).await {
         println!("[TEST WORKFLOW] Failed to send message to processor: {}", e);
-        return (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to send message to processor: {}", e),
+        return respond_to_unanswered_run(
+            &state,
+            &workflow_id,
+            idempotency_key.as_deref(),
+            flow_session_id,
+            UnansweredRun::NotStarted,
         )
-            .into_response();
+        .await;
     }
+    request_context.started_session(flow_session_id);
 
     println!("[WEBHOOK API] Task created successfully");
     Json(serde_json::json!({
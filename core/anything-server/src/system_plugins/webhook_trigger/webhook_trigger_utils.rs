@@ -5,16 +5,31 @@ use axum::{
     Json,
 };
 
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use ipnet::IpNet;
+use jsonwebtoken::{
+    decode, decode_header,
+    jwk::{AlgorithmParameters, EllipticCurve, Jwk, JwkSet},
+    Algorithm, DecodingKey, Validation,
+};
 use serde_json::{json, Value};
+use sha1::Sha1;
+use sha2::Sha256;
 
 use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
 
 use std::sync::Arc;
 
 use crate::{
     secrets::get_secret_by_secret_value,
     types::action_types::{Action, ActionType, PluginName},
-    types::workflow_types::WorkflowVersionDefinition,
+    trigger_engine::is_flow_paused,
+    types::workflow_types::{DatabaseFlowVersion, WorkflowVersionDefinition},
     AppState, CachedApiKey,
 };
 
@@ -133,6 +148,8 @@ pub async fn validate_api_key(state: Arc<AppState>, api_key: String) -> Result<S
 pub async fn validate_security_model(
     rendered_inputs: &Value,
     headers: &HeaderMap,
+    body: &[u8],
+    remote_addr: &SocketAddr,
     state: Arc<AppState>,
 ) -> Option<impl IntoResponse> {
     // Extract the security model from the rendered inputs
@@ -256,6 +273,41 @@ pub async fn validate_security_model(
             }
             None
         }
+        "hmac_signature" => {
+            println!("[WEBHOOK API] Validating HMAC signature");
+            match validate_hmac_signature(rendered_inputs, headers, body) {
+                Ok(()) => None,
+                Err(message) => {
+                    println!("[WEBHOOK API] HMAC signature check failed: {}", message);
+                    Some((StatusCode::UNAUTHORIZED, message).into_response())
+                }
+            }
+        }
+        "jwt" => {
+            println!("[WEBHOOK API] Validating JWT");
+            match validate_jwt(rendered_inputs, headers) {
+                Ok(()) => None,
+                Err(message) => {
+                    println!("[WEBHOOK API] JWT check failed: {}", message);
+                    Some((StatusCode::UNAUTHORIZED, message).into_response())
+                }
+            }
+        }
+        "ip_allowlist" => {
+            println!("[WEBHOOK API] Validating IP allowlist");
+            match validate_ip_allowlist(
+                rendered_inputs,
+                headers,
+                remote_addr,
+                &server_trusted_proxies(),
+            ) {
+                Ok(()) => None,
+                Err(message) => {
+                    println!("[WEBHOOK API] IP allowlist check failed: {}", message);
+                    Some((StatusCode::FORBIDDEN, message).into_response())
+                }
+            }
+        }
         _ => {
             println!("[WEBHOOK API] Invalid security model specified");
             Some((StatusCode::BAD_REQUEST, "Invalid security model").into_response())
@@ -263,6 +315,295 @@ pub async fn validate_security_model(
     }
 }
 
+// Checks the sender's signature over the raw body, e.g. GitHub's X-Hub-Signature-256.
+// Slack style signatures that cover a timestamp use a signed content like `v0:{timestamp}:{body}`.
+pub fn validate_hmac_signature(
+    rendered_inputs: &Value,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Result<(), String> {
+    let input = |key: &str| {
+        rendered_inputs
+            .get(key)
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    };
+
+    let secret = input("hmac_secret").ok_or("Invalid signature configuration")?;
+    let header_name = input("hmac_header").unwrap_or("X-Hub-Signature-256");
+    let algorithm = input("hmac_algorithm").unwrap_or("sha256").to_lowercase();
+    let encoding = input("hmac_encoding").unwrap_or("hex").to_lowercase();
+    let prefix = input("hmac_signature_prefix").unwrap_or("");
+    let signed_content = input("hmac_signed_content").unwrap_or("{body}");
+    let tolerance = rendered_inputs
+        .get("hmac_timestamp_tolerance")
+        .and_then(|value| {
+            value
+                .as_i64()
+                .or_else(|| value.as_str().and_then(|s| s.trim().parse().ok()))
+        })
+        .unwrap_or(0);
+
+    let header_value = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+    };
+
+    let signature = header_value(header_name).ok_or("Missing signature header")?;
+    let signature = signature.strip_prefix(prefix).unwrap_or(signature);
+    let signature = match encoding.as_str() {
+        "hex" => hex::decode(signature).map_err(|_| "Invalid signature encoding")?,
+        "base64" => STANDARD
+            .decode(signature)
+            .map_err(|_| "Invalid signature encoding")?,
+        _ => return Err("Invalid signature configuration".to_string()),
+    };
+
+    let timestamp = match input("hmac_timestamp_header") {
+        Some(name) => Some(header_value(name).ok_or("Missing timestamp header")?),
+        None => None,
+    };
+
+    // Replay protection, a captured request stops verifying once it is old enough
+    if tolerance > 0 {
+        let sent_at: i64 = timestamp
+            .and_then(|timestamp| timestamp.parse().ok())
+            .ok_or("Missing or invalid timestamp")?;
+        if (Utc::now().timestamp() - sent_at).abs() > tolerance {
+            return Err("Request timestamp outside tolerance".to_string());
+        }
+    }
+
+    // Everything around {body} is small, the body itself is used as is so it is never re-encoded
+    let (before, after) = signed_content
+        .split_once("{body}")
+        .ok_or("Invalid signature configuration")?;
+    let timestamp = timestamp.unwrap_or("");
+    let before = before.replace("{timestamp}", timestamp);
+    let after = after.replace("{timestamp}", timestamp);
+
+    let verified = match algorithm.as_str() {
+        "sha256" => verify_hmac::<Hmac<Sha256>>(secret, &before, body, &after, &signature),
+        "sha1" => verify_hmac::<Hmac<Sha1>>(secret, &before, body, &after, &signature),
+        _ => return Err("Invalid signature configuration".to_string()),
+    };
+
+    if !verified {
+        return Err("Invalid signature".to_string());
+    }
+
+    Ok(())
+}
+
+fn verify_hmac<M: Mac + hmac::digest::KeyInit>(
+    secret: &str,
+    before: &str,
+    body: &[u8],
+    after: &str,
+    signature: &[u8],
+) -> bool {
+    let mut mac = match <M as hmac::digest::KeyInit>::new_from_slice(secret.as_bytes()) {
+        Ok(mac) => mac,
+        Err(_) => return false,
+    };
+    mac.update(before.as_bytes());
+    mac.update(body);
+    mac.update(after.as_bytes());
+    // Constant time comparison
+    mac.verify_slice(signature).is_ok()
+}
+
+// Bearer tokens from the partner's IdP. Signed with a shared HS256 secret or a key from a JWKS document.
+// Expiry is always checked, issuer and audience only when configured.
+pub fn validate_jwt(rendered_inputs: &Value, headers: &HeaderMap) -> Result<(), String> {
+    let input = |key: &str| {
+        rendered_inputs
+            .get(key)
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    };
+
+    let token = headers
+        .get("Authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+        .ok_or("Missing bearer token")?;
+
+    let header = decode_header(token).map_err(|_| "Invalid token")?;
+
+    let (key, algorithm) = match (input("jwt_secret"), input("jwt_jwks")) {
+        (Some(secret), _) => (
+            DecodingKey::from_secret(secret.as_bytes()),
+            Algorithm::HS256,
+        ),
+        (None, Some(jwks)) => {
+            let jwks: JwkSet =
+                serde_json::from_str(jwks).map_err(|_| "Invalid token configuration")?;
+            let jwk = match &header.kid {
+                Some(kid) => jwks.find(kid),
+                // Without a kid only an unambiguous document works
+                None if jwks.keys.len() == 1 => jwks.keys.first(),
+                None => None,
+            }
+            .ok_or("Unknown signing key")?;
+            let key = DecodingKey::from_jwk(jwk).map_err(|_| "Invalid token configuration")?;
+            // The key decides the algorithm, the token only picks within what the key allows
+            let algorithms = jwk_algorithms(jwk);
+            if algorithms.is_empty() {
+                return Err("Invalid token configuration".to_string());
+            }
+            if !algorithms.contains(&header.alg) {
+                return Err("Invalid token".to_string());
+            }
+            (key, header.alg)
+        }
+        (None, None) => return Err("Invalid token configuration".to_string()),
+    };
+
+    let mut validation = Validation::new(algorithm);
+    match input("jwt_issuer") {
+        Some(issuer) => validation.set_issuer(&[issuer]),
+        None => validation.iss = None,
+    }
+    match input("jwt_audience") {
+        Some(audience) => validation.set_audience(&[audience]),
+        None => validation.validate_aud = false,
+    }
+
+    decode::<Value>(token, &key, &validation).map_err(|e| match e.kind() {
+        jsonwebtoken::errors::ErrorKind::ExpiredSignature => "Token expired".to_string(),
+        jsonwebtoken::errors::ErrorKind::InvalidIssuer => "Invalid token issuer".to_string(),
+        jsonwebtoken::errors::ErrorKind::InvalidAudience => "Invalid token audience".to_string(),
+        _ => "Invalid token".to_string(),
+    })?;
+
+    Ok(())
+}
+
+// The key's alg when it has one, otherwise whatever its key type and curve can verify
+fn jwk_algorithms(jwk: &Jwk) -> Vec<Algorithm> {
+    if let Some(algorithm) = jwk.common.key_algorithm {
+        // Encryption algorithms don't parse and leave the key unusable
+        return Algorithm::from_str(&algorithm.to_string())
+            .into_iter()
+            .collect();
+    }
+
+    match &jwk.algorithm {
+        AlgorithmParameters::OctetKey(_) => {
+            vec![Algorithm::HS256, Algorithm::HS384, Algorithm::HS512]
+        }
+        AlgorithmParameters::RSA(_) => vec![
+            Algorithm::RS256,
+            Algorithm::RS384,
+            Algorithm::RS512,
+            Algorithm::PS256,
+            Algorithm::PS384,
+            Algorithm::PS512,
+        ],
+        AlgorithmParameters::EllipticCurve(params) => match params.curve {
+            EllipticCurve::P256 => vec![Algorithm::ES256],
+            EllipticCurve::P384 => vec![Algorithm::ES384],
+            _ => Vec::new(),
+        },
+        AlgorithmParameters::OctetKeyPair(params) => match params.curve {
+            EllipticCurve::Ed25519 => vec![Algorithm::EdDSA],
+            _ => Vec::new(),
+        },
+    }
+}
+
+// Allowed ranges are CIDRs or single addresses separated by commas or new lines.
+// Behind a proxy the client address comes from the configured header instead of the socket, but
+// only when the socket address is a trusted proxy. Anyone else could just send the header.
+// Proxies the server itself runs behind are trusted through the TRUSTED_PROXIES env var.
+pub fn validate_ip_allowlist(
+    rendered_inputs: &Value,
+    headers: &HeaderMap,
+    remote_addr: &SocketAddr,
+    server_trusted_proxies: &[IpNet],
+) -> Result<(), String> {
+    let input = |key: &str| {
+        rendered_inputs
+            .get(key)
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    };
+
+    let ranges = parse_ip_ranges(input("ip_allowlist").unwrap_or(""))
+        .ok_or("Invalid IP allowlist configuration")?;
+    if ranges.is_empty() {
+        return Err("Invalid IP allowlist configuration".to_string());
+    }
+
+    let mut trusted_proxies = parse_ip_ranges(input("trusted_proxies").unwrap_or(""))
+        .ok_or("Invalid trusted proxies configuration")?;
+    trusted_proxies.extend_from_slice(server_trusted_proxies);
+    let is_trusted_proxy = |ip: &IpAddr| trusted_proxies.iter().any(|range| range.contains(ip));
+
+    // IPv4 clients on a dual stack socket show up as ::ffff:a.b.c.d
+    let peer_ip = remote_addr.ip().to_canonical();
+
+    let client_ip = match input("trusted_proxy_header") {
+        Some(name) if is_trusted_proxy(&peer_ip) => {
+            // Every proxy appends the address it got the request from. Walking back from the
+            // last entry, the first address that isn't one of our proxies is the client.
+            let forwarded: Vec<IpAddr> = headers
+                .get_all(name)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(','))
+                .map(|ip| ip.trim().parse::<IpAddr>().map(|ip| ip.to_canonical()))
+                .collect::<Result<_, _>>()
+                .map_err(|_| "Invalid client address")?;
+            forwarded
+                .iter()
+                .rev()
+                .find(|ip| !is_trusted_proxy(ip))
+                .or(forwarded.first())
+                .copied()
+                .ok_or("Missing client address")?
+        }
+        _ => peer_ip,
+    };
+
+    if !ranges.iter().any(|range| range.contains(&client_ip)) {
+        return Err("IP address not allowed".to_string());
+    }
+
+    Ok(())
+}
+
+// CIDRs or single addresses separated by commas or new lines, None if any of them is invalid
+pub fn parse_ip_ranges(ranges: &str) -> Option<Vec<IpNet>> {
+    ranges
+        .split([',', '\n'])
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            entry
+                .parse::<IpNet>()
+                .or_else(|_| entry.parse::<IpAddr>().map(IpNet::from))
+                .ok()
+        })
+        .collect()
+}
+
+// Proxies in front of this server, e.g. the load balancer, from the TRUSTED_PROXIES env var
+pub fn server_trusted_proxies() -> Vec<IpNet> {
+    let trusted_proxies = std::env::var("TRUSTED_PROXIES").unwrap_or_default();
+    parse_ip_ranges(&trusted_proxies).unwrap_or_else(|| {
+        println!("[WEBHOOK API] Invalid TRUSTED_PROXIES, not trusting any proxy");
+        Vec::new()
+    })
+}
+
 pub fn validate_request_method(
     rendered_inputs: &Value,
     request_method: &str,
@@ -303,6 +644,63 @@ pub fn validate_request_method(
     None
 }
 
+// Paused workflows answer with their configured paused_response instead of starting a run
+pub fn validate_workflow_not_paused(
+    workflow_version: &DatabaseFlowVersion,
+) -> Option<impl IntoResponse> {
+    if !is_flow_paused(workflow_version) {
+        return None;
+    }
+
+    println!(
+        "[WEBHOOK API] Workflow {} is paused, skipping run",
+        workflow_version.flow_id
+    );
+
+    let paused_response = workflow_version
+        .flow
+        .as_ref()
+        .and_then(|flow| flow.get("paused_response"))
+        .filter(|response| response.is_object());
+
+    let status_code = paused_response
+        .and_then(|response| response.get("status_code"))
+        .and_then(Value::as_u64)
+        .and_then(|code| StatusCode::from_u16(code as u16).ok())
+        .unwrap_or(StatusCode::SERVICE_UNAVAILABLE);
+
+    let mut headers = HeaderMap::new();
+    if let Some(configured) = paused_response
+        .and_then(|response| response.get("headers"))
+        .and_then(Value::as_object)
+    {
+        for (name, value) in configured {
+            if let (Ok(name), Some(Ok(value))) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                value.as_str().map(HeaderValue::from_str),
+            ) {
+                headers.insert(name, value);
+            }
+        }
+    }
+
+    let response = match paused_response.and_then(|response| response.get("body")) {
+        Some(Value::String(body)) => (status_code, headers, body.clone()).into_response(),
+        Some(body) => (status_code, headers, Json(body.clone())).into_response(),
+        None => (
+            status_code,
+            headers,
+            Json(json!({
+                "error": "Workflow is paused",
+                "workflow_id": workflow_version.flow_id
+            })),
+        )
+            .into_response(),
+    };
+
+    Some(response)
+}
+
 pub fn convert_request_to_payload(
     method: axum::http::Method,
     query: Option<Query<HashMap<String, String>>>,
@@ -501,3 +899,324 @@ pub fn parse_response_action_response_into_api_response(stored_result: Value) ->
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+
+    fn headers(pairs: &[(&str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(
+                HeaderName::from_str(name).unwrap(),
+                HeaderValue::from_str(value).unwrap(),
+            );
+        }
+        headers
+    }
+
+    fn addr(ip: &str) -> SocketAddr {
+        SocketAddr::new(ip.parse().unwrap(), 443)
+    }
+
+    #[test]
+    fn test_ip_allowlist_uses_socket_address() {
+        let inputs = json!({ "ip_allowlist": "10.0.0.0/8, 192.168.1.7" });
+        let none = HeaderMap::new();
+
+        assert!(validate_ip_allowlist(&inputs, &none, &addr("10.1.2.3"), &[]).is_ok());
+        assert!(validate_ip_allowlist(&inputs, &none, &addr("192.168.1.7"), &[]).is_ok());
+        assert!(validate_ip_allowlist(&inputs, &none, &addr("::ffff:10.1.2.3"), &[]).is_ok());
+        assert_eq!(
+            validate_ip_allowlist(&inputs, &none, &addr("8.8.8.8"), &[]),
+            Err("IP address not allowed".to_string())
+        );
+
+        for allowlist in ["", "10.0.0.0/33", "not an address"] {
+            assert_eq!(
+                validate_ip_allowlist(
+                    &json!({ "ip_allowlist": allowlist }),
+                    &none,
+                    &addr("10.1.2.3"),
+                    &[]
+                ),
+                Err("Invalid IP allowlist configuration".to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn test_ip_allowlist_ignores_proxy_header_from_untrusted_peers() {
+        let inputs = json!({
+            "ip_allowlist": "10.0.0.0/8",
+            "trusted_proxy_header": "X-Forwarded-For",
+            "trusted_proxies": "172.16.0.0/12"
+        });
+        let spoofed = headers(&[("X-Forwarded-For", "10.1.2.3")]);
+
+        assert_eq!(
+            validate_ip_allowlist(&inputs, &spoofed, &addr("8.8.8.8"), &[]),
+            Err("IP address not allowed".to_string())
+        );
+
+        // Without trusted proxies the header is never read
+        let no_proxies = json!({
+            "ip_allowlist": "10.0.0.0/8",
+            "trusted_proxy_header": "X-Forwarded-For"
+        });
+        assert_eq!(
+            validate_ip_allowlist(&no_proxies, &spoofed, &addr("172.16.0.5"), &[]),
+            Err("IP address not allowed".to_string())
+        );
+    }
+
+    #[test]
+    fn test_ip_allowlist_reads_proxy_header_from_trusted_proxies() {
+        let inputs = json!({
+            "ip_allowlist": "10.0.0.0/8",
+            "trusted_proxy_header": "X-Forwarded-For",
+            "trusted_proxies": "172.16.0.0/12"
+        });
+        let proxy = addr("172.16.0.5");
+        let check = |forwarded: &str| {
+            validate_ip_allowlist(
+                &inputs,
+                &headers(&[("X-Forwarded-For", forwarded)]),
+                &proxy,
+                &[],
+            )
+        };
+
+        // Our proxy appends the address it saw, whatever the client put before it doesn't count
+        assert!(check("8.8.8.8, 10.1.2.3").is_ok());
+        assert!(check("10.1.2.3, 8.8.8.8").is_err());
+        // Chained trusted proxies are skipped
+        assert!(check("10.1.2.3, 172.16.0.9").is_ok());
+        assert_eq!(check("junk"), Err("Invalid client address".to_string()));
+        assert_eq!(
+            validate_ip_allowlist(&inputs, &HeaderMap::new(), &proxy, &[]),
+            Err("Missing client address".to_string())
+        );
+
+        // Proxies the server runs behind are trusted for every workflow
+        let server_proxies = vec!["172.16.0.0/12".parse().unwrap()];
+        let header_only = json!({
+            "ip_allowlist": "10.0.0.0/8",
+            "trusted_proxy_header": "X-Forwarded-For"
+        });
+        assert!(validate_ip_allowlist(
+            &header_only,
+            &headers(&[("X-Forwarded-For", "8.8.8.8, 10.1.2.3")]),
+            &proxy,
+            &server_proxies
+        )
+        .is_ok());
+    }
+
+    fn hmac_hex(secret: &str, content: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(content);
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    #[test]
+    fn test_hmac_signature() {
+        let body = br#"{"action":"opened"}"#;
+        let inputs = json!({ "hmac_secret": "secret", "hmac_signature_prefix": "sha256=" });
+        let signed = headers(&[(
+            "X-Hub-Signature-256",
+            &format!("sha256={}", hmac_hex("secret", body)),
+        )]);
+
+        assert!(validate_hmac_signature(&inputs, &signed, body).is_ok());
+        assert_eq!(
+            validate_hmac_signature(&inputs, &signed, br#"{"action":"closed"}"#),
+            Err("Invalid signature".to_string())
+        );
+        assert_eq!(
+            validate_hmac_signature(&inputs, &HeaderMap::new(), body),
+            Err("Missing signature header".to_string())
+        );
+        assert_eq!(
+            validate_hmac_signature(
+                &inputs,
+                &headers(&[("X-Hub-Signature-256", "sha256=zz")]),
+                body
+            ),
+            Err("Invalid signature encoding".to_string())
+        );
+        assert_eq!(
+            validate_hmac_signature(&json!({}), &signed, body),
+            Err("Invalid signature configuration".to_string())
+        );
+    }
+
+    #[test]
+    fn test_hmac_signature_with_timestamp() {
+        let body = b"token=abc&team_id=T1";
+        let inputs = json!({
+            "hmac_secret": "secret",
+            "hmac_header": "X-Slack-Signature",
+            "hmac_signature_prefix": "v0=",
+            "hmac_timestamp_header": "X-Slack-Request-Timestamp",
+            "hmac_signed_content": "v0:{timestamp}:{body}",
+            "hmac_timestamp_tolerance": 300
+        });
+        let signed_at = |timestamp: i64| {
+            let content = format!("v0:{}:{}", timestamp, std::str::from_utf8(body).unwrap());
+            headers(&[
+                (
+                    "X-Slack-Signature",
+                    &format!("v0={}", hmac_hex("secret", content.as_bytes())),
+                ),
+                ("X-Slack-Request-Timestamp", &timestamp.to_string()),
+            ])
+        };
+
+        let now = Utc::now().timestamp();
+        assert!(validate_hmac_signature(&inputs, &signed_at(now), body).is_ok());
+        assert_eq!(
+            validate_hmac_signature(&inputs, &signed_at(now - 600), body),
+            Err("Request timestamp outside tolerance".to_string())
+        );
+    }
+
+    fn bearer(claims: Value, header: Header, secret: &str) -> HeaderMap {
+        let token = encode(
+            &header,
+            &claims,
+            &EncodingKey::from_secret(secret.as_bytes()),
+        )
+        .unwrap();
+        headers(&[("Authorization", &format!("Bearer {}", token))])
+    }
+
+    #[test]
+    fn test_jwt_with_shared_secret() {
+        let inputs = json!({
+            "jwt_secret": "secret",
+            "jwt_issuer": "https://idp.example.com",
+            "jwt_audience": "anything"
+        });
+        let claims = |exp: i64, iss: &str, aud: &str| json!({ "exp": exp, "iss": iss, "aud": aud });
+        let exp = Utc::now().timestamp() + 600;
+        let check = |claims: Value, secret: &str| {
+            validate_jwt(&inputs, &bearer(claims, Header::default(), secret))
+        };
+
+        assert!(check(claims(exp, "https://idp.example.com", "anything"), "secret").is_ok());
+        assert_eq!(
+            check(claims(exp, "https://idp.example.com", "anything"), "other"),
+            Err("Invalid token".to_string())
+        );
+        assert_eq!(
+            check(
+                claims(exp - 1200, "https://idp.example.com", "anything"),
+                "secret"
+            ),
+            Err("Token expired".to_string())
+        );
+        assert_eq!(
+            check(
+                claims(exp, "https://evil.example.com", "anything"),
+                "secret"
+            ),
+            Err("Invalid token issuer".to_string())
+        );
+        assert_eq!(
+            check(claims(exp, "https://idp.example.com", "other"), "secret"),
+            Err("Invalid token audience".to_string())
+        );
+        assert_eq!(
+            validate_jwt(&inputs, &HeaderMap::new()),
+            Err("Missing bearer token".to_string())
+        );
+        assert_eq!(
+            validate_jwt(
+                &json!({}),
+                &bearer(
+                    claims(exp, "https://idp.example.com", "anything"),
+                    Header::default(),
+                    "secret"
+                )
+            ),
+            Err("Invalid token configuration".to_string())
+        );
+    }
+
+    #[test]
+    fn test_jwt_with_jwks() {
+        let jwks = json!({
+            "keys": [{ "kty": "oct", "kid": "key-1", "alg": "HS256", "k": URL_SAFE_NO_PAD.encode("secret") }]
+        });
+        let inputs = json!({ "jwt_jwks": jwks.to_string() });
+        let claims = json!({ "exp": Utc::now().timestamp() + 600 });
+        let with_kid = |kid: &str| Header {
+            kid: Some(kid.to_string()),
+            ..Header::default()
+        };
+
+        assert!(validate_jwt(
+            &inputs,
+            &bearer(claims.clone(), with_kid("key-1"), "secret")
+        )
+        .is_ok());
+        // A lone key is used when the token names none
+        assert!(validate_jwt(
+            &inputs,
+            &bearer(claims.clone(), Header::default(), "secret")
+        )
+        .is_ok());
+        assert_eq!(
+            validate_jwt(&inputs, &bearer(claims, with_kid("key-2"), "secret")),
+            Err("Unknown signing key".to_string())
+        );
+    }
+
+    #[test]
+    fn test_jwt_with_jwks_key_without_alg() {
+        let jwks = json!({
+            "keys": [
+                { "kty": "oct", "kid": "oct", "k": URL_SAFE_NO_PAD.encode("secret") },
+                { "kty": "RSA", "kid": "rsa", "n": URL_SAFE_NO_PAD.encode("secret"), "e": "AQAB" },
+                { "kty": "EC", "kid": "p521", "crv": "P-521", "x": "AQAB", "y": "AQAB" }
+            ]
+        });
+        let inputs = json!({ "jwt_jwks": jwks.to_string() });
+        let claims = json!({ "exp": Utc::now().timestamp() + 600 });
+        let header = |kid: &str, alg: Algorithm| Header {
+            kid: Some(kid.to_string()),
+            ..Header::new(alg)
+        };
+
+        // Symmetric keys take any HMAC algorithm
+        assert!(validate_jwt(
+            &inputs,
+            &bearer(claims.clone(), header("oct", Algorithm::HS256), "secret")
+        )
+        .is_ok());
+        assert!(validate_jwt(
+            &inputs,
+            &bearer(claims.clone(), header("oct", Algorithm::HS512), "secret")
+        )
+        .is_ok());
+        // An RSA key never verifies an HMAC token signed with its public parts
+        assert_eq!(
+            validate_jwt(
+                &inputs,
+                &bearer(claims.clone(), header("rsa", Algorithm::HS256), "secret")
+            ),
+            Err("Invalid token".to_string())
+        );
+        // Curves no supported algorithm uses are rejected
+        assert_eq!(
+            validate_jwt(
+                &inputs,
+                &bearer(claims, header("p521", Algorithm::HS256), "secret")
+            ),
+            Err("Invalid token configuration".to_string())
+        );
+    }
+}
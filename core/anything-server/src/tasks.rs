@@ -1,14 +1,24 @@
 use axum::{
     extract::{Extension, Path, State},
     http::StatusCode,
-    response::IntoResponse,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse,
+    },
     Json,
 };
 
-use serde_json::Value;
+use futures::stream::{self, StreamExt};
+use serde_json::{json, Value};
+use std::convert::Infallible;
 use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::{timeout_at, Duration, Instant};
+use uuid::Uuid;
 
+use crate::status_updater::events::{FlowSessionEvent, FlowSessionEventType};
 use crate::supabase_jwt_middleware::User;
+use crate::types::task_types::{FlowSessionStatus, Task};
 use crate::AppState;
 
 //Task
@@ -117,3 +127,193 @@ pub async fn get_task_by_workflow_id(
 
     Json(item).into_response()
 }
+
+// Clients reconnect for longer sessions, they get a fresh snapshot when they do
+const SESSION_STREAM_MAX_DURATION: Duration = Duration::from_secs(60 * 60);
+
+fn to_sse_event(event_type: &str, data: &Value) -> Event {
+    Event::default().event(event_type).data(data.to_string())
+}
+
+// Tasks of the session stored so far, as the user sees them
+async fn fetch_session_tasks(
+    state: &Arc<AppState>,
+    jwt: &str,
+    account_id: &str,
+    flow_session_id: &str,
+) -> Result<Vec<Task>, Box<dyn std::error::Error + Send + Sync>> {
+    let response = state
+        .anything_client
+        .from("tasks")
+        .auth(jwt)
+        .eq("account_id", account_id)
+        .eq("flow_session_id", flow_session_id)
+        .select("*")
+        .order("processing_order.asc")
+        .execute()
+        .await?;
+
+    let body = response.text().await?;
+    Ok(serde_json::from_str(&body)?)
+}
+
+// The session_completed payload once every task has a final session status
+fn finished_status(tasks: &[Task]) -> Option<Value> {
+    let finished = !tasks.is_empty()
+        && tasks.iter().all(|task| {
+            matches!(
+                task.flow_session_status,
+                FlowSessionStatus::Completed
+                    | FlowSessionStatus::Failed
+                    | FlowSessionStatus::Canceled
+            )
+        });
+
+    finished.then(|| {
+        json!({
+            "flow_session_status": tasks[0].flow_session_status,
+            "trigger_session_status": tasks[0].trigger_session_status,
+        })
+    })
+}
+
+// Sessions with nothing stored yet only count when they are running here for the same account
+async fn is_running_for_account(
+    state: &Arc<AppState>,
+    account_id: &str,
+    session_id: &Uuid,
+) -> bool {
+    state
+        .flow_session_cache
+        .read()
+        .await
+        .get(session_id)
+        .is_some_and(|session| {
+            !session.tasks.is_empty()
+                && session
+                    .tasks
+                    .values()
+                    .all(|task| task.account_id.to_string() == account_id)
+        })
+}
+
+// Streams task created/started/completed/failed events for a flow session as Server-Sent Events.
+// Starts with a snapshot of the tasks stored so far and ends with a session_completed event.
+pub async fn stream_flow_session_events(
+    Path((account_id, flow_session_id)): Path<(String, String)>,
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+) -> impl IntoResponse {
+    println!(
+        "[TASKS] Streaming events for flow session {} in account {}",
+        flow_session_id, account_id
+    );
+
+    let session_id = match Uuid::parse_str(&flow_session_id) {
+        Ok(id) => id,
+        Err(_) => return (StatusCode::BAD_REQUEST, "Invalid flow session id").into_response(),
+    };
+
+    // Subscribe before reading the snapshot so nothing emitted in between is missed
+    let receiver = state.flow_session_events.subscribe();
+
+    let tasks = match fetch_session_tasks(&state, &user.jwt, &account_id, &flow_session_id).await {
+        Ok(tasks) => tasks,
+        Err(err) => {
+            println!("[TASKS] Failed to get session tasks: {:?}", err);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to get session tasks",
+            )
+                .into_response();
+        }
+    };
+
+    // Live events carry no account, so only stream sessions known to belong to this one
+    if tasks.is_empty() && !is_running_for_account(&state, &account_id, &session_id).await {
+        return (StatusCode::NOT_FOUND, "Flow session not found").into_response();
+    }
+
+    // Session already finished, nothing left to stream after the snapshot
+    let finished_status = finished_status(&tasks);
+
+    let mut initial_events = vec![to_sse_event("snapshot", &json!({ "tasks": tasks }))];
+    if let Some(status) = &finished_status {
+        initial_events.push(to_sse_event(
+            FlowSessionEventType::SessionCompleted.as_str(),
+            status,
+        ));
+    }
+
+    let deadline = Instant::now() + SESSION_STREAM_MAX_DURATION;
+    let live_events = stream::unfold(
+        (receiver, finished_status.is_some()),
+        move |(mut receiver, finished)| {
+            let state = state.clone();
+            let jwt = user.jwt.clone();
+            let account_id = account_id.clone();
+            let flow_session_id = flow_session_id.clone();
+            async move {
+                if finished {
+                    return None;
+                }
+                loop {
+                    let event = match timeout_at(deadline, receiver.recv()).await {
+                        Ok(event) => event,
+                        Err(_) => {
+                            println!(
+                                "[TASKS] Closing event stream for session {} after {:?}",
+                                session_id, SESSION_STREAM_MAX_DURATION
+                            );
+                            return None;
+                        }
+                    };
+                    match event {
+                        Ok(FlowSessionEvent {
+                            flow_session_id,
+                            event_type,
+                            data,
+                        }) if flow_session_id == session_id => {
+                            let done = event_type == FlowSessionEventType::SessionCompleted;
+                            return Some((
+                                to_sse_event(event_type.as_str(), &data),
+                                (receiver, done),
+                            ));
+                        }
+                        Ok(_) => continue,
+                        // The skipped events may have finished the session, so ask the database
+                        Err(RecvError::Lagged(skipped)) => {
+                            println!(
+                                "[TASKS] Event stream for session {} lagged, skipped {} events",
+                                session_id, skipped
+                            );
+                            let status =
+                                fetch_session_tasks(&state, &jwt, &account_id, &flow_session_id)
+                                    .await
+                                    .ok()
+                                    .and_then(|tasks| finished_status(&tasks));
+                            if let Some(status) = status {
+                                return Some((
+                                    to_sse_event(
+                                        FlowSessionEventType::SessionCompleted.as_str(),
+                                        &status,
+                                    ),
+                                    (receiver, true),
+                                ));
+                            }
+                        }
+                        Err(RecvError::Closed) => return None,
+                    }
+                }
+            }
+        },
+    );
+
+    let events = stream::iter(initial_events)
+        .chain(live_events)
+        .map(Ok::<Event, Infallible>);
+
+    Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
@@ -0,0 +1,570 @@
+//! `{% %}` blocks for string templates, rendered around the usual `{{ }}` interpolation.
+//!
+//! - `{% if expression %}`, `{% elif expression %}`, `{% else %}`, `{% endif %}`
+//! - `{% for item in expression %}` ... `{% endfor %}`, with `loop.index` (from 1), `loop.index0`,
+//!   `loop.first`, `loop.last` and `loop.length` inside. Objects are iterated as `{key, value}` items.
+//!
+//! Conditions take expressions like `{{ }}` does (paths, `??` fallbacks, `| filters`, literals)
+//! combined with `==`, `!=`, `>`, `>=`, `<`, `<=`, `not`, `and` and `or`. Missing values are false
+//! rather than an error, so `{% if actions.x.result.items %}` is how to check that something exists.
+//! `{%-` and `-%}` strip the whitespace before or after a tag.
+
+use serde_json::{json, Map, Value};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use super::compiled::{parse_parts, Expression, Part};
+use super::filters::{self, split_top_level};
+use super::{TemplateError, Templater};
+use crate::types::json_schema::{ValidationField, ValidationFieldType};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    Text(Vec<Part>),
+    If {
+        branches: Vec<(Condition, Vec<Node>)>,
+        otherwise: Vec<Node>,
+    },
+    For {
+        variable: String,
+        iterable: Expression,
+        body: Vec<Node>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    Any(Vec<Condition>),
+    All(Vec<Condition>),
+    Not(Box<Condition>),
+    Compare {
+        op: &'static str,
+        left: Expression,
+        right: Expression,
+    },
+    Truthy(Expression),
+}
+
+enum Token {
+    Text(String),
+    Tag(String),
+}
+
+const KEYWORDS: [&str; 6] = ["if", "elif", "else", "endif", "for", "endfor"];
+
+// Only our own tags count, `{% %}` meant for other template languages is left alone
+pub fn has_blocks(template: &str) -> bool {
+    let mut rest = template;
+    while let Some(open) = rest.find("{%") {
+        let tag = rest[open + 2..].trim_start_matches('-').trim_start();
+        let keyword = tag
+            .split(|c: char| c.is_whitespace() || c == '-' || c == '%')
+            .next()
+            .unwrap_or("");
+        if KEYWORDS.contains(&keyword) {
+            return true;
+        }
+        rest = &rest[open + 2..];
+    }
+    false
+}
+
+fn block_error(message: String, tag: &str) -> TemplateError {
+    TemplateError {
+        message,
+        variable: tag.to_string(),
+    }
+}
+
+fn tokenize(template: &str) -> Result<Vec<Token>, TemplateError> {
+    let mut tokens = Vec::new();
+    let mut rest = template;
+    let mut trim_next = false;
+
+    while let Some(open) = rest.find("{%") {
+        let close = rest[open..]
+            .find("%}")
+            .ok_or_else(|| block_error("Unclosed template block".to_string(), &rest[open..]))?
+            + open;
+
+        let mut text = &rest[..open];
+        let mut tag = &rest[open + 2..close];
+        if trim_next {
+            text = text.trim_start();
+        }
+        if let Some(stripped) = tag.strip_prefix('-') {
+            text = text.trim_end();
+            tag = stripped;
+        }
+        trim_next = false;
+        if let Some(stripped) = tag.strip_suffix('-') {
+            trim_next = true;
+            tag = stripped;
+        }
+
+        if !text.is_empty() {
+            tokens.push(Token::Text(text.to_string()));
+        }
+        tokens.push(Token::Tag(tag.trim().to_string()));
+        rest = &rest[close + 2..];
+    }
+
+    let text = if trim_next { rest.trim_start() } else { rest };
+    if !text.is_empty() {
+        tokens.push(Token::Text(text.to_string()));
+    }
+
+    Ok(tokens)
+}
+
+fn split_tag(tag: &str) -> (&str, &str) {
+    match tag.split_once(char::is_whitespace) {
+        Some((keyword, rest)) => (keyword, rest.trim()),
+        None => (tag, ""),
+    }
+}
+
+// Parses nodes until one of `ends` closes them, returning the closing tag
+fn parse_until(
+    tokens: &mut std::vec::IntoIter<Token>,
+    ends: &[&str],
+) -> Result<(Vec<Node>, Option<String>), TemplateError> {
+    let mut nodes = Vec::new();
+
+    while let Some(token) = tokens.next() {
+        let tag = match token {
+            Token::Text(text) => {
+                nodes.push(Node::Text(parse_parts(&text)?));
+                continue;
+            }
+            Token::Tag(tag) => tag,
+        };
+
+        let (keyword, rest) = split_tag(&tag);
+        match keyword {
+            "if" => {
+                let mut branches = Vec::new();
+                let mut condition = rest.to_string();
+                let mut otherwise = Vec::new();
+                loop {
+                    if condition.is_empty() {
+                        return Err(block_error("Missing if condition".to_string(), &tag));
+                    }
+                    let parsed = parse_condition(&condition)?;
+                    let (body, end) = parse_until(tokens, &["elif", "else", "endif"])?;
+                    branches.push((parsed, body));
+                    let end =
+                        end.ok_or_else(|| block_error("Unclosed if block".to_string(), &tag))?;
+                    match split_tag(&end) {
+                        ("elif", next_condition) => condition = next_condition.to_string(),
+                        ("else", _) => {
+                            let (body, end) = parse_until(tokens, &["endif"])?;
+                            if end.is_none() {
+                                return Err(block_error("Unclosed if block".to_string(), &tag));
+                            }
+                            otherwise = body;
+                            break;
+                        }
+                        _ => break,
+                    }
+                }
+                nodes.push(Node::If {
+                    branches,
+                    otherwise,
+                });
+            }
+            "for" => {
+                let (variable, iterable) = rest
+                    .split_once(" in ")
+                    .map(|(variable, iterable)| (variable.trim(), iterable.trim()))
+                    .filter(|(variable, iterable)| {
+                        !iterable.is_empty()
+                            && !variable.is_empty()
+                            && variable.chars().all(|c| c.is_alphanumeric() || c == '_')
+                    })
+                    .ok_or_else(|| {
+                        block_error("Expected {% for item in expression %}".to_string(), &tag)
+                    })?;
+                let (body, end) = parse_until(tokens, &["endfor"])?;
+                if end.is_none() {
+                    return Err(block_error("Unclosed for block".to_string(), &tag));
+                }
+                nodes.push(Node::For {
+                    variable: variable.to_string(),
+                    iterable: Expression::parse(iterable)?,
+                    body,
+                });
+            }
+            _ if ends.contains(&keyword) => return Ok((nodes, Some(tag))),
+            _ => {
+                return Err(block_error(
+                    format!("Unexpected template block '{}'", keyword),
+                    &tag,
+                ))
+            }
+        }
+    }
+
+    Ok((nodes, None))
+}
+
+pub fn parse_blocks(template: &str) -> Result<Vec<Node>, TemplateError> {
+    let mut tokens = tokenize(template)?.into_iter();
+    let (nodes, _) = parse_until(&mut tokens, &[])?;
+    Ok(nodes)
+}
+
+/// Paths read by the conditions and loops of a template, so they can be fetched up front.
+pub fn block_paths(template: &str) -> Vec<String> {
+    let mut paths = Vec::new();
+    let mut rest = template;
+
+    while let Some(open) = rest.find("{%") {
+        let close = match rest[open..].find("%}") {
+            Some(close) => close + open,
+            None => break,
+        };
+        let tag = rest[open + 2..close].trim_matches('-').trim();
+        let (keyword, expression) = split_tag(tag);
+        match keyword {
+            "if" | "elif" => {
+                for operand in condition_operands(expression) {
+                    paths.extend(filters::expression_paths(operand));
+                }
+            }
+            "for" => {
+                if let Some((_, iterable)) = expression.split_once(" in ") {
+                    paths.extend(filters::expression_paths(iterable));
+                }
+            }
+            _ => {}
+        }
+        rest = &rest[close + 2..];
+    }
+
+    paths
+}
+
+pub(super) const COMPARISONS: [&str; 6] = ["==", "!=", ">=", "<=", ">", "<"];
+
+fn condition_operands(condition: &str) -> Vec<&str> {
+    split_top_level(condition, " or ")
+        .into_iter()
+        .flat_map(|part| split_top_level(part, " and "))
+        .map(|part| {
+            let part = part.trim();
+            part.strip_prefix("not ").unwrap_or(part)
+        })
+        .flat_map(|part| {
+            COMPARISONS
+                .iter()
+                .map(|op| split_top_level(part, op))
+                .find(|sides| sides.len() == 2)
+                .unwrap_or_else(|| vec![part])
+        })
+        .map(str::trim)
+        .collect()
+}
+
+pub(super) fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64().is_some_and(|n| n != 0.0),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(map) => !map.is_empty(),
+    }
+}
+
+fn as_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn compare(left: &Value, right: &Value) -> Option<Ordering> {
+    match (as_number(left), as_number(right)) {
+        (Some(left), Some(right)) => left.partial_cmp(&right),
+        _ => match (left, right) {
+            (Value::String(left), Value::String(right)) => Some(left.cmp(right)),
+            _ => None,
+        },
+    }
+}
+
+// Applies one of COMPARISONS
+pub(super) fn compare_with(op: &str, left: &Value, right: &Value) -> bool {
+    let ordering = compare(left, right);
+    match op {
+        "==" => left == right || ordering == Some(Ordering::Equal),
+        "!=" => left != right && ordering != Some(Ordering::Equal),
+        ">=" => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+        "<=" => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+        ">" => ordering == Some(Ordering::Greater),
+        _ => ordering == Some(Ordering::Less),
+    }
+}
+
+fn parse_condition(condition: &str) -> Result<Condition, TemplateError> {
+    let any_of = split_top_level(condition, " or ");
+    if any_of.len() > 1 {
+        return Ok(Condition::Any(
+            any_of
+                .into_iter()
+                .map(parse_condition)
+                .collect::<Result<_, _>>()?,
+        ));
+    }
+
+    let all_of = split_top_level(condition, " and ");
+    if all_of.len() > 1 {
+        return Ok(Condition::All(
+            all_of
+                .into_iter()
+                .map(parse_condition)
+                .collect::<Result<_, _>>()?,
+        ));
+    }
+
+    let condition = condition.trim();
+    if let Some(negated) = condition.strip_prefix("not ") {
+        return Ok(Condition::Not(Box::new(parse_condition(negated)?)));
+    }
+
+    for op in COMPARISONS {
+        let sides = split_top_level(condition, op);
+        if sides.len() == 2 {
+            return Ok(Condition::Compare {
+                op,
+                left: Expression::parse(sides[0].trim())?,
+                right: Expression::parse(sides[1].trim())?,
+            });
+        }
+    }
+
+    Ok(Condition::Truthy(Expression::parse(condition)?))
+}
+
+impl Condition {
+    fn evaluate(&self, context: &Value) -> Result<bool, TemplateError> {
+        let operand = |expression: &Expression| -> Result<Value, TemplateError> {
+            Ok(expression
+                .evaluate(context, &ValidationFieldType::Unknown)?
+                .unwrap_or(Value::Null))
+        };
+
+        match self {
+            Condition::Any(conditions) => {
+                for condition in conditions {
+                    if condition.evaluate(context)? {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+            Condition::All(conditions) => {
+                for condition in conditions {
+                    if !condition.evaluate(context)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            Condition::Not(condition) => Ok(!condition.evaluate(context)?),
+            Condition::Compare { op, left, right } => {
+                Ok(compare_with(op, &operand(left)?, &operand(right)?))
+            }
+            Condition::Truthy(expression) => Ok(is_truthy(&operand(expression)?)),
+        }
+    }
+}
+
+impl Templater {
+    pub(super) fn render_nodes(
+        &self,
+        nodes: &[Node],
+        context: &Value,
+        validations: &HashMap<String, ValidationField>,
+        path: &[String],
+        output: &mut String,
+    ) -> Result<(), TemplateError> {
+        for node in nodes {
+            match node {
+                Node::Text(parts) => {
+                    output.push_str(&self.render_parts(parts, context, validations, path)?)
+                }
+                Node::If {
+                    branches,
+                    otherwise,
+                } => {
+                    let mut body = otherwise;
+                    for (condition, branch) in branches {
+                        if condition.evaluate(context)? {
+                            body = branch;
+                            break;
+                        }
+                    }
+                    self.render_nodes(body, context, validations, path, output)?;
+                }
+                Node::For {
+                    variable,
+                    iterable,
+                    body,
+                } => {
+                    let items = match iterable.evaluate(context, &ValidationFieldType::Array)? {
+                        Some(Value::Array(items)) => items,
+                        Some(Value::Object(map)) => map
+                            .into_iter()
+                            .map(|(key, value)| json!({ "key": key, "value": value }))
+                            .collect(),
+                        Some(Value::Null) | None => Vec::new(),
+                        Some(other) => vec![other],
+                    };
+                    // Loop variables shadow context keys of the same name inside the body
+                    let mut scope = match context {
+                        Value::Object(map) => map.clone(),
+                        _ => Map::new(),
+                    };
+                    let length = items.len();
+                    for (index, item) in items.into_iter().enumerate() {
+                        scope.insert(variable.clone(), item);
+                        scope.insert(
+                            "loop".to_string(),
+                            json!({
+                                "index": index + 1,
+                                "index0": index,
+                                "first": index == 0,
+                                "last": index + 1 == length,
+                                "length": length,
+                            }),
+                        );
+                        let scope_value = Value::Object(std::mem::take(&mut scope));
+                        self.render_nodes(body, &scope_value, validations, path, output)?;
+                        if let Value::Object(map) = scope_value {
+                            scope = map;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(template: &str, context: Value) -> Result<Value, TemplateError> {
+        let mut output = String::new();
+        Templater::new().render_nodes(
+            &parse_blocks(template)?,
+            &context,
+            &HashMap::from([("body".to_string(), ValidationField::default())]),
+            &["body".to_string()],
+            &mut output,
+        )?;
+        Ok(Value::String(output))
+    }
+
+    #[test]
+    fn test_if_blocks() {
+        let template = "{% if actions.order.result.items %}Items{% elif variables.draft == true %}Draft{% else %}Empty{% endif %}";
+
+        assert_eq!(
+            render(
+                template,
+                json!({ "actions": { "order": { "result": { "items": [1] } } } })
+            )
+            .unwrap(),
+            json!("Items")
+        );
+        assert_eq!(
+            render(template, json!({ "variables": { "draft": true } })).unwrap(),
+            json!("Draft")
+        );
+        assert_eq!(render(template, json!({})).unwrap(), json!("Empty"));
+    }
+
+    #[test]
+    fn test_conditions() {
+        let context = json!({
+            "order": { "total": "120.5", "status": "open", "items": ["a", "b"] }
+        });
+        let check = |condition: &str| {
+            render(
+                &format!("{{% if {} %}}yes{{% endif %}}", condition),
+                context.clone(),
+            )
+            .unwrap()
+                == json!("yes")
+        };
+
+        assert!(check("order.total > 100"));
+        assert!(check("order.total >= 120.5 and order.status == 'open'"));
+        assert!(check("order.status != \"closed\""));
+        assert!(check("order.items | length == 2"));
+        assert!(check("order.missing or order.status == 'open'"));
+        assert!(check("not order.missing"));
+        assert!(!check("order.total < 100"));
+        assert!(!check("order.status == 'open' and order.missing"));
+    }
+
+    #[test]
+    fn test_for_blocks() {
+        let template = "{%- for item in actions.order.result.items -%}\n{{ loop.index }}. {{ item.name | upper }}{% if not loop.last %}, {% endif %}\n{%- endfor %}";
+        let context = json!({
+            "actions": {
+                "order": {
+                    "result": { "items": "[{\"name\": \"apple\"}, {\"name\": \"pear\"}]" }
+                }
+            }
+        });
+
+        assert_eq!(
+            render(template, context).unwrap(),
+            json!("1. APPLE, 2. PEAR")
+        );
+        assert_eq!(render(template, json!({})).unwrap(), json!(""));
+    }
+
+    #[test]
+    fn test_for_over_object() {
+        let template = "{% for entry in headers %}{{ entry.key }}={{ entry.value }};{% endfor %}";
+
+        assert_eq!(
+            render(template, json!({ "headers": { "a": "1", "b": "2" } })).unwrap(),
+            json!("a=1;b=2;")
+        );
+    }
+
+    #[test]
+    fn test_invalid_blocks() {
+        assert!(render("{% if a %}never closed", json!({})).is_err());
+        assert!(render("{% for a %}{% endfor %}", json!({})).is_err());
+        assert!(render("{% endif %}", json!({})).is_err());
+        assert!(render("{% if a %}{% while a %}{% endif %}", json!({})).is_err());
+        assert!(render("{% if a ", json!({})).is_err());
+    }
+
+    #[test]
+    fn test_other_template_languages_are_left_alone() {
+        assert!(!has_blocks("{% assign name = 'x' %}{{ name }}"));
+        assert!(has_blocks("{%- if a -%}{% endif %}"));
+    }
+
+    #[test]
+    fn test_block_paths() {
+        assert_eq!(
+            block_paths("{% for item in actions.x.result.items %}{% if item.ok and variables.flag == 'y' %}{% endif %}{% endfor %}"),
+            vec![
+                "actions.x.result.items".to_string(),
+                "item.ok".to_string(),
+                "variables.flag".to_string()
+            ]
+        );
+    }
+}
@@ -0,0 +1,324 @@
+//! Templates parsed once and rendered many times.
+//!
+//! `Templater::compile` turns a template into a `CompiledTemplate`: strings are split into text and
+//! `{{ }}` parts, expressions into their `??` alternatives, paths and `| filters`, and `{% %}`
+//! strings into blocks. Rendering it only walks that tree, which is what `Templater::render` does
+//! too after compiling, so both give the same results.
+
+use serde_json::Value;
+use std::collections::HashMap;
+
+use super::blocks::{self, Node};
+use super::escape;
+use super::filters::{self, Filter};
+use super::path::{self, Segment};
+use super::{TemplateError, Templater};
+use crate::types::json_schema::{ValidationField, ValidationFieldType};
+
+#[derive(Debug, Clone)]
+pub struct CompiledTemplate {
+    root: CompiledValue,
+    variables: Vec<String>,
+}
+
+impl CompiledTemplate {
+    /// Paths the template reads, like `Templater::get_template_variables`.
+    pub fn variables(&self) -> &[String] {
+        &self.variables
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum CompiledValue {
+    // Nothing to render below this value
+    Literal(Value),
+    Object(Vec<(String, CompiledValue)>),
+    Array(Vec<CompiledValue>),
+    // The whole string is one `{{ }}`, it renders to the value itself
+    Variable(Expression),
+    Text(Vec<Part>),
+    Blocks(Vec<Node>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Part {
+    Text(String),
+    Expression(Expression),
+}
+
+/// What's inside `{{ }}`: alternatives separated by `??`, then `| filters` in order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expression {
+    pub source: String,
+    alternatives: Vec<Alternative>,
+    filters: Vec<Filter>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Alternative {
+    Literal(Value),
+    // None when the path isn't valid, it then never resolves
+    Path {
+        segments: Option<Vec<Segment>>,
+        // In `a.b?.c` only `a.b` has to exist, anything missing after the `?.` is null
+        optional_prefix: Option<Vec<Segment>>,
+    },
+}
+
+impl Expression {
+    pub fn parse(source: &str) -> Result<Self, TemplateError> {
+        let (path, filters) = filters::split_expression(source)?;
+        let alternatives = filters::split_top_level(path, "??")
+            .into_iter()
+            .map(|alternative| {
+                let alternative = alternative.trim();
+                if let Some(literal) = filters::parse_literal(alternative) {
+                    return Alternative::Literal(literal);
+                }
+                match alternative.find("?.") {
+                    Some(optional_start) => Alternative::Path {
+                        segments: path::parse_path(&alternative.replace("?.", ".")),
+                        optional_prefix: path::parse_path(&alternative[..optional_start]),
+                    },
+                    None => Alternative::Path {
+                        segments: path::parse_path(alternative),
+                        optional_prefix: None,
+                    },
+                }
+            })
+            .collect();
+
+        Ok(Expression {
+            source: source.to_string(),
+            alternatives,
+            filters,
+        })
+    }
+
+    // `| escape(...)`, `| raw` or `| urlencode` replace the field's escaping
+    pub fn escapes_itself(&self) -> bool {
+        self.filters
+            .iter()
+            .any(|filter| escape::ESCAPING_FILTERS.contains(&filter.name.as_str()))
+    }
+
+    // The first alternative that isn't missing or null wins
+    pub fn evaluate(
+        &self,
+        context: &Value,
+        expected_type: &ValidationFieldType,
+    ) -> Result<Option<Value>, TemplateError> {
+        // Only parse JSON held in strings if the expected type is not String
+        let parse_json = *expected_type != ValidationFieldType::String;
+        let mut resolved_null = false;
+        let mut value = None;
+
+        for alternative in &self.alternatives {
+            let resolved = match alternative {
+                Alternative::Literal(literal) => Some(literal.clone()),
+                Alternative::Path {
+                    segments,
+                    optional_prefix,
+                } => segments
+                    .as_ref()
+                    .and_then(|segments| path::select(context, segments, parse_json))
+                    .or_else(|| {
+                        optional_prefix
+                            .as_ref()
+                            .and_then(|prefix| path::select(context, prefix, parse_json))
+                            .map(|_| Value::Null)
+                    }),
+            };
+            match resolved {
+                Some(Value::Null) => resolved_null = true,
+                Some(resolved) => {
+                    value = Some(resolved);
+                    break;
+                }
+                None => {}
+            }
+        }
+
+        let value = value.or_else(|| resolved_null.then_some(Value::Null));
+        filters::apply_filters(value, &self.filters, &self.source)
+    }
+}
+
+/// Splits a string into its text and `{{ }}` parts.
+pub fn parse_parts(s: &str) -> Result<Vec<Part>, TemplateError> {
+    let mut parts = Vec::new();
+    let mut rest = s;
+
+    while let Some(open) = rest.find("{{") {
+        let close = rest[open..].find("}}").ok_or_else(|| TemplateError {
+            message: "Unclosed template variable".to_string(),
+            variable: s.to_string(),
+        })? + open;
+        if open > 0 {
+            parts.push(Part::Text(rest[..open].to_string()));
+        }
+        parts.push(Part::Expression(Expression::parse(
+            rest[open + 2..close].trim(),
+        )?));
+        rest = &rest[close + 2..];
+    }
+    if !rest.is_empty() {
+        parts.push(Part::Text(rest.to_string()));
+    }
+
+    Ok(parts)
+}
+
+// Validations apply to the keys of objects at the top, which arrays don't change
+pub(super) fn compile_value(
+    value: &Value,
+    top_level: bool,
+) -> Result<CompiledValue, TemplateError> {
+    let compiled = match value {
+        Value::Object(map) => {
+            let entries = map
+                .iter()
+                .map(|(k, v)| Ok((k.clone(), compile_value(v, false)?)))
+                .collect::<Result<Vec<_>, TemplateError>>()?;
+            if !top_level
+                && entries
+                    .iter()
+                    .all(|(_, v)| matches!(v, CompiledValue::Literal(_)))
+            {
+                CompiledValue::Literal(value.clone())
+            } else {
+                CompiledValue::Object(entries)
+            }
+        }
+        Value::Array(items) => {
+            let items = items
+                .iter()
+                .map(|v| compile_value(v, top_level))
+                .collect::<Result<Vec<_>, TemplateError>>()?;
+            if !top_level && items.iter().all(|v| matches!(v, CompiledValue::Literal(_))) {
+                CompiledValue::Literal(value.clone())
+            } else {
+                CompiledValue::Array(items)
+            }
+        }
+        Value::String(s) => {
+            let trimmed = s.trim();
+            if blocks::has_blocks(s) {
+                CompiledValue::Blocks(blocks::parse_blocks(s)?)
+            } else if trimmed.starts_with("{{") && trimmed.ends_with("}}") {
+                CompiledValue::Variable(Expression::parse(trimmed[2..trimmed.len() - 2].trim())?)
+            } else if s.contains("{{") {
+                CompiledValue::Text(parse_parts(s)?)
+            } else {
+                CompiledValue::Literal(value.clone())
+            }
+        }
+        _ => CompiledValue::Literal(value.clone()),
+    };
+    Ok(compiled)
+}
+
+impl Templater {
+    /// Parses a template once so it can be rendered any number of times with `render_compiled`.
+    pub fn compile(template: &Value) -> Result<CompiledTemplate, TemplateError> {
+        Ok(CompiledTemplate {
+            root: compile_value(template, true)?,
+            variables: Self::extract_variables(template)?,
+        })
+    }
+
+    pub fn render_compiled(
+        &self,
+        template: &CompiledTemplate,
+        context: &Value,
+        validations: HashMap<String, ValidationField>,
+    ) -> Result<Value, TemplateError> {
+        self.render_value(&template.root, context, &validations, &[])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_compile_keeps_literals_whole() {
+        let compiled = Templater::compile(&json!({
+            "headers": { "accept": "application/json", "retries": [1, 2] },
+            "url": "https://example.com/{{ variables.id }}",
+            "body": "{{ variables.body }}"
+        }))
+        .unwrap();
+
+        let CompiledValue::Object(entries) = &compiled.root else {
+            panic!("expected an object");
+        };
+        let entry = |key: &str| &entries.iter().find(|(k, _)| k == key).unwrap().1;
+        assert_eq!(
+            entry("headers"),
+            &CompiledValue::Literal(json!({ "accept": "application/json", "retries": [1, 2] }))
+        );
+        assert!(matches!(entry("url"), CompiledValue::Text(parts) if parts.len() == 2));
+        assert!(
+            matches!(entry("body"), CompiledValue::Variable(expression) if expression.source == "variables.body")
+        );
+
+        let mut variables = compiled.variables().to_vec();
+        variables.sort();
+        assert_eq!(variables, ["variables.body", "variables.id"]);
+    }
+
+    #[test]
+    fn test_compile_reports_template_errors() {
+        let error = Templater::compile(&json!({ "subject": "Hi {{ variables.name" })).unwrap_err();
+        assert_eq!(error.message, "Unclosed template variable");
+
+        let error =
+            Templater::compile(&json!({ "subject": "{% if variables.x %}open" })).unwrap_err();
+        assert_eq!(error.message, "Unclosed if block");
+    }
+
+    #[test]
+    fn test_compiled_template_renders_against_many_contexts() {
+        let templater = Templater::new();
+        let compiled = Templater::compile(&json!({
+            "subject": "Order {{ variables.id }} for {{ variables.name | upper }}",
+            "total": "{{ variables.total ?? 0 }}"
+        }))
+        .unwrap();
+
+        let validations = HashMap::from([
+            (
+                "subject".to_string(),
+                ValidationField {
+                    r#type: ValidationFieldType::String,
+                    strict: true,
+                    escape: None,
+                },
+            ),
+            (
+                "total".to_string(),
+                ValidationField {
+                    r#type: ValidationFieldType::Number,
+                    strict: true,
+                    escape: None,
+                },
+            ),
+        ]);
+
+        for (id, name, total) in [(1, "ann", json!(5)), (2, "bob", Value::Null)] {
+            let context = json!({ "variables": { "id": id, "name": name, "total": total } });
+            let rendered = templater
+                .render_compiled(&compiled, &context, validations.clone())
+                .unwrap();
+            assert_eq!(
+                rendered,
+                json!({
+                    "subject": format!("Order {} for {}", id, name.to_uppercase()),
+                    "total": if id == 1 { 5 } else { 0 }
+                })
+            );
+        }
+    }
+}
@@ -0,0 +1,117 @@
+//! Escaping for values interpolated into a larger string, e.g. `<p>{{ trigger.body.comment }}</p>`.
+//!
+//! A field's mode comes from its `x-any-validation` `escape`, else its `contentMediaType`, else
+//! its input type (`html_or_variable` and `xml_or_variable` are HTML, `object_or_variable` is JSON).
+//! It applies to the `{{ }}` parts of the string that is the field's value, not to a field that is
+//! a single `{{ }}` since that renders to the value itself.
+//!
+//! An expression picks its own escaping with `| escape("url")` or opts out with `| raw`, then the
+//! field's mode is skipped. `urlencode` counts as escaping too.
+//!
+//! - `html` replaces `& < > " '` with entities, which is also valid XML
+//! - `url` percent-encodes a URL component
+//! - `json` escapes strings for use inside a JSON string, other values are inserted as JSON
+//! - `shell` single-quotes the value as one shell word
+//! - `raw` leaves the value as is
+
+use serde_json::Value;
+
+use crate::types::json_schema::EscapeMode;
+
+/// Filters after which an expression's value is already escaped.
+pub const ESCAPING_FILTERS: [&str; 3] = ["escape", "raw", "urlencode"];
+
+pub fn parse_mode(mode: &str) -> Option<EscapeMode> {
+    serde_json::from_value(Value::String(mode.to_lowercase())).ok()
+}
+
+/// The text to insert for `value` with the given escaping.
+pub fn escape_value(value: &Value, mode: EscapeMode) -> String {
+    let text = match value {
+        Value::String(s) => s.as_str(),
+        other => return escape_non_string(other, mode),
+    };
+
+    match mode {
+        EscapeMode::Html => escape_html(text),
+        EscapeMode::Url => urlencoding::encode(text).into_owned(),
+        EscapeMode::Json => {
+            let quoted = Value::String(text.to_string()).to_string();
+            quoted[1..quoted.len() - 1].to_string()
+        }
+        EscapeMode::Shell => format!("'{}'", text.replace('\'', "'\\''")),
+        EscapeMode::Raw => text.to_string(),
+    }
+}
+
+// Numbers, booleans, null, arrays and objects are inserted as JSON
+fn escape_non_string(value: &Value, mode: EscapeMode) -> String {
+    match mode {
+        EscapeMode::Json | EscapeMode::Raw => value.to_string(),
+        _ => escape_value(&Value::String(value.to_string()), mode),
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_escape_modes() {
+        let value = json!("<b>Tom & \"Jerry's\"</b>");
+        assert_eq!(
+            escape_value(&value, EscapeMode::Html),
+            "&lt;b&gt;Tom &amp; &quot;Jerry&#39;s&quot;&lt;/b&gt;"
+        );
+        assert_eq!(
+            escape_value(&json!("a b/c?d=e&f"), EscapeMode::Url),
+            "a%20b%2Fc%3Fd%3De%26f"
+        );
+        assert_eq!(
+            escape_value(&json!("say \"hi\"\n\\o/"), EscapeMode::Json),
+            "say \\\"hi\\\"\\n\\\\o/"
+        );
+        assert_eq!(
+            escape_value(&json!("it's; rm -rf /"), EscapeMode::Shell),
+            "'it'\\''s; rm -rf /'"
+        );
+        assert_eq!(
+            escape_value(&value, EscapeMode::Raw),
+            value.as_str().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_escape_non_strings() {
+        let value = json!({ "name": "<b>" });
+        assert_eq!(escape_value(&value, EscapeMode::Json), "{\"name\":\"<b>\"}");
+        assert_eq!(
+            escape_value(&value, EscapeMode::Html),
+            "{&quot;name&quot;:&quot;&lt;b&gt;&quot;}"
+        );
+        assert_eq!(escape_value(&json!(42), EscapeMode::Shell), "'42'");
+    }
+
+    #[test]
+    fn test_parse_mode() {
+        assert_eq!(parse_mode("HTML"), Some(EscapeMode::Html));
+        assert_eq!(parse_mode("shell"), Some(EscapeMode::Shell));
+        assert_eq!(parse_mode("base64"), None);
+    }
+}
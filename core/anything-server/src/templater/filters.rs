@@ -0,0 +1,538 @@
+//! Pipe filters for template expressions, e.g. `{{ actions.x.result.name | upper | truncate(20) }}`.
+//!
+//! Filters run left to right on the value the path resolved to. Arguments are JSON literals or
+//! single / double quoted strings. A missing value passes through every filter untouched except
+//! `default`, so strict and non-strict variables behave the same as without filters.
+//!
+//! - `upper`, `lower`, `trim`, `capitalize`
+//! - `truncate(length)`, `truncate(length, "...")` shortens text to `length` characters
+//! - `default("n/a")` replaces a missing, null or empty value
+//! - `json` serializes the value as a JSON string
+//! - `date("%Y-%m-%d")`, `date("%Y-%m-%d %H:%M", "Europe/Berlin")` formats RFC 3339 strings,
+//!   `YYYY-MM-DD[ HH:MM:SS]` strings or unix timestamps (seconds or milliseconds)
+//! - `urlencode` percent-encodes the value for use in a URL
+//! - `escape("html")` escapes for `html`, `url`, `json` or `shell`, `raw` skips the field's escaping
+//! - `join(", ")` joins an array into text
+//! - `split(",")` splits text into an array
+//! - `replace("from", "to")`
+//! - `length` counts characters, array items or object keys
+//! - `first`, `last` of an array or text
+
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde_json::Value;
+
+use super::escape;
+use super::TemplateError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    pub name: String,
+    pub args: Vec<Value>,
+}
+
+// Splits on `separator` where it isn't inside quotes or parentheses
+pub(super) fn split_top_level<'a>(input: &'a str, separator: &str) -> Vec<&'a str> {
+    let mut parts = Vec::new();
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    let mut depth = 0usize;
+    let mut start = 0;
+
+    for (i, c) in input.char_indices() {
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        match c {
+            '"' | '\'' => quote = Some(c),
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            _ if depth == 0 && i >= start && input[i..].starts_with(separator) => {
+                parts.push(&input[start..i]);
+                start = i + separator.len();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&input[start..]);
+    parts
+}
+
+/// The context paths an expression reads, without filters, literal fallbacks or `?.` markers.
+pub fn expression_paths(expression: &str) -> Vec<String> {
+    split_top_level(split_top_level(expression, "|")[0], "??")
+        .into_iter()
+        .map(str::trim)
+        .filter(|path| !path.is_empty() && parse_literal(path).is_none())
+        .map(|path| path.replace("?.", "."))
+        .collect()
+}
+
+/// Splits `path | filter | filter(args)` into the path and its parsed filters.
+pub fn split_expression(expression: &str) -> Result<(&str, Vec<Filter>), TemplateError> {
+    let mut parts = split_top_level(expression, "|").into_iter();
+    let path = parts.next().unwrap_or("").trim();
+
+    let filters = parts
+        .map(|part| parse_filter(part.trim(), expression))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok((path, filters))
+}
+
+fn parse_filter(filter: &str, expression: &str) -> Result<Filter, TemplateError> {
+    let error = |message: String| TemplateError {
+        message,
+        variable: expression.to_string(),
+    };
+
+    let (name, args) = match filter.find('(') {
+        Some(open) => {
+            let args = filter[open + 1..]
+                .strip_suffix(')')
+                .ok_or_else(|| error(format!("Unclosed arguments for filter '{}'", filter)))?;
+            (filter[..open].trim(), args.trim())
+        }
+        None => (filter, ""),
+    };
+
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(error(format!("Invalid filter '{}'", filter)));
+    }
+
+    let args = if args.is_empty() {
+        Vec::new()
+    } else {
+        split_top_level(args, ",")
+            .into_iter()
+            .map(|arg| {
+                parse_literal(arg.trim()).ok_or_else(|| {
+                    error(format!(
+                        "Invalid argument '{}' for filter '{}'",
+                        arg.trim(),
+                        name
+                    ))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?
+    };
+
+    Ok(Filter {
+        name: name.to_string(),
+        args,
+    })
+}
+
+/// A quoted string or a JSON literal such as a number, `true` or `null`.
+pub fn parse_literal(literal: &str) -> Option<Value> {
+    let mut chars = literal.chars();
+    match chars.next() {
+        Some(q @ ('"' | '\'')) if literal.len() >= 2 && literal.ends_with(q) => {
+            let inner = &literal[1..literal.len() - 1];
+            let mut text = String::with_capacity(inner.len());
+            let mut escaped = false;
+            for c in inner.chars() {
+                if escaped {
+                    text.push(match c {
+                        'n' => '\n',
+                        't' => '\t',
+                        other => other,
+                    });
+                    escaped = false;
+                } else if c == '\\' {
+                    escaped = true;
+                } else {
+                    text.push(c);
+                }
+            }
+            Some(Value::String(text))
+        }
+        _ => serde_json::from_str(literal).ok(),
+    }
+}
+
+/// Applies the filters in order. `variable` is only used for error messages.
+pub fn apply_filters(
+    value: Option<Value>,
+    filters: &[Filter],
+    variable: &str,
+) -> Result<Option<Value>, TemplateError> {
+    filters
+        .iter()
+        .try_fold(value, |value, filter| apply_filter(value, filter, variable))
+}
+
+// Text form of a value, strings without their quotes
+fn as_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+fn apply_filter(
+    value: Option<Value>,
+    filter: &Filter,
+    variable: &str,
+) -> Result<Option<Value>, TemplateError> {
+    let error = |message: String| TemplateError {
+        message: format!("Filter '{}': {}", filter.name, message),
+        variable: variable.to_string(),
+    };
+    let text_arg = |index: usize, default: &str| -> Result<String, TemplateError> {
+        match filter.args.get(index) {
+            None => Ok(default.to_string()),
+            Some(Value::String(s)) => Ok(s.clone()),
+            Some(other) => Err(error(format!("expected a string argument, got {}", other))),
+        }
+    };
+
+    if filter.name == "default" {
+        let fallback = filter.args.first().cloned().unwrap_or(Value::Null);
+        return Ok(match value {
+            None | Some(Value::Null) => Some(fallback),
+            Some(Value::String(s)) if s.is_empty() => Some(fallback),
+            value => value,
+        });
+    }
+
+    let value = match value {
+        Some(value) => value,
+        None => return Ok(None),
+    };
+
+    let filtered = match filter.name.as_str() {
+        "upper" => Value::String(as_text(&value).to_uppercase()),
+        "lower" => Value::String(as_text(&value).to_lowercase()),
+        "trim" => Value::String(as_text(&value).trim().to_string()),
+        "capitalize" => {
+            let text = as_text(&value);
+            let mut chars = text.chars();
+            Value::String(match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            })
+        }
+        "truncate" => {
+            let length = filter
+                .args
+                .first()
+                .and_then(Value::as_u64)
+                .ok_or_else(|| error("expected a length".to_string()))?
+                as usize;
+            let end = text_arg(1, "")?;
+            let text = as_text(&value);
+            if text.chars().count() > length {
+                Value::String(text.chars().take(length).collect::<String>() + &end)
+            } else {
+                Value::String(text)
+            }
+        }
+        "json" => Value::String(value.to_string()),
+        "date" => {
+            let format = text_arg(0, "%Y-%m-%dT%H:%M:%S%:z")?;
+            let timezone = filter.args.get(1).map(as_text);
+            Value::String(format_date(&value, &format, timezone.as_deref()).map_err(error)?)
+        }
+        "urlencode" => Value::String(urlencoding::encode(&as_text(&value)).into_owned()),
+        "escape" => {
+            let mode = text_arg(0, "html")?;
+            let mode = escape::parse_mode(&mode)
+                .ok_or_else(|| error(format!("unknown escaping '{}'", mode)))?;
+            Value::String(escape::escape_value(&value, mode))
+        }
+        "raw" => value,
+        "join" => {
+            let separator = text_arg(0, ",")?;
+            match &value {
+                Value::Array(items) => Value::String(
+                    items
+                        .iter()
+                        .map(as_text)
+                        .collect::<Vec<_>>()
+                        .join(&separator),
+                ),
+                other => Value::String(as_text(other)),
+            }
+        }
+        "split" => {
+            let separator = text_arg(0, ",")?;
+            Value::Array(
+                as_text(&value)
+                    .split(separator.as_str())
+                    .map(|part| Value::String(part.to_string()))
+                    .collect(),
+            )
+        }
+        "replace" => {
+            let from = text_arg(0, "")?;
+            if from.is_empty() {
+                return Err(error("expected the text to replace".to_string()));
+            }
+            Value::String(as_text(&value).replace(&from, &text_arg(1, "")?))
+        }
+        "length" => Value::from(match &value {
+            Value::Array(items) => items.len(),
+            Value::Object(map) => map.len(),
+            other => as_text(other).chars().count(),
+        }),
+        "first" | "last" => {
+            let first = filter.name == "first";
+            match &value {
+                Value::Array(items) => {
+                    let item = if first { items.first() } else { items.last() };
+                    match item {
+                        Some(item) => item.clone(),
+                        None => return Ok(None),
+                    }
+                }
+                other => {
+                    let text = as_text(other);
+                    let c = if first {
+                        text.chars().next()
+                    } else {
+                        text.chars().last()
+                    };
+                    match c {
+                        Some(c) => Value::String(c.to_string()),
+                        None => return Ok(None),
+                    }
+                }
+            }
+        }
+        _ => return Err(error("unknown filter".to_string())),
+    };
+
+    Ok(Some(filtered))
+}
+
+fn parse_date(value: &Value) -> Option<DateTime<FixedOffset>> {
+    match value {
+        Value::Number(n) => {
+            let timestamp = n.as_f64()?;
+            // Anything this large is a millisecond timestamp
+            let millis = if timestamp.abs() >= 1e11 {
+                timestamp
+            } else {
+                timestamp * 1000.0
+            };
+            Utc.timestamp_millis_opt(millis as i64)
+                .single()
+                .map(|date| date.fixed_offset())
+        }
+        Value::String(s) => {
+            let s = s.trim();
+            DateTime::parse_from_rfc3339(s)
+                .ok()
+                .or_else(|| {
+                    NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S")
+                        .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S"))
+                        .ok()
+                        .map(|date| date.and_utc().fixed_offset())
+                })
+                .or_else(|| {
+                    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                        .ok()
+                        .and_then(|date| date.and_hms_opt(0, 0, 0))
+                        .map(|date| date.and_utc().fixed_offset())
+                })
+                .or_else(|| {
+                    s.parse::<f64>()
+                        .ok()
+                        .and_then(|n| parse_date(&Value::from(n)))
+                })
+        }
+        _ => None,
+    }
+}
+
+fn format_date(value: &Value, format: &str, timezone: Option<&str>) -> Result<String, String> {
+    use chrono::format::{Item, StrftimeItems};
+    use std::fmt::Write;
+
+    // chrono panics on invalid formats when displaying, so check up front
+    if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+        return Err(format!("invalid date format '{}'", format));
+    }
+
+    let date = parse_date(value).ok_or_else(|| format!("cannot read {} as a date", value))?;
+
+    let mut formatted = String::new();
+    let result = match timezone {
+        Some(timezone) => {
+            let tz: Tz = timezone
+                .parse()
+                .map_err(|_| format!("unknown timezone '{}'", timezone))?;
+            write!(formatted, "{}", date.with_timezone(&tz).format(format))
+        }
+        None => write!(formatted, "{}", date.format(format)),
+    };
+    result.map_err(|_| format!("cannot format date with '{}'", format))?;
+
+    Ok(formatted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn run(expression: &str, value: Option<Value>) -> Result<Option<Value>, TemplateError> {
+        let (_, filters) = split_expression(expression)?;
+        apply_filters(value, &filters, expression)
+    }
+
+    #[test]
+    fn test_split_expression() {
+        let (path, filters) =
+            split_expression(r#" actions.x.result.name | upper | truncate(20, "|...") "#).unwrap();
+
+        assert_eq!(path, "actions.x.result.name");
+        assert_eq!(
+            filters,
+            vec![
+                Filter {
+                    name: "upper".to_string(),
+                    args: vec![],
+                },
+                Filter {
+                    name: "truncate".to_string(),
+                    args: vec![json!(20), json!("|...")],
+                },
+            ]
+        );
+        assert_eq!(
+            expression_paths(r#"a.b?.c ?? d ?? "n/a" | join(', ')"#),
+            vec!["a.b.c".to_string(), "d".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_invalid_filters() {
+        assert!(split_expression("a | truncate(20").is_err());
+        assert!(split_expression("a | default(nope)").is_err());
+        assert!(run("a | nope", Some(json!("x"))).is_err());
+        assert!(run("a | truncate", Some(json!("x"))).is_err());
+        assert!(run("a | escape('base64')", Some(json!("x"))).is_err());
+    }
+
+    #[test]
+    fn test_text_filters() {
+        assert_eq!(
+            run("a | upper | truncate(5)", Some(json!("hello world"))).unwrap(),
+            Some(json!("HELLO"))
+        );
+        assert_eq!(
+            run(
+                "a | trim | capitalize | truncate(3, '...')",
+                Some(json!("  émile "))
+            )
+            .unwrap(),
+            Some(json!("Émi..."))
+        );
+        assert_eq!(
+            run("a | replace('-', ' ') | lower", Some(json!("A-B-C"))).unwrap(),
+            Some(json!("a b c"))
+        );
+        assert_eq!(
+            run("a | urlencode", Some(json!("a b&c=d"))).unwrap(),
+            Some(json!("a%20b%26c%3Dd"))
+        );
+        assert_eq!(
+            run("a | escape", Some(json!("<i>"))).unwrap(),
+            Some(json!("&lt;i&gt;"))
+        );
+        assert_eq!(
+            run("a | escape('shell')", Some(json!("a b"))).unwrap(),
+            Some(json!("'a b'"))
+        );
+        assert_eq!(run("a | raw", Some(json!(1))).unwrap(), Some(json!(1)));
+    }
+
+    #[test]
+    fn test_default_filter() {
+        assert_eq!(
+            run(r#"a | default("n/a")"#, None).unwrap(),
+            Some(json!("n/a"))
+        );
+        assert_eq!(
+            run(r#"a | default("n/a")"#, Some(json!(""))).unwrap(),
+            Some(json!("n/a"))
+        );
+        assert_eq!(
+            run(r#"a | default(0)"#, Some(json!(5))).unwrap(),
+            Some(json!(5))
+        );
+        // Missing values skip the other filters
+        assert_eq!(run("a | upper", None).unwrap(), None);
+    }
+
+    #[test]
+    fn test_collection_filters() {
+        let items = json!(["a", 1, true]);
+        assert_eq!(
+            run(r#"a | join(", ")"#, Some(items.clone())).unwrap(),
+            Some(json!("a, 1, true"))
+        );
+        assert_eq!(
+            run("a | length", Some(items.clone())).unwrap(),
+            Some(json!(3))
+        );
+        assert_eq!(
+            run("a | first", Some(items.clone())).unwrap(),
+            Some(json!("a"))
+        );
+        assert_eq!(run("a | last", Some(items)).unwrap(), Some(json!(true)));
+        assert_eq!(
+            run("a | split(',') | length", Some(json!("x,y"))).unwrap(),
+            Some(json!(2))
+        );
+        assert_eq!(
+            run("a | json", Some(json!({"k": [1]}))).unwrap(),
+            Some(json!(r#"{"k":[1]}"#))
+        );
+    }
+
+    #[test]
+    fn test_date_filter() {
+        assert_eq!(
+            run(
+                r#"a | date("%Y-%m-%d")"#,
+                Some(json!("2024-07-04T23:30:00Z"))
+            )
+            .unwrap(),
+            Some(json!("2024-07-04"))
+        );
+        assert_eq!(
+            run(
+                r#"a | date("%Y-%m-%d %H:%M", "Europe/Berlin")"#,
+                Some(json!("2024-07-04T23:30:00Z"))
+            )
+            .unwrap(),
+            Some(json!("2024-07-05 01:30"))
+        );
+        assert_eq!(
+            run(r#"a | date("%Y-%m-%d")"#, Some(json!(1720137600))).unwrap(),
+            Some(json!("2024-07-05"))
+        );
+        assert_eq!(
+            run(r#"a | date("%d/%m/%Y")"#, Some(json!(1720137600000i64))).unwrap(),
+            Some(json!("05/07/2024"))
+        );
+        assert!(run(
+            r#"a | date("%Y", "Mars/Olympus")"#,
+            Some(json!("2024-07-04"))
+        )
+        .is_err());
+        assert!(run(r#"a | date("%Q")"#, Some(json!("2024-07-04"))).is_err());
+        assert!(run(r#"a | date"#, Some(json!("not a date"))).is_err());
+    }
+}
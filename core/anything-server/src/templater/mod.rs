@@ -3,6 +3,12 @@ use std::collections::HashMap;
 use std::error::Error;
 
 use crate::types::json_schema::{ValidationField, ValidationFieldType};
+use compiled::{CompiledValue, Part};
+pub mod blocks;
+pub mod compiled;
+pub mod escape;
+pub mod filters;
+pub mod path;
 pub mod utils;
 
 #[derive(Debug)]
@@ -50,23 +56,24 @@ impl Templater {
                 variable: template_name.to_string(),
             })?;
 
-        self.extract_variables(template)
+        Self::extract_variables(template)
     }
 
-    fn extract_variables(&self, value: &Value) -> Result<Vec<String>, TemplateError> {
+    fn extract_variables(value: &Value) -> Result<Vec<String>, TemplateError> {
         let mut variables = Vec::new();
         match value {
             Value::Object(map) => {
                 for (_, v) in map {
-                    variables.extend(self.extract_variables(v)?);
+                    variables.extend(Self::extract_variables(v)?);
                 }
             }
             Value::Array(arr) => {
                 for v in arr {
-                    variables.extend(self.extract_variables(v)?);
+                    variables.extend(Self::extract_variables(v)?);
                 }
             }
             Value::String(s) => {
+                variables.extend(blocks::block_paths(s));
                 let mut start = 0;
                 while let Some(open_idx) = s[start..].find("{{") {
                     let open_idx = start + open_idx;
@@ -75,8 +82,7 @@ impl Templater {
                         variable: s.to_string(),
                     })?;
                     let close_idx = open_idx + close_idx;
-                    let variable = s[open_idx + 2..close_idx].trim().to_string();
-                    variables.push(variable);
+                    variables.extend(filters::expression_paths(&s[open_idx + 2..close_idx]));
                     start = close_idx + 2;
                 }
             }
@@ -85,51 +91,24 @@ impl Templater {
         Ok(variables)
     }
 
-    fn get_value_from_path(
-        context: &Value,
-        path: &str,
-        expected_type: &ValidationFieldType,
-    ) -> Option<Value> {
-        let mut current = context;
-        let parts: Vec<&str> = path.split('.').collect();
-
-        for (i, part) in parts.iter().enumerate() {
-            if let Some(index_start) = part.find('[') {
-                let key = &part[..index_start];
-                let index_end = part.find(']').unwrap_or(part.len());
-                let index: usize = part[index_start + 1..index_end].parse().ok()?;
-
-                current = current.get(key)?;
-                if current.is_array() {
-                    current = current.get(index)?;
-                } else {
-                    return None; // Not an array when we expected one
-                }
-            } else {
-                current = current.get(part)?;
-            }
+    fn variable_not_found(context: &Value, variable: &str) -> TemplateError {
+        let hints = filters::expression_paths(variable)
+            .iter()
+            .filter_map(|path| utils::describe_missing_path(context, path))
+            .collect::<Vec<_>>();
 
-            if let Value::String(s) = current {
-                // Only parse JSON if the expected type is not String
-                if *expected_type != ValidationFieldType::String {
-                    if let Ok(parsed) = serde_json::from_str(s) {
-                        if i < parts.len() - 1 {
-                            // If not the last part, continue traversing
-                            return Self::get_value_from_path(
-                                &parsed,
-                                &parts[i + 1..].join("."),
-                                expected_type,
-                            );
-                        } else {
-                            // If it's the last part, return the parsed value
-                            return Some(parsed);
-                        }
-                    }
-                }
-            }
+        TemplateError {
+            message: if hints.is_empty() {
+                format!("Variable not found in context: {}", variable)
+            } else {
+                format!(
+                    "Variable not found in context: {} ({})",
+                    variable,
+                    hints.join("; ")
+                )
+            },
+            variable: variable.to_string(),
         }
-        println!("[GET VALUE FROM PATH] {}", current.clone());
-        Some(current.clone())
     }
 
     pub fn render(
@@ -150,22 +129,22 @@ impl Templater {
         println!("[TEMPLATER] Context: {:?}", context);
         println!("[TEMPLATER] Validations: {:?}", validations);
 
-        self.render_value(template, context, &validations, &[])
+        let compiled = compiled::compile_value(template, true)?;
+        self.render_value(&compiled, context, &validations, &[])
     }
     fn render_value(
         &self,
-        template: &Value,
+        template: &CompiledValue,
         context: &Value,
         validations: &HashMap<String, ValidationField>,
         path: &[String],
     ) -> Result<Value, TemplateError> {
         match template {
-            Value::Object(map) => {
+            CompiledValue::Object(entries) => {
                 let mut result = serde_json::Map::new();
-                for (k, v) in map {
+                for (k, v) in entries {
                     let mut current_path = path.to_vec();
                     current_path.push(k.clone());
-                    println!("[RENDER VALUE] Current path: {:?} - 1", current_path);
                     if path.is_empty() {
                         let expected_validation_field =
                             Self::get_validation_field(validations, &k)?;
@@ -185,288 +164,260 @@ impl Templater {
                 }
                 Ok(Value::Object(result))
             }
-            Value::Array(arr) => {
+            CompiledValue::Array(items) => {
                 let mut result = Vec::new();
-                for v in arr.iter() {
-                    println!("[RENDER VALUE] Current path: {:?} - 2", path);
+                for v in items {
                     result.push(self.render_value(v, context, validations, path)?);
                 }
                 Ok(Value::Array(result))
             }
-            Value::String(s) => {
-                let trimmed = s.trim();
-                //Item is "ALL" variable
-                if trimmed.starts_with("{{") && trimmed.ends_with("}}") {
-                    let variable = trimmed[2..trimmed.len() - 2].trim();
-                    println!("[RENDER VALUE] Current path: {:?} - 3", path);
-                    // Only validate if this is a top-level path
-                    if path.is_empty() {
-                        let validation_key = variable.to_string();
-                        let expected_validation_field =
-                            Self::get_validation_field(validations, &validation_key)?;
-                        let value = Self::get_value_from_path(
-                            context,
-                            variable,
-                            &expected_validation_field.r#type,
-                        );
-                        let value = match value {
-                            Some(value) => value,
-                            None => {
-                                if !expected_validation_field.strict {
-                                    match expected_validation_field.r#type {
-                                        ValidationFieldType::String => {
-                                            Value::String("".to_string())
-                                        }
-                                        ValidationFieldType::Number => {
-                                            Value::Number(serde_json::Number::from(0))
-                                        }
-                                        ValidationFieldType::Boolean => Value::Bool(false),
-                                        ValidationFieldType::Array => Value::Array(vec![]),
-                                        ValidationFieldType::Object => {
-                                            Value::Object(serde_json::Map::new())
-                                        }
-                                        ValidationFieldType::Null => Value::Null,
-                                        _ => {
-                                            return Err(TemplateError {
-                                                message: format!(
-                                                    "Unsupported validation type for variable: {}",
-                                                    variable
-                                                ),
-                                                variable: variable.to_string(),
-                                            });
-                                        }
+            // `{% %}` blocks render their text with the same interpolation as below
+            CompiledValue::Blocks(nodes) => {
+                let mut output = String::new();
+                self.render_nodes(nodes, context, validations, path, &mut output)?;
+                Ok(Value::String(output))
+            }
+            //Item is "ALL" variable
+            CompiledValue::Variable(expression) => {
+                let variable = expression.source.as_str();
+                // Only validate if this is a top-level path
+                if path.is_empty() {
+                    let validation_key = variable.to_string();
+                    let expected_validation_field =
+                        Self::get_validation_field(validations, &validation_key)?;
+                    let value = expression.evaluate(context, &expected_validation_field.r#type)?;
+                    let value = match value {
+                        Some(value) => value,
+                        None => {
+                            if !expected_validation_field.strict {
+                                match expected_validation_field.r#type {
+                                    ValidationFieldType::String => Value::String("".to_string()),
+                                    ValidationFieldType::Number => {
+                                        Value::Number(serde_json::Number::from(0))
+                                    }
+                                    ValidationFieldType::Boolean => Value::Bool(false),
+                                    ValidationFieldType::Array => Value::Array(vec![]),
+                                    ValidationFieldType::Object => {
+                                        Value::Object(serde_json::Map::new())
+                                    }
+                                    ValidationFieldType::Null => Value::Null,
+                                    _ => {
+                                        return Err(TemplateError {
+                                            message: format!(
+                                                "Unsupported validation type for variable: {}",
+                                                variable
+                                            ),
+                                            variable: variable.to_string(),
+                                        });
                                     }
-                                } else {
-                                    return Err(TemplateError {
-                                        message: format!(
-                                            "Variable not found in context: {}",
-                                            variable
-                                        ),
-                                        variable: variable.to_string(),
-                                    });
                                 }
+                            } else {
+                                return Err(Self::variable_not_found(context, variable));
                             }
-                        };
-                        let value = self.validate_and_convert_value(
-                            value,
-                            &expected_validation_field.r#type,
-                            &validation_key,
-                        )?;
-                        return Ok(value);
-                    } else {
-                        // For nested variables, just get the value without validation
-                        let value = Self::get_value_from_path(
-                            context,
-                            variable,
-                            &ValidationFieldType::Unknown,
-                        );
-                        let value = match value {
-                            Some(value) => value,
-                            None => {
-                                // Check if the parent field has non-strict validation
-                                if !path.is_empty() {
-                                    println!("[RENDER VALUE] Current path: {:?} - 4", path);
-                                    // Get the first element of path (top-level field name)
-                                    let top_field = &path[0];
-                                    // Look up validation for this field
-                                    if let Ok(field_validation) =
-                                        Self::get_validation_field(validations, top_field)
-                                    {
-                                        // If parent field is non-strict, return appropriate default value
-                                        if !field_validation.strict {
-                                            match field_validation.r#type {
-                                                ValidationFieldType::String => {
-                                                    return Ok(Value::String("".to_string()))
-                                                }
-                                                ValidationFieldType::Number => {
-                                                    return Ok(Value::Number(
-                                                        serde_json::Number::from(0),
-                                                    ))
-                                                }
-                                                ValidationFieldType::Boolean => {
-                                                    return Ok(Value::Bool(false))
-                                                }
-                                                ValidationFieldType::Array => {
-                                                    return Ok(Value::Array(vec![]))
-                                                }
-                                                ValidationFieldType::Object => {
-                                                    return Ok(
-                                                        Value::Object(serde_json::Map::new()),
-                                                    )
-                                                }
-                                                ValidationFieldType::Null => {
-                                                    return Ok(Value::Null)
-                                                }
-                                                _ => return Ok(Value::Null),
+                        }
+                    };
+                    self.validate_and_convert_value(
+                        value,
+                        &expected_validation_field.r#type,
+                        &validation_key,
+                    )
+                } else {
+                    // For nested variables, just get the value without validation
+                    let value = expression.evaluate(context, &ValidationFieldType::Unknown)?;
+                    let value = match value {
+                        Some(value) => value,
+                        None => {
+                            // Check if the parent field has non-strict validation
+                            if !path.is_empty() {
+                                // Get the first element of path (top-level field name)
+                                let top_field = &path[0];
+                                // Look up validation for this field
+                                if let Ok(field_validation) =
+                                    Self::get_validation_field(validations, top_field)
+                                {
+                                    // If parent field is non-strict, return appropriate default value
+                                    if !field_validation.strict {
+                                        match field_validation.r#type {
+                                            ValidationFieldType::String => {
+                                                return Ok(Value::String("".to_string()))
+                                            }
+                                            ValidationFieldType::Number => {
+                                                return Ok(Value::Number(serde_json::Number::from(
+                                                    0,
+                                                )))
+                                            }
+                                            ValidationFieldType::Boolean => {
+                                                return Ok(Value::Bool(false))
                                             }
+                                            ValidationFieldType::Array => {
+                                                return Ok(Value::Array(vec![]))
+                                            }
+                                            ValidationFieldType::Object => {
+                                                return Ok(Value::Object(serde_json::Map::new()))
+                                            }
+                                            ValidationFieldType::Null => return Ok(Value::Null),
+                                            _ => return Ok(Value::Null),
                                         }
                                     }
                                 }
-
-                                // Otherwise error as before
-                                return Err(TemplateError {
-                                    message: format!("Variable not found in context: {}", variable),
-                                    variable: variable.to_string(),
-                                });
                             }
-                        };
-                        return Ok(value);
-                    }
-                }
-
-                // Regular string interpolation logic
-                let mut result = s.clone();
-                let mut start = 0;
 
-                while let Some(open_idx) = result[start..].find("{{") {
-                    let open_idx = start + open_idx;
-                    let close_idx = result[open_idx..].find("}}").ok_or_else(|| TemplateError {
-                        message: "Unclosed template variable".to_string(),
-                        variable: result.clone(),
-                    })?;
-                    let close_idx = open_idx + close_idx;
-                    let variable = result[open_idx + 2..close_idx].trim();
-                    println!("[RENDER VALUE] Current path: {:?} - 5", path);
+                            // Otherwise error as before
+                            return Err(Self::variable_not_found(context, variable));
+                        }
+                    };
+                    Ok(value)
+                }
+            }
+            // Regular string interpolation logic
+            CompiledValue::Text(parts) => Ok(Value::String(self.render_parts(
+                parts,
+                context,
+                validations,
+                path,
+            )?)),
+            CompiledValue::Literal(value) => Ok(value.clone()),
+        }
+    }
 
-                    // Only validate if this is a top-level path
-                    let value = if path.is_empty() {
-                        println!("[RENDER VALUE] Current path: {:?} - 6", path);
-                        let validation_key = variable.to_string();
-                        let expected_validation_field =
-                            Self::get_validation_field(validations, &validation_key)?;
-                        let value = Self::get_value_from_path(
-                            context,
-                            variable,
-                            &expected_validation_field.r#type,
-                        );
-                        let value = match value {
-                            Some(value) => value,
-                            None => {
-                                if !expected_validation_field.strict {
-                                    match expected_validation_field.r#type {
-                                        ValidationFieldType::String => {
-                                            Value::String("".to_string())
-                                        }
-                                        ValidationFieldType::Number => {
-                                            Value::Number(serde_json::Number::from(0))
-                                        }
-                                        ValidationFieldType::Boolean => Value::Bool(false),
-                                        ValidationFieldType::Array => Value::Array(vec![]),
-                                        ValidationFieldType::Object => {
-                                            Value::Object(serde_json::Map::new())
-                                        }
-                                        ValidationFieldType::Null => Value::Null,
-                                        _ => {
-                                            return Err(TemplateError {
-                                                message: format!(
-                                                    "Unsupported validation type for variable: {}",
-                                                    variable
-                                                ),
-                                                variable: variable.to_string(),
-                                            });
-                                        }
-                                    }
-                                } else {
+    // Renders the `{{ }}` parts of a larger string between its text
+    fn render_parts(
+        &self,
+        parts: &[Part],
+        context: &Value,
+        validations: &HashMap<String, ValidationField>,
+        path: &[String],
+    ) -> Result<String, TemplateError> {
+        let mut result = String::new();
+        // Only the string that is a field's value is in that field's format
+        let escape_mode = match path {
+            [field] => validations
+                .get(field)
+                .and_then(|validation| validation.escape),
+            _ => None,
+        };
+
+        for part in parts {
+            let expression = match part {
+                Part::Text(text) => {
+                    result.push_str(text);
+                    continue;
+                }
+                Part::Expression(expression) => expression,
+            };
+            let variable = expression.source.as_str();
+
+            // Only validate if this is a top-level path
+            let value = if path.is_empty() {
+                let validation_key = variable.to_string();
+                let expected_validation_field =
+                    Self::get_validation_field(validations, &validation_key)?;
+                let value = expression.evaluate(context, &expected_validation_field.r#type)?;
+                let value = match value {
+                    Some(value) => value,
+                    None => {
+                        if !expected_validation_field.strict {
+                            match expected_validation_field.r#type {
+                                ValidationFieldType::String => Value::String("".to_string()),
+                                ValidationFieldType::Number => {
+                                    Value::Number(serde_json::Number::from(0))
+                                }
+                                ValidationFieldType::Boolean => Value::Bool(false),
+                                ValidationFieldType::Array => Value::Array(vec![]),
+                                ValidationFieldType::Object => {
+                                    Value::Object(serde_json::Map::new())
+                                }
+                                ValidationFieldType::Null => Value::Null,
+                                _ => {
                                     return Err(TemplateError {
                                         message: format!(
-                                            "Variable not found in context: {}",
+                                            "Unsupported validation type for variable: {}",
                                             variable
                                         ),
                                         variable: variable.to_string(),
                                     });
                                 }
                             }
-                        };
-                        self.validate_and_convert_value(
-                            value,
-                            &expected_validation_field.r#type,
-                            &validation_key,
-                        )?
-                    } else {
-                        println!("[RENDER VALUE] Current path: {:?} - 7", path);
-                        // Get the first element of path (top-level field name)
-                        let top_field = &path[0];
-                        let expected_validation_field =
-                            Self::get_validation_field(validations, &top_field)?;
-                        let value = Self::get_value_from_path(
-                            context,
-                            variable,
-                            &expected_validation_field.r#type,
-                        );
-
-                        println!("Validation Field: {:?}", expected_validation_field);
-
-                        let value = match value {
-                            Some(value) => value,
-                            None => {
-                                if !expected_validation_field.strict {
-                                    match expected_validation_field.r#type {
-                                        ValidationFieldType::String => {
-                                            Value::String("".to_string())
-                                        }
-                                        ValidationFieldType::Number => {
-                                            Value::Number(serde_json::Number::from(0))
-                                        }
-                                        ValidationFieldType::Boolean => Value::Bool(false),
-                                        ValidationFieldType::Array => Value::Array(vec![]),
-                                        ValidationFieldType::Object => {
-                                            Value::Object(serde_json::Map::new())
-                                        }
-                                        ValidationFieldType::Null => Value::Null,
-                                        _ => {
-                                            return Err(TemplateError {
-                                                message: format!(
-                                                    "Unsupported validation type for variable: {}",
-                                                    variable
-                                                ),
-                                                variable: variable.to_string(),
-                                            });
-                                        }
-                                    }
-                                } else {
+                        } else {
+                            return Err(Self::variable_not_found(context, variable));
+                        }
+                    }
+                };
+                self.validate_and_convert_value(
+                    value,
+                    &expected_validation_field.r#type,
+                    &validation_key,
+                )?
+            } else {
+                // Get the first element of path (top-level field name)
+                let top_field = &path[0];
+                let expected_validation_field =
+                    Self::get_validation_field(validations, &top_field)?;
+                let value = expression.evaluate(context, &expected_validation_field.r#type)?;
+
+                let value = match value {
+                    Some(value) => value,
+                    None => {
+                        if !expected_validation_field.strict {
+                            match expected_validation_field.r#type {
+                                ValidationFieldType::String => Value::String("".to_string()),
+                                ValidationFieldType::Number => {
+                                    Value::Number(serde_json::Number::from(0))
+                                }
+                                ValidationFieldType::Boolean => Value::Bool(false),
+                                ValidationFieldType::Array => Value::Array(vec![]),
+                                ValidationFieldType::Object => {
+                                    Value::Object(serde_json::Map::new())
+                                }
+                                ValidationFieldType::Null => Value::Null,
+                                _ => {
                                     return Err(TemplateError {
                                         message: format!(
-                                            "Variable not found in context: {}",
+                                            "Unsupported validation type for variable: {}",
                                             variable
                                         ),
                                         variable: variable.to_string(),
                                     });
                                 }
                             }
-                        };
-                        value
-
-                        // self.validate_and_convert_value(
-                        //     value,
-                        //     &expected_validation_field.r#type,
-                        //     &top_field,
-                        // )?
-                    };
-
-                    println!("Value: {}", value.clone());
-
-                    let replacement = match value {
-                        Value::String(s) => {
-                            if s.contains('<') && s.contains('>') || s.contains('\n') {
-                                // Escape quotes and newlines manually, without adding extra quotes
-                                s.replace('\\', "\\\\")
-                                    .replace('"', "\\\"")
-                                    .replace('\n', "\\n")
-                            } else {
-                                s.clone()
-                            }
+                        } else {
+                            return Err(Self::variable_not_found(context, variable));
                         }
-                        _ => value.to_string(),
-                    };
-                    println!("Replacement: {}", replacement.clone());
-                    result.replace_range(open_idx..close_idx + 2, &replacement);
-                    start = open_idx + replacement.len();
-                }
-
-                Ok(Value::String(result))
-            }
-            _ => Ok(template.clone()),
+                    }
+                };
+                value
+
+                // self.validate_and_convert_value(
+                //     value,
+                //     &expected_validation_field.r#type,
+                //     &top_field,
+                // )?
+            };
+
+            let replacement = match escape_mode {
+                _ if expression.escapes_itself() => match value {
+                    Value::String(s) => s,
+                    other => other.to_string(),
+                },
+                Some(mode) => escape::escape_value(&value, mode),
+                None => match value {
+                    Value::String(s) => {
+                        if s.contains('<') && s.contains('>') || s.contains('\n') {
+                            // Escape quotes and newlines manually, without adding extra quotes
+                            s.replace('\\', "\\\\")
+                                .replace('"', "\\\"")
+                                .replace('\n', "\\n")
+                        } else {
+                            s.clone()
+                        }
+                    }
+                    _ => value.to_string(),
+                },
+            };
+            result.push_str(&replacement);
         }
+
+        Ok(result)
     }
 
     fn get_validation_field(
@@ -594,6 +545,7 @@ impl Templater {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::json_schema::EscapeMode;
     use serde_json::json;
 
     #[test]
@@ -618,6 +570,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::String,
                 strict: true,
+                escape: None,
             },
         );
 
@@ -655,6 +608,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::String,
                 strict: true,
+                escape: None,
             },
         );
 
@@ -691,6 +645,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::Number,
                 strict: true,
+                escape: None,
             },
         );
 
@@ -730,6 +685,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::Object,
                 strict: true,
+                escape: None,
             },
         );
 
@@ -789,6 +745,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::Object,
                 strict: true,
+                escape: None,
             },
         );
         template_key_validations.insert(
@@ -796,6 +753,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::Number,
                 strict: true,
+                escape: None,
             },
         );
         template_key_validations.insert(
@@ -803,6 +761,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::String,
                 strict: true,
+                escape: None,
             },
         );
         template_key_validations.insert(
@@ -810,6 +769,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::Boolean,
                 strict: true,
+                escape: None,
             },
         );
         template_key_validations.insert(
@@ -817,6 +777,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::Array,
                 strict: true,
+                escape: None,
             },
         );
         template_key_validations.insert(
@@ -824,6 +785,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::Null,
                 strict: true,
+                escape: None,
             },
         );
         template_key_validations.insert(
@@ -831,6 +793,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::Number,
                 strict: true,
+                escape: None,
             },
         );
         template_key_validations.insert(
@@ -838,6 +801,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::String,
                 strict: true,
+                escape: None,
             },
         );
         template_key_validations.insert(
@@ -845,6 +809,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::String,
                 strict: true,
+                escape: None,
             },
         );
         template_key_validations.insert(
@@ -852,6 +817,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::String,
                 strict: true,
+                escape: None,
             },
         );
 
@@ -916,6 +882,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::String,
                 strict: true,
+                escape: None,
             },
         );
         template_key_validations.insert(
@@ -923,6 +890,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::Object,
                 strict: true,
+                escape: None,
             },
         );
         template_key_validations.insert(
@@ -930,6 +898,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::String,
                 strict: true,
+                escape: None,
             },
         );
         let result = templater
@@ -980,6 +949,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::String,
                 strict: true,
+                escape: None,
             },
         );
         template_key_validations.insert(
@@ -987,6 +957,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::String,
                 strict: true,
+                escape: None,
             },
         );
 
@@ -1034,6 +1005,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::Object,
                 strict: true,
+                escape: None,
             },
         );
 
@@ -1082,6 +1054,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::Object,
                 strict: true,
+                escape: None,
             },
         );
         validations.insert(
@@ -1089,6 +1062,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::Array,
                 strict: true,
+                escape: None,
             },
         );
 
@@ -1152,6 +1126,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::Object,
                 strict: true,
+                escape: None,
             },
         );
 
@@ -1218,6 +1193,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::String,
                 strict: true,
+                escape: None,
             },
         );
         validations.insert(
@@ -1225,6 +1201,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::String,
                 strict: true,
+                escape: None,
             },
         );
         validations.insert(
@@ -1232,6 +1209,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::Object,
                 strict: true,
+                escape: None,
             },
         );
         validations.insert(
@@ -1239,6 +1217,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::Object,
                 strict: true,
+                escape: None,
             },
         );
 
@@ -1329,6 +1308,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::String,
                 strict: true,
+                escape: None,
             },
         );
         validations.insert(
@@ -1336,6 +1316,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::String,
                 strict: true,
+                escape: None,
             },
         );
         validations.insert(
@@ -1343,6 +1324,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::String,
                 strict: true,
+                escape: None,
             },
         );
         validations.insert(
@@ -1350,6 +1332,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::String,
                 strict: true,
+                escape: None,
             },
         );
         validations.insert(
@@ -1357,6 +1340,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::String,
                 strict: true,
+                escape: None,
             },
         );
         validations.insert(
@@ -1364,6 +1348,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::String,
                 strict: true,
+                escape: None,
             },
         );
         validations.insert(
@@ -1371,6 +1356,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::String,
                 strict: true,
+                escape: None,
             },
         );
         validations.insert(
@@ -1378,6 +1364,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::Object,
                 strict: true,
+                escape: None,
             },
         );
 
@@ -1496,6 +1483,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::Object,
                 strict: true,
+                escape: None,
             },
         );
 
@@ -1603,6 +1591,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::Object,
                 strict: true,
+                escape: None,
             },
         );
         validations.insert(
@@ -1610,6 +1599,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::Object,
                 strict: true,
+                escape: None,
             },
         );
         validations.insert(
@@ -1617,6 +1607,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::Object,
                 strict: true,
+                escape: None,
             },
         );
         validations.insert(
@@ -1624,6 +1615,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::Object,
                 strict: true,
+                escape: None,
             },
         );
         validations.insert(
@@ -1631,6 +1623,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::Object,
                 strict: true,
+                escape: None,
             },
         );
         validations.insert(
@@ -1638,6 +1631,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::Object,
                 strict: true,
+                escape: None,
             },
         );
         validations.insert(
@@ -1645,6 +1639,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::Object,
                 strict: true,
+                escape: None,
             },
         );
         validations.insert(
@@ -1652,6 +1647,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::Object,
                 strict: true,
+                escape: None,
             },
         );
 
@@ -1740,6 +1736,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::String,
                 strict: false,
+                escape: None,
             },
         );
         template_key_validations.insert(
@@ -1747,6 +1744,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::Object,
                 strict: true,
+                escape: None,
             },
         );
         template_key_validations.insert(
@@ -1754,6 +1752,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::String,
                 strict: true,
+                escape: None,
             },
         );
         let result = templater
@@ -1806,6 +1805,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::String,
                 strict: true,
+                escape: None,
             },
         );
         template_key_validations.insert(
@@ -1813,6 +1813,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::Number,
                 strict: true,
+                escape: None,
             },
         );
         template_key_validations.insert(
@@ -1820,6 +1821,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::Object,
                 strict: false,
+                escape: None,
             },
         );
         template_key_validations.insert(
@@ -1827,6 +1829,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::String,
                 strict: false,
+                escape: None,
             },
         );
 
@@ -1874,6 +1877,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::String,
                 strict: false,
+                escape: None,
             },
         );
         template_key_validations.insert(
@@ -1881,6 +1885,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::String,
                 strict: true,
+                escape: None,
             },
         );
 
@@ -1930,6 +1935,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::String,
                 strict: true,
+                escape: None,
             },
         );
 
@@ -1938,6 +1944,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::Object,
                 strict: true,
+                escape: None,
             },
         );
 
@@ -1946,6 +1953,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::String,
                 strict: true,
+                escape: None,
             },
         );
 
@@ -1990,6 +1998,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::String,
                 strict: false,
+                escape: None,
             },
         );
         template_key_validations.insert(
@@ -1997,6 +2006,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::String,
                 strict: false,
+                escape: None,
             },
         );
         // Last field is strict
@@ -2005,6 +2015,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::String,
                 strict: true,
+                escape: None,
             },
         );
 
@@ -2074,6 +2085,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::String,
                 strict: true,
+                escape: None,
             },
         );
         validations.insert(
@@ -2081,6 +2093,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::String,
                 strict: true,
+                escape: None,
             },
         );
         validations.insert(
@@ -2088,6 +2101,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::String,
                 strict: true,
+                escape: None,
             },
         );
         validations.insert(
@@ -2095,6 +2109,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::String,
                 strict: true,
+                escape: None,
             },
         );
         validations.insert(
@@ -2102,6 +2117,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::String,
                 strict: false,
+                escape: None,
             },
         );
         validations.insert(
@@ -2109,6 +2125,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::String,
                 strict: false,
+                escape: None,
             },
         );
         validations.insert(
@@ -2116,6 +2133,7 @@ mod tests {
             ValidationField {
                 r#type: ValidationFieldType::String,
                 strict: false,
+                escape: None,
             },
         );
 
@@ -2140,4 +2158,284 @@ mod tests {
 
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn test_filters_in_templates() {
+        let mut templater = Templater::new();
+
+        let template = json!({
+            "subject": "Order for {{ variables.name | upper | truncate(4) }}",
+            "tags": "{{ variables.tags | join(\", \") }}",
+            "note": "{{ variables.missing | default(\"n/a\") }}"
+        });
+
+        templater.add_template("test_template", template);
+
+        let context = json!({
+            "variables": {
+                "name": "Johnny",
+                "tags": ["a", "b"]
+            }
+        });
+
+        let mut validations = HashMap::new();
+        for key in ["subject", "tags", "note"] {
+            validations.insert(
+                key.to_string(),
+                ValidationField {
+                    r#type: ValidationFieldType::String,
+                    strict: true,
+                    escape: None,
+                },
+            );
+        }
+
+        let result = templater
+            .render("test_template", &context, validations)
+            .unwrap();
+
+        assert_eq!(
+            result,
+            json!({
+                "subject": "Order for JOHN",
+                "tags": "a, b",
+                "note": "n/a"
+            })
+        );
+    }
+
+    #[test]
+    fn test_fallbacks_and_optional_chaining() {
+        let mut templater = Templater::new();
+
+        let template = json!({
+            "email": "{{ variables.contact?.email ?? secrets.DEFAULT_EMAIL }}",
+            "name": "Hi {{ variables.contact?.name ?? \"there\" | upper }}",
+            "details": {
+                "city": "{{ variables.contact?.address.city }}"
+            }
+        });
+
+        templater.add_template("test_template", template);
+
+        let context = json!({
+            "variables": { "contact": null },
+            "secrets": { "DEFAULT_EMAIL": "team@example.com" }
+        });
+
+        let mut validations = HashMap::new();
+        for (key, r#type) in [
+            ("email", ValidationFieldType::String),
+            ("name", ValidationFieldType::String),
+            ("details", ValidationFieldType::Object),
+        ] {
+            validations.insert(
+                key.to_string(),
+                ValidationField {
+                    r#type,
+                    strict: true,
+                    escape: None,
+                },
+            );
+        }
+
+        let result = templater
+            .render("test_template", &context, validations)
+            .unwrap();
+
+        assert_eq!(
+            result,
+            json!({
+                "email": "team@example.com",
+                "name": "Hi THERE",
+                "details": { "city": null }
+            })
+        );
+    }
+
+    #[test]
+    fn test_optional_chaining_still_requires_the_start_of_the_path() {
+        let mut templater = Templater::new();
+
+        templater.add_template(
+            "test_template",
+            json!({ "email": "{{ variables.contact?.email }}" }),
+        );
+
+        let mut validations = HashMap::new();
+        validations.insert(
+            "email".to_string(),
+            ValidationField {
+                r#type: ValidationFieldType::String,
+                strict: true,
+                escape: None,
+            },
+        );
+
+        let result = templater.render("test_template", &json!({ "variables": {} }), validations);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_strict_error_names_resolved_path_and_near_misses() {
+        let mut templater = Templater::new();
+
+        templater.add_template(
+            "test_template",
+            json!({ "to": "{{ actions.lookup.result.emial }}" }),
+        );
+
+        let context = json!({
+            "actions": {
+                "lookup": {
+                    "result": { "email": "a@example.com", "name": "A" }
+                }
+            }
+        });
+
+        let mut validations = HashMap::new();
+        validations.insert(
+            "to".to_string(),
+            ValidationField {
+                r#type: ValidationFieldType::String,
+                strict: true,
+                escape: None,
+            },
+        );
+
+        let error = templater
+            .render("test_template", &context, validations)
+            .unwrap_err();
+
+        assert!(error.message.contains("Variable not found in context"));
+        assert!(error
+            .message
+            .contains("'actions.lookup.result' has no key 'emial', did you mean 'email'?"));
+    }
+
+    #[test]
+    fn test_wildcard_and_filter_paths() {
+        let mut templater = Templater::new();
+
+        let template = json!({
+            "emails": "{{ actions.lookup.result.contacts[*].email }}",
+            "first_open": "{{ actions.lookup.result.contacts[?(@.status == 'open')] | first }}",
+            "last_name": "Last: {{ actions.lookup.result.contacts[-1].name }}",
+            "count": "{{ actions.lookup.result.contacts[?(@.status != 'open')] | length }}"
+        });
+
+        templater.add_template("test_template", template);
+
+        let context = json!({
+            "actions": {
+                "lookup": {
+                    "result": {
+                        "contacts": [
+                            { "name": "A", "email": "a@example.com", "status": "closed" },
+                            { "name": "B", "email": "b@example.com", "status": "open" }
+                        ]
+                    }
+                }
+            }
+        });
+
+        let mut validations = HashMap::new();
+        for (key, r#type) in [
+            ("emails", ValidationFieldType::Array),
+            ("first_open", ValidationFieldType::Object),
+            ("last_name", ValidationFieldType::String),
+            ("count", ValidationFieldType::Number),
+        ] {
+            validations.insert(
+                key.to_string(),
+                ValidationField {
+                    r#type,
+                    strict: true,
+                    escape: None,
+                },
+            );
+        }
+
+        let result = templater
+            .render("test_template", &context, validations)
+            .unwrap();
+
+        assert_eq!(
+            result,
+            json!({
+                "emails": ["a@example.com", "b@example.com"],
+                "first_open": { "name": "B", "email": "b@example.com", "status": "open" },
+                "last_name": "Last: B",
+                "count": 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_escaping_by_field() {
+        let mut templater = Templater::new();
+
+        let template = json!({
+            "html_body": "<p>{{ trigger.comment }}</p>{% for tag in trigger.tags %}<i>{{ tag }}</i>{% endfor %}",
+            "json_body": "{\"comment\": \"{{ trigger.comment }}\", \"tags\": {{ trigger.tags }}}",
+            "command": "echo {{ trigger.comment }}",
+            "link": "<a href=\"https://example.com?q={{ trigger.comment | escape('url') }}\">{{ trigger.comment | raw }}</a>",
+            "whole": "{{ trigger.comment }}"
+        });
+
+        templater.add_template("test_template", template);
+
+        let context = json!({
+            "trigger": {
+                "comment": "Tom & \"Jerry\" <script>",
+                "tags": ["a<b"]
+            }
+        });
+
+        let mut validations = HashMap::new();
+        for (key, r#type, escape) in [
+            (
+                "html_body",
+                ValidationFieldType::String,
+                Some(EscapeMode::Html),
+            ),
+            (
+                "json_body",
+                ValidationFieldType::Object,
+                Some(EscapeMode::Json),
+            ),
+            (
+                "command",
+                ValidationFieldType::String,
+                Some(EscapeMode::Shell),
+            ),
+            ("link", ValidationFieldType::String, Some(EscapeMode::Html)),
+            ("whole", ValidationFieldType::String, Some(EscapeMode::Html)),
+        ] {
+            validations.insert(
+                key.to_string(),
+                ValidationField {
+                    r#type,
+                    strict: true,
+                    escape,
+                },
+            );
+        }
+
+        let result = templater
+            .render("test_template", &context, validations)
+            .unwrap();
+
+        assert_eq!(
+            result,
+            json!({
+                "html_body": "<p>Tom &amp; &quot;Jerry&quot; &lt;script&gt;</p><i>a&lt;b</i>",
+                "json_body": { "comment": "Tom & \"Jerry\" <script>", "tags": ["a<b"] },
+                "command": "echo 'Tom & \"Jerry\" <script>'",
+                "link": "<a href=\"https://example.com?q=Tom%20%26%20%22Jerry%22%20%3Cscript%3E\">Tom & \"Jerry\" <script></a>",
+                "whole": "Tom & \"Jerry\" <script>"
+            })
+        );
+    }
 }
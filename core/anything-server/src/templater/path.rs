@@ -0,0 +1,532 @@
+//! JSONPath-style paths into the render context.
+//!
+//! - `actions.x.result.name`, `items[0]`, `items[-1]`, `items.0`, `data['key with.dots']`
+//! - `contacts[*].email`, `contacts.*.email` for every item or object value
+//! - `items[1:3]`, `items[:2]`, `items[::2]` slices, `items[::-1]` walks them backwards
+//! - `items[?(@.status == 'open' && @.total > 10)]` keeps the items matching the filter,
+//!   with the same comparisons as `{% if %}` blocks
+//! - `result..email` finds `email` at any depth
+//!
+//! Wildcards, slices, filters and `..` make the path return an array of every match (empty when
+//! nothing matched), plain paths return the single value or nothing.
+
+use serde_json::Value;
+use std::borrow::Cow;
+
+use super::blocks::{compare_with, is_truthy, COMPARISONS};
+use super::filters::{parse_literal, split_top_level};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Selector {
+    Key(String),
+    Index(i64),
+    Wildcard,
+    Slice {
+        start: Option<i64>,
+        end: Option<i64>,
+        step: i64,
+    },
+    Filter(Predicate),
+}
+
+// `@.status == 'open' && @.total > 10`, `!@.archived`, `@ > 3`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Predicate {
+    Any(Vec<Predicate>),
+    All(Vec<Predicate>),
+    Not(Box<Predicate>),
+    Compare {
+        op: &'static str,
+        left: Operand,
+        right: Operand,
+    },
+    Truthy(Operand),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    // `@` is the item being filtered, `@.a.b` a path below it
+    Current(Vec<Segment>),
+    Literal(Value),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    pub selector: Selector,
+    // `..` before the selector, it applies at every depth
+    pub descendants: bool,
+}
+
+impl Segment {
+    // Whether the segment can match more than one value
+    pub fn is_multiple(&self) -> bool {
+        self.descendants
+            || matches!(
+                self.selector,
+                Selector::Wildcard | Selector::Slice { .. } | Selector::Filter(_)
+            )
+    }
+}
+
+// Index of the `]` closing the bracket `path` starts with
+fn bracket_end(path: &str) -> Option<usize> {
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    let mut depth = 0usize;
+
+    for (i, c) in path.char_indices() {
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        match c {
+            '"' | '\'' => quote = Some(c),
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_bracket(inner: &str) -> Option<Selector> {
+    let inner = inner.trim();
+
+    if inner == "*" {
+        return Some(Selector::Wildcard);
+    }
+    if let Some(filter) = inner.strip_prefix('?') {
+        let filter = filter.trim();
+        let filter = filter
+            .strip_prefix('(')
+            .and_then(|f| f.strip_suffix(')'))
+            .unwrap_or(filter);
+        return Some(Selector::Filter(parse_predicate(filter)));
+    }
+    if inner.starts_with('"') || inner.starts_with('\'') {
+        return match parse_literal(inner)? {
+            Value::String(key) => Some(Selector::Key(key)),
+            _ => None,
+        };
+    }
+    if inner.contains(':') {
+        let parts: Vec<&str> = inner.split(':').map(str::trim).collect();
+        if parts.len() > 3 {
+            return None;
+        }
+        let bound = |part: Option<&&str>| -> Option<Option<i64>> {
+            match part {
+                None | Some(&"") => Some(None),
+                Some(part) => part.parse().ok().map(Some),
+            }
+        };
+        return Some(Selector::Slice {
+            start: bound(parts.first())?,
+            end: bound(parts.get(1))?,
+            step: bound(parts.get(2))?.unwrap_or(1),
+        });
+    }
+    inner.parse().ok().map(Selector::Index)
+}
+
+/// Parses a path, None when it isn't valid.
+pub fn parse_path(path: &str) -> Option<Vec<Segment>> {
+    let path = path.trim();
+    if path.is_empty() {
+        return None;
+    }
+    let mut rest = path.strip_prefix('$').unwrap_or(path);
+    let mut segments = Vec::new();
+    let mut needs_separator = false;
+
+    while !rest.is_empty() {
+        let mut descendants = false;
+        if let Some(after) = rest.strip_prefix("..") {
+            descendants = true;
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix('.') {
+            rest = after;
+        } else if needs_separator && !rest.starts_with('[') {
+            return None;
+        }
+
+        let selector = if rest.starts_with('[') {
+            let close = bracket_end(rest)?;
+            let selector = parse_bracket(&rest[1..close])?;
+            rest = &rest[close + 1..];
+            selector
+        } else {
+            let end = rest.find(['.', '[']).unwrap_or(rest.len());
+            let key = &rest[..end];
+            if key.is_empty() {
+                return None;
+            }
+            rest = &rest[end..];
+            if key == "*" {
+                Selector::Wildcard
+            } else {
+                Selector::Key(key.to_string())
+            }
+        };
+
+        segments.push(Segment {
+            selector,
+            descendants,
+        });
+        needs_separator = true;
+    }
+
+    Some(segments)
+}
+
+fn resolve_index(index: i64, len: usize) -> Option<usize> {
+    let index = if index < 0 { len as i64 + index } else { index };
+    (0..len as i64).contains(&index).then_some(index as usize)
+}
+
+// Same bounds as JSONPath (RFC 9535), a step of 0 selects nothing
+fn slice_indices(start: Option<i64>, end: Option<i64>, step: i64, len: usize) -> Vec<usize> {
+    let len = len as i64;
+    let normalize = |bound: i64| if bound < 0 { len + bound } else { bound };
+
+    let mut indices = Vec::new();
+    if step > 0 {
+        let lower = normalize(start.unwrap_or(0)).clamp(0, len);
+        let upper = normalize(end.unwrap_or(len)).clamp(0, len);
+        let mut index = lower;
+        while index < upper {
+            indices.push(index as usize);
+            index += step;
+        }
+    } else if step < 0 {
+        let upper = start.map_or(len - 1, normalize).clamp(-1, len - 1);
+        let lower = end.map_or(-1, normalize).clamp(-1, len - 1);
+        let mut index = upper;
+        while lower < index {
+            indices.push(index as usize);
+            index += step;
+        }
+    }
+    indices
+}
+
+fn select_children<'v>(value: &'v Value, selector: &Selector) -> Vec<&'v Value> {
+    match (selector, value) {
+        (Selector::Key(key), Value::Object(map)) => map.get(key).into_iter().collect(),
+        // `items.0` works like `items[0]`
+        (Selector::Key(key), Value::Array(items)) => key
+            .parse::<usize>()
+            .ok()
+            .and_then(|index| items.get(index))
+            .into_iter()
+            .collect(),
+        (Selector::Index(index), Value::Array(items)) => resolve_index(*index, items.len())
+            .map(|index| &items[index])
+            .into_iter()
+            .collect(),
+        (Selector::Wildcard, Value::Array(items)) => items.iter().collect(),
+        (Selector::Wildcard, Value::Object(map)) => map.values().collect(),
+        (Selector::Slice { start, end, step }, Value::Array(items)) => {
+            slice_indices(*start, *end, *step, items.len())
+                .into_iter()
+                .map(|index| &items[index])
+                .collect()
+        }
+        (Selector::Filter(filter), Value::Array(items)) => {
+            items.iter().filter(|item| filter.matches(item)).collect()
+        }
+        (Selector::Filter(filter), Value::Object(map)) => {
+            map.values().filter(|item| filter.matches(item)).collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+// The value itself and everything below it, parents before children
+fn collect_descendants<'v>(value: &'v Value, out: &mut Vec<&'v Value>) {
+    out.push(value);
+    match value {
+        Value::Array(items) => items.iter().for_each(|item| collect_descendants(item, out)),
+        Value::Object(map) => map.values().for_each(|item| collect_descendants(item, out)),
+        _ => {}
+    }
+}
+
+fn apply_segment<'v>(value: &'v Value, segment: &Segment) -> Vec<&'v Value> {
+    if !segment.descendants {
+        return select_children(value, &segment.selector);
+    }
+    let mut targets = Vec::new();
+    collect_descendants(value, &mut targets);
+    targets
+        .into_iter()
+        .flat_map(|target| select_children(target, &segment.selector))
+        .collect()
+}
+
+// Strings holding JSON are read as the JSON they hold
+fn parse_json_string(value: Cow<'_, Value>) -> Cow<'_, Value> {
+    if let Value::String(s) = value.as_ref() {
+        if let Ok(parsed) = serde_json::from_str(s) {
+            return Cow::Owned(parsed);
+        }
+    }
+    value
+}
+
+/// Selects the path from `root`. With `parse_json`, strings holding JSON are parsed as they're
+/// passed through, so paths can reach into JSON bodies that were stored as text.
+pub fn select(root: &Value, segments: &[Segment], parse_json: bool) -> Option<Value> {
+    let mut values: Vec<Cow<Value>> = vec![Cow::Borrowed(root)];
+    let mut multiple = false;
+
+    for segment in segments {
+        let mut next = Vec::new();
+        for value in values {
+            match value {
+                Cow::Borrowed(value) => {
+                    next.extend(apply_segment(value, segment).into_iter().map(Cow::Borrowed))
+                }
+                Cow::Owned(value) => next.extend(
+                    apply_segment(&value, segment)
+                        .into_iter()
+                        .map(|child| Cow::Owned(child.clone())),
+                ),
+            }
+        }
+        if parse_json {
+            next = next.into_iter().map(parse_json_string).collect();
+        }
+
+        multiple |= segment.is_multiple();
+        if !multiple && next.is_empty() {
+            return None;
+        }
+        values = next;
+    }
+
+    if multiple {
+        Some(Value::Array(
+            values.into_iter().map(Cow::into_owned).collect(),
+        ))
+    } else {
+        values.into_iter().next().map(Cow::into_owned)
+    }
+}
+
+fn parse_predicate(filter: &str) -> Predicate {
+    let any_of = split_top_level(filter, "||");
+    if any_of.len() > 1 {
+        return Predicate::Any(any_of.into_iter().map(parse_predicate).collect());
+    }
+    let all_of = split_top_level(filter, "&&");
+    if all_of.len() > 1 {
+        return Predicate::All(all_of.into_iter().map(parse_predicate).collect());
+    }
+
+    let filter = filter.trim();
+    if let Some(negated) = filter.strip_prefix('!') {
+        if !negated.starts_with('=') {
+            return Predicate::Not(Box::new(parse_predicate(negated)));
+        }
+    }
+    if let Some(inner) = filter.strip_prefix('(').and_then(|f| f.strip_suffix(')')) {
+        return parse_predicate(inner);
+    }
+
+    for op in COMPARISONS {
+        let sides = split_top_level(filter, op);
+        if sides.len() == 2 {
+            return Predicate::Compare {
+                op,
+                left: parse_operand(sides[0]),
+                right: parse_operand(sides[1]),
+            };
+        }
+    }
+
+    Predicate::Truthy(parse_operand(filter))
+}
+
+// Anything that isn't a path or a literal is null
+fn parse_operand(operand: &str) -> Operand {
+    let operand = operand.trim();
+    match operand.strip_prefix('@') {
+        Some("") => Operand::Current(Vec::new()),
+        Some(relative) => parse_path(relative)
+            .map(Operand::Current)
+            .unwrap_or(Operand::Literal(Value::Null)),
+        None => Operand::Literal(parse_literal(operand).unwrap_or(Value::Null)),
+    }
+}
+
+impl Predicate {
+    fn matches(&self, item: &Value) -> bool {
+        match self {
+            Predicate::Any(predicates) => predicates.iter().any(|p| p.matches(item)),
+            Predicate::All(predicates) => predicates.iter().all(|p| p.matches(item)),
+            Predicate::Not(predicate) => !predicate.matches(item),
+            Predicate::Compare { op, left, right } => {
+                compare_with(op, &left.value(item), &right.value(item))
+            }
+            Predicate::Truthy(operand) => is_truthy(&operand.value(item)),
+        }
+    }
+}
+
+impl Operand {
+    fn value(&self, item: &Value) -> Value {
+        match self {
+            Operand::Current(segments) => select(item, segments, true).unwrap_or(Value::Null),
+            Operand::Literal(value) => value.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn get(value: &Value, path: &str) -> Option<Value> {
+        select(value, &parse_path(path)?, true)
+    }
+
+    fn contacts() -> Value {
+        json!({
+            "contacts": [
+                { "id": 1, "email": "a@example.com", "status": "open", "total": 5 },
+                { "id": 5, "email": "b@example.com", "status": "closed", "total": 20 },
+                { "id": 7, "status": "open", "total": "30" }
+            ],
+            "meta": { "owner": { "email": "owner@example.com" } }
+        })
+    }
+
+    #[test]
+    fn test_parse_path() {
+        assert_eq!(
+            parse_path("$.items[-1]['a.b']..name"),
+            Some(vec![
+                Segment {
+                    selector: Selector::Key("items".to_string()),
+                    descendants: false,
+                },
+                Segment {
+                    selector: Selector::Index(-1),
+                    descendants: false,
+                },
+                Segment {
+                    selector: Selector::Key("a.b".to_string()),
+                    descendants: false,
+                },
+                Segment {
+                    selector: Selector::Key("name".to_string()),
+                    descendants: true,
+                },
+            ])
+        );
+        assert_eq!(parse_path("a.."), None);
+        assert_eq!(parse_path("a[0]b"), None);
+        assert_eq!(parse_path("a[x]"), None);
+        assert_eq!(parse_path("a[0"), None);
+    }
+
+    #[test]
+    fn test_plain_paths() {
+        let context = contacts();
+        assert_eq!(get(&context, "contacts[1].id"), Some(json!(5)));
+        assert_eq!(get(&context, "contacts.1.id"), Some(json!(5)));
+        assert_eq!(get(&context, "contacts[-1].id"), Some(json!(7)));
+        assert_eq!(get(&context, "contacts[9].id"), None);
+        assert_eq!(get(&context, "contacts[2].email"), None);
+    }
+
+    #[test]
+    fn test_wildcards_and_slices() {
+        let context = contacts();
+        assert_eq!(
+            get(&context, "contacts[*].email"),
+            Some(json!(["a@example.com", "b@example.com"]))
+        );
+        assert_eq!(get(&context, "contacts.*.id"), Some(json!([1, 5, 7])));
+        assert_eq!(get(&context, "contacts[1:].id"), Some(json!([5, 7])));
+        assert_eq!(get(&context, "contacts[::2].id"), Some(json!([1, 7])));
+        assert_eq!(get(&context, "contacts[:-1].id"), Some(json!([1, 5])));
+        assert_eq!(get(&context, "contacts[::-1].id"), Some(json!([7, 5, 1])));
+        assert_eq!(get(&context, "contacts[::-2].id"), Some(json!([7, 1])));
+        assert_eq!(get(&context, "contacts[1::-1].id"), Some(json!([5, 1])));
+        assert_eq!(get(&context, "contacts[:0:-1].id"), Some(json!([7, 5])));
+        assert_eq!(get(&context, "contacts[-1:-3:-1].id"), Some(json!([7, 5])));
+        assert_eq!(get(&context, "contacts[::0].id"), Some(json!([])));
+        assert_eq!(get(&context, "contacts[*].missing"), Some(json!([])));
+    }
+
+    #[test]
+    fn test_filters() {
+        let context = contacts();
+        assert_eq!(
+            get(&context, "contacts[?(@.id == 5)].email"),
+            Some(json!(["b@example.com"]))
+        );
+        assert_eq!(
+            get(&context, "contacts[?(@.status=='open' && @.total > 10)].id"),
+            Some(json!([7]))
+        );
+        assert_eq!(
+            get(&context, "contacts[?(@.email)].id"),
+            Some(json!([1, 5]))
+        );
+        assert_eq!(
+            get(&context, "contacts[?(!@.email || @.id < 2)].id"),
+            Some(json!([1, 7]))
+        );
+        assert_eq!(
+            get(&json!({ "scores": [1, 5, 3, 8] }), "scores[?(@ >= 3)]"),
+            Some(json!([5, 3, 8]))
+        );
+    }
+
+    #[test]
+    fn test_recursive_descent() {
+        let context = contacts();
+        assert_eq!(
+            get(&context, "..email"),
+            Some(json!([
+                "a@example.com",
+                "b@example.com",
+                "owner@example.com"
+            ]))
+        );
+        assert_eq!(
+            get(&context, "meta..email"),
+            Some(json!(["owner@example.com"]))
+        );
+    }
+
+    #[test]
+    fn test_json_strings_are_traversed() {
+        let context = json!({
+            "result": { "body": "{\"items\": [{\"id\": 1}, {\"id\": 2}]}" }
+        });
+        assert_eq!(
+            get(&context, "result.body.items[*].id"),
+            Some(json!([1, 2]))
+        );
+        assert_eq!(
+            select(&context, &parse_path("result.body").unwrap(), false),
+            Some(json!("{\"items\": [{\"id\": 1}, {\"id\": 2}]}"))
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use super::path::{parse_path, Selector};
 use super::TemplateError;
 use super::Templater;
 use serde_json::Value;
@@ -17,6 +18,11 @@ pub fn get_template_file_requirements(
     templater.add_template("_analysis_template", template.clone());
 
     let variables = templater.get_template_variables("_analysis_template")?;
+    Ok(get_file_requirements(&variables))
+}
+
+// Same as get_template_file_requirements for the variables of an already compiled template
+pub fn get_file_requirements(variables: &[String]) -> Vec<FileRequirement> {
     let mut file_requirements = Vec::new();
 
     // Analyze each variable for file patterns
@@ -39,7 +45,271 @@ pub fn get_template_file_requirements(
         }
     }
 
-    Ok(file_requirements)
+    file_requirements
+}
+
+// Key marking a task result that was offloaded to object storage
+pub const RESULT_REFERENCE_KEY: &str = "_anything_ref";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResultReferenceRequirement {
+    pub pointer: String, // JSON pointer to the reference inside the render context (e.g. "/actions/http/result")
+    pub key: String,     // Storage key of the offloaded result
+}
+
+// Finds the offloaded results a template actually dereferences so only those get fetched
+pub fn get_template_result_reference_requirements(
+    template: &Value,
+    context: &Value,
+) -> Result<Vec<ResultReferenceRequirement>, TemplateError> {
+    let mut templater = Templater::new();
+    templater.add_template("_analysis_template", template.clone());
+
+    let variables = templater.get_template_variables("_analysis_template")?;
+    Ok(get_result_reference_requirements(&variables, context))
+}
+
+// Same as get_template_result_reference_requirements for the variables of a compiled template
+pub fn get_result_reference_requirements(
+    variables: &[String],
+    context: &Value,
+) -> Vec<ResultReferenceRequirement> {
+    let mut requirements: Vec<ResultReferenceRequirement> = Vec::new();
+
+    for var in variables {
+        let mut current = context;
+        let mut pointer = String::new();
+        let mut reached_end = true;
+        let Some(parts) = split_path_segments(var) else {
+            continue;
+        };
+
+        // A selector after the plain steps may match anything below them, so those steps count
+        // as the end of the path
+        for part in parts {
+            if let Some(requirement) = as_reference_requirement(current, &pointer) {
+                push_unique(&mut requirements, requirement);
+                reached_end = false;
+                break;
+            }
+
+            let next = match part.parse::<usize>() {
+                Ok(index) if current.is_array() => current.get(index),
+                _ => current.get(part.as_str()),
+            };
+
+            match next {
+                Some(next) => {
+                    pointer.push('/');
+                    pointer.push_str(&part.replace('~', "~0").replace('/', "~1"));
+                    current = next;
+                }
+                None => {
+                    reached_end = false;
+                    break;
+                }
+            }
+        }
+
+        // The variable points at a whole subtree, so any reference inside it is needed too
+        if reached_end {
+            collect_nested_references(current, &pointer, &mut requirements);
+        }
+    }
+
+    requirements
+}
+
+// The plain key and index steps a path starts with, up to any wildcard, slice, filter or `..`.
+// None when the path isn't valid.
+fn split_path_segments(path: &str) -> Option<Vec<String>> {
+    let mut segments = Vec::new();
+    for segment in parse_path(path)? {
+        match segment.selector {
+            Selector::Key(key) if !segment.descendants => segments.push(key),
+            Selector::Index(index) if !segment.descendants && index >= 0 => {
+                segments.push(index.to_string())
+            }
+            _ => break,
+        }
+    }
+    Some(segments)
+}
+
+fn as_reference_requirement(value: &Value, pointer: &str) -> Option<ResultReferenceRequirement> {
+    let reference = value.as_object()?.get(RESULT_REFERENCE_KEY)?;
+    let key = reference.get("key")?.as_str()?;
+    Some(ResultReferenceRequirement {
+        pointer: pointer.to_string(),
+        key: key.to_string(),
+    })
+}
+
+fn collect_nested_references(
+    value: &Value,
+    pointer: &str,
+    requirements: &mut Vec<ResultReferenceRequirement>,
+) {
+    if let Some(requirement) = as_reference_requirement(value, pointer) {
+        push_unique(requirements, requirement);
+        return;
+    }
+
+    match value {
+        Value::Object(map) => {
+            for (k, v) in map {
+                let child = format!("{}/{}", pointer, k.replace('~', "~0").replace('/', "~1"));
+                collect_nested_references(v, &child, requirements);
+            }
+        }
+        Value::Array(arr) => {
+            for (i, v) in arr.iter().enumerate() {
+                collect_nested_references(v, &format!("{}/{}", pointer, i), requirements);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn push_unique(
+    requirements: &mut Vec<ResultReferenceRequirement>,
+    requirement: ResultReferenceRequirement,
+) {
+    if !requirements.contains(&requirement) {
+        requirements.push(requirement);
+    }
+}
+
+/// Explains where a path stopped resolving, naming the longest part that did and any
+/// keys that look like what was meant. None when the whole path resolves.
+pub fn describe_missing_path(context: &Value, path: &str) -> Option<String> {
+    let mut current = context.clone();
+    let mut resolved = String::new();
+    let Some(segments) = split_path_segments(path) else {
+        return Some(format!("'{}' is not a valid path", path));
+    };
+
+    for segment in segments {
+        // Values holding JSON text are traversed like the templater does
+        if let Value::String(s) = &current {
+            if let Ok(parsed) = serde_json::from_str::<Value>(s) {
+                if parsed.is_object() || parsed.is_array() {
+                    current = parsed;
+                }
+            }
+        }
+
+        let place = if resolved.is_empty() {
+            "the context".to_string()
+        } else {
+            format!("'{}'", resolved)
+        };
+
+        let next = match &current {
+            Value::Object(map) => match map.get(&segment) {
+                Some(next) => next.clone(),
+                None => {
+                    let suggestions = near_miss_keys(map.keys(), &segment);
+                    return Some(if suggestions.is_empty() {
+                        format!("{} has no key '{}'", place, segment)
+                    } else {
+                        format!(
+                            "{} has no key '{}', did you mean {}?",
+                            place,
+                            segment,
+                            suggestions
+                                .iter()
+                                .map(|key| format!("'{}'", key))
+                                .collect::<Vec<_>>()
+                                .join(" or ")
+                        )
+                    });
+                }
+            },
+            Value::Array(items) => match segment.parse::<usize>() {
+                Ok(index) if index < items.len() => items[index].clone(),
+                Ok(index) => {
+                    return Some(format!(
+                        "{} has {} items, no index {}",
+                        place,
+                        items.len(),
+                        index
+                    ))
+                }
+                Err(_) => {
+                    return Some(format!(
+                        "{} is an array, use an index instead of '{}'",
+                        place, segment
+                    ))
+                }
+            },
+            Value::Null => return Some(format!("{} is null", place)),
+            other => {
+                let kind = match other {
+                    Value::String(_) => "a string",
+                    Value::Number(_) => "a number",
+                    _ => "a boolean",
+                };
+                return Some(format!("{} is {}, it has no '{}'", place, kind, segment));
+            }
+        };
+
+        if segment.parse::<usize>().is_ok() && current.is_array() {
+            resolved.push_str(&format!("[{}]", segment));
+        } else {
+            if !resolved.is_empty() {
+                resolved.push('.');
+            }
+            resolved.push_str(&segment);
+        }
+        current = next;
+    }
+
+    None
+}
+
+// Keys a typo away from `wanted`, closest first
+fn near_miss_keys<'a>(keys: impl Iterator<Item = &'a String>, wanted: &str) -> Vec<&'a str> {
+    let wanted_lower = wanted.to_lowercase();
+    let max_distance = (wanted.chars().count() / 3).clamp(1, 3);
+
+    let mut candidates: Vec<(usize, &str)> = keys
+        .filter_map(|key| {
+            let distance = edit_distance(&key.to_lowercase(), &wanted_lower);
+            (distance <= max_distance).then_some((distance, key.as_str()))
+        })
+        .collect();
+    candidates.sort();
+    candidates.into_iter().take(3).map(|(_, key)| key).collect()
+}
+
+// Levenshtein distance where swapping two neighbouring characters counts as one edit
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+
+    distances[a.len()][b.len()]
 }
 
 fn analyze_file_pattern<'a>(parts: &[&'a str]) -> Option<(&'a str, &'a str)> {
@@ -160,4 +430,139 @@ mod tests {
         assert_eq!(requirements[3].file_extension, "docx");
         assert_eq!(requirements[3].format, "url");
     }
+
+    #[test]
+    fn test_result_reference_requirements_only_for_dereferenced_results() {
+        let template = json!({
+            "image": "{{actions.download.result.body.data}}",
+            "name": "{{actions.lookup.result.name}}",
+            "missing": "{{actions.nope.result}}"
+        });
+
+        let context = json!({
+            "actions": {
+                "download": {
+                    "result": { "_anything_ref": { "storage": "r2", "key": "acc/task_results/s/t1.json" } }
+                },
+                "lookup": {
+                    "result": { "name": "Carl" }
+                },
+                "unused": {
+                    "result": { "_anything_ref": { "storage": "r2", "key": "acc/task_results/s/t2.json" } }
+                }
+            }
+        });
+
+        let requirements = get_template_result_reference_requirements(&template, &context).unwrap();
+
+        assert_eq!(
+            requirements,
+            vec![ResultReferenceRequirement {
+                pointer: "/actions/download/result".to_string(),
+                key: "acc/task_results/s/t1.json".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_result_reference_requirements_for_whole_action() {
+        let template = json!({
+            "everything": "{{actions.download}}",
+            "again": "{{actions.download.result}}"
+        });
+
+        let context = json!({
+            "actions": {
+                "download": {
+                    "result": { "_anything_ref": { "storage": "r2", "key": "acc/task_results/s/t1.json" } }
+                }
+            }
+        });
+
+        let requirements = get_template_result_reference_requirements(&template, &context).unwrap();
+
+        assert_eq!(requirements.len(), 1);
+        assert_eq!(requirements[0].pointer, "/actions/download/result");
+    }
+
+    #[test]
+    fn test_result_reference_requirements_for_selector_paths() {
+        let template = json!({
+            "emails": "{{actions.download.result.contacts[*].email}}",
+            "skipped": "{{actions.other[?(@.id == 1)].name}}"
+        });
+
+        let context = json!({
+            "actions": {
+                "download": {
+                    "result": { "_anything_ref": { "storage": "r2", "key": "acc/task_results/s/t1.json" } }
+                },
+                "other": [
+                    { "id": 1, "name": { "_anything_ref": { "storage": "r2", "key": "acc/task_results/s/t2.json" } } }
+                ]
+            }
+        });
+
+        let requirements = get_template_result_reference_requirements(&template, &context).unwrap();
+
+        assert_eq!(
+            requirements,
+            vec![
+                ResultReferenceRequirement {
+                    pointer: "/actions/download/result".to_string(),
+                    key: "acc/task_results/s/t1.json".to_string(),
+                },
+                ResultReferenceRequirement {
+                    pointer: "/actions/other/0/name".to_string(),
+                    key: "acc/task_results/s/t2.json".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_describe_missing_path() {
+        let context = json!({
+            "actions": {
+                "http": {
+                    "result": {
+                        "items": [{ "id": 1 }],
+                        "body": "{\"status\": \"ok\"}"
+                    }
+                }
+            }
+        });
+
+        assert_eq!(
+            describe_missing_path(&context, "actoins.http"),
+            Some("the context has no key 'actoins', did you mean 'actions'?".to_string())
+        );
+        assert_eq!(
+            describe_missing_path(&context, "actions.http.result.items[3]"),
+            Some("'actions.http.result.items' has 1 items, no index 3".to_string())
+        );
+        assert_eq!(
+            describe_missing_path(&context, "actions.http.result.items[0].id.value"),
+            Some("'actions.http.result.items[0].id' is a number, it has no 'value'".to_string())
+        );
+        assert_eq!(
+            describe_missing_path(&context, "actions.http.result.body.stauts"),
+            Some(
+                "'actions.http.result.body' has no key 'stauts', did you mean 'status'?"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            describe_missing_path(&context, "actions.http.result.unrelated"),
+            Some("'actions.http.result' has no key 'unrelated'".to_string())
+        );
+        assert_eq!(
+            describe_missing_path(&context, "actions.http.result.items[0].id"),
+            None
+        );
+        assert_eq!(
+            describe_missing_path(&context, "actions.http.result.items[0"),
+            Some("'actions.http.result.items[0' is not a valid path".to_string())
+        );
+    }
 }
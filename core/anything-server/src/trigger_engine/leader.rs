@@ -0,0 +1,228 @@
+use chrono::{DateTime, Utc};
+use dotenv::dotenv;
+use serde::Serialize;
+use serde_json::Value;
+use std::env;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::time::{sleep, Duration};
+use uuid::Uuid;
+
+use crate::AppState;
+
+const LEASE_NAME: &str = "cron_trigger_engine";
+
+// A dead leader's lease runs out after this long, so another instance takes over within seconds
+const LEASE_TTL_SECONDS: i32 = 10;
+const LEASE_RENEW_INTERVAL: Duration = Duration::from_secs(3);
+
+// Old fires only guard against repeats around a leader handover, the newest one per trigger is
+// always kept since catch-up starts from it
+const TRIGGER_FIRE_RETENTION_DAYS: i32 = 30;
+
+// Tracks whether this instance currently owns the cron schedule
+pub struct TriggerEngineLeader {
+    pub instance_id: String,
+    is_leader: AtomicBool,
+}
+
+impl TriggerEngineLeader {
+    pub fn new() -> Self {
+        let hostname = sys_info::hostname().unwrap_or_else(|_| "unknown".to_string());
+        Self {
+            instance_id: format!("{}-{}", hostname, Uuid::new_v4()),
+            is_leader: AtomicBool::new(false),
+        }
+    }
+
+    pub fn is_leader(&self) -> bool {
+        self.is_leader.load(Ordering::SeqCst)
+    }
+
+    pub fn poll_interval(&self) -> Duration {
+        LEASE_RENEW_INTERVAL
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct LeaseInput {
+    p_lease_name: String,
+    p_holder_id: String,
+    p_ttl_seconds: i32,
+}
+
+#[derive(Debug, Serialize)]
+struct ClaimTriggerFireInput {
+    p_idempotency_key: String,
+    p_flow_id: String,
+    p_action_id: String,
+    p_scheduled_for: Option<DateTime<Utc>>,
+    p_holder_id: String,
+    p_retention_days: i32,
+}
+
+// Keeps renewing the lease row. Whoever holds an unexpired lease is the only instance that fires cron triggers.
+pub async fn leader_election_loop(state: Arc<AppState>, leader: Arc<TriggerEngineLeader>) {
+    println!(
+        "[TRIGGER_ENGINE] Starting leader election as {}",
+        leader.instance_id
+    );
+
+    loop {
+        if state.shutdown_signal.load(Ordering::SeqCst) {
+            if leader.is_leader() {
+                if let Err(e) = release_lease(&state, &leader.instance_id).await {
+                    println!("[TRIGGER_ENGINE] Failed to release leader lease: {:?}", e);
+                }
+            }
+            leader.is_leader.store(false, Ordering::SeqCst);
+            println!("[TRIGGER_ENGINE] Shutdown signal detected, stopping leader election");
+            break;
+        }
+
+        renew_leadership(&state, &leader).await;
+
+        sleep(LEASE_RENEW_INTERVAL).await;
+    }
+}
+
+// Takes or renews the lease and updates the leader flag. Returns whether this instance is the leader.
+pub async fn renew_leadership(state: &Arc<AppState>, leader: &TriggerEngineLeader) -> bool {
+    let acquired = match acquire_lease(state, &leader.instance_id).await {
+        Ok(acquired) => acquired,
+        Err(e) => {
+            // Can't prove we still hold the lease so stop firing until we can
+            println!("[TRIGGER_ENGINE] Failed to renew leader lease: {:?}", e);
+            false
+        }
+    };
+
+    let was_leader = leader.is_leader.swap(acquired, Ordering::SeqCst);
+    if acquired && !was_leader {
+        println!(
+            "[TRIGGER_ENGINE] {} is now the cron trigger leader",
+            leader.instance_id
+        );
+    } else if !acquired && was_leader {
+        println!(
+            "[TRIGGER_ENGINE] {} lost the cron trigger leader lease",
+            leader.instance_id
+        );
+    }
+
+    acquired
+}
+
+async fn acquire_lease(
+    state: &Arc<AppState>,
+    instance_id: &str,
+) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    let input = LeaseInput {
+        p_lease_name: LEASE_NAME.to_string(),
+        p_holder_id: instance_id.to_string(),
+        p_ttl_seconds: LEASE_TTL_SECONDS,
+    };
+
+    let response = state
+        .anything_client
+        .rpc(
+            "acquire_trigger_engine_lease",
+            serde_json::to_string(&input)?,
+        )
+        .auth(supabase_service_role_api_key)
+        .execute()
+        .await?;
+
+    let body = response.text().await?;
+    let acquired: Value = serde_json::from_str(&body)?;
+
+    acquired
+        .as_bool()
+        .ok_or_else(|| format!("Unexpected lease response: {}", body).into())
+}
+
+async fn release_lease(
+    state: &Arc<AppState>,
+    instance_id: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    let input = LeaseInput {
+        p_lease_name: LEASE_NAME.to_string(),
+        p_holder_id: instance_id.to_string(),
+        p_ttl_seconds: 0,
+    };
+
+    state
+        .anything_client
+        .rpc(
+            "release_trigger_engine_lease",
+            serde_json::to_string(&input)?,
+        )
+        .auth(supabase_service_role_api_key)
+        .execute()
+        .await?;
+
+    Ok(())
+}
+
+pub fn trigger_fire_idempotency_key(
+    flow_id: &str,
+    action_id: &str,
+    scheduled_for: Option<DateTime<Utc>>,
+) -> String {
+    match scheduled_for {
+        Some(scheduled_for) => format!(
+            "cron:{}:{}:{}",
+            flow_id,
+            action_id,
+            scheduled_for.timestamp()
+        ),
+        // Nothing to dedupe against without a schedule slot
+        None => format!("cron:{}:{}:{}", flow_id, action_id, Uuid::new_v4()),
+    }
+}
+
+/// Records the fire under its idempotency key. Returns false when another instance already fired this slot,
+/// or when the workflow was paused, possibly on another instance.
+pub async fn claim_trigger_fire(
+    state: &Arc<AppState>,
+    instance_id: &str,
+    idempotency_key: &str,
+    flow_id: &str,
+    action_id: &str,
+    scheduled_for: Option<DateTime<Utc>>,
+) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    let input = ClaimTriggerFireInput {
+        p_idempotency_key: idempotency_key.to_string(),
+        p_flow_id: flow_id.to_string(),
+        p_action_id: action_id.to_string(),
+        p_scheduled_for: scheduled_for,
+        p_holder_id: instance_id.to_string(),
+        p_retention_days: TRIGGER_FIRE_RETENTION_DAYS,
+    };
+
+    let response = state
+        .anything_client
+        .rpc("claim_trigger_fire", serde_json::to_string(&input)?)
+        .auth(supabase_service_role_api_key)
+        .execute()
+        .await?;
+
+    let body = response.text().await?;
+    let claimed: Value = serde_json::from_str(&body)?;
+
+    claimed
+        .as_bool()
+        .ok_or_else(|| format!("Unexpected claim response: {}", body).into())
+}
@@ -0,0 +1,1246 @@
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use postgrest::Postgrest;
+use tokio::time::{sleep, Duration};
+
+use dotenv::dotenv;
+use std::env;
+
+use node_semver::Version;
+use serde_json::json;
+
+use crate::{
+    bundler::bundle_context_from_parts,
+    processor::processor::ProcessorMessage,
+    types::{
+        action_types::{ActionType, PluginName},
+        task_types::{Stage, Task, TaskConfig},
+        workflow_types::DatabaseFlowVersion,
+    },
+    AppState,
+};
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use tokio::sync::RwLock;
+
+use cron::Schedule;
+use std::str::FromStr;
+use uuid::Uuid;
+
+pub mod leader;
+pub mod platform_event;
+pub mod poll;
+pub mod postgres_notify;
+pub mod rss;
+
+use leader::TriggerEngineLeader;
+use platform_event::PlatformEventFilter;
+use postgres_notify::{PostgresListeners, PostgresNotifyConfig};
+
+// A workflow can have several schedule triggers so each one is keyed by (flow_id, action_id)
+pub type TriggerKey = (String, String);
+
+// Trigger plugins the engine runs on a schedule
+#[derive(Debug, Clone, PartialEq)]
+pub enum TriggerKind {
+    Cron, // Starts a session every time the schedule fires
+    Poll, // Calls an API every time the schedule fires and starts a session per new item
+    Rss, // Reads a feed every time the schedule fires and starts a session per new entry
+    PlatformEvent, // Not scheduled, starts a session when a matching platform event happens
+    PostgresNotify, // Not scheduled, starts a session for every NOTIFY on a channel
+}
+
+impl TriggerKind {
+    pub fn from_plugin_name(plugin_name: &PluginName) -> Option<Self> {
+        match plugin_name.as_str() {
+            "@anything/cron" => Some(TriggerKind::Cron),
+            "@anything/poll" => Some(TriggerKind::Poll),
+            "@anything/rss" => Some(TriggerKind::Rss),
+            "@anything/platform_event" => Some(TriggerKind::PlatformEvent),
+            "@anything/postgres_notify" => Some(TriggerKind::PostgresNotify),
+            _ => None,
+        }
+    }
+}
+
+// Longest we sleep without re-checking, even when no trigger is due sooner
+const MAX_IDLE_INTERVAL: Duration = Duration::from_secs(60);
+
+// Longest a poll or RSS check may take, including rendering its config and starting sessions
+const MAX_TRIGGER_CHECK_DURATION: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Clone)]
+pub struct InMemoryTrigger {
+    pub kind: TriggerKind,
+    pub account_id: String,
+    pub action_id: String,
+    pub plugin_name: PluginName,
+    pub plugin_version: Version,
+    pub flow_id: String,
+    pub action_label: String,
+    pub flow_version_id: String,
+    pub config: TaskConfig,
+    pub last_fired: Option<DateTime<Utc>>,
+    pub next_fire: Option<DateTime<Utc>>,
+    pub cron_expression: String,
+    pub timezone: Tz,
+    pub missed_run_policy: MissedRunPolicy,
+    pub max_catch_up_runs: usize,
+    pub paused: bool,
+    pub event_filter: Option<PlatformEventFilter>,
+    pub notify_config: Option<PostgresNotifyConfig>,
+}
+
+// What to do with runs that were scheduled while the server was down
+#[derive(Debug, Clone, PartialEq)]
+pub enum MissedRunPolicy {
+    Skip,    // Forget missed runs and wait for the next scheduled time
+    RunOnce, // Fire a single catch-up run for everything that was missed
+    RunAll,  // Fire every missed run, up to max_catch_up_runs
+}
+
+impl MissedRunPolicy {
+    pub fn from_config(policy: Option<&str>) -> Self {
+        match policy.map(|p| p.trim().to_lowercase()).as_deref() {
+            Some("run_once") => MissedRunPolicy::RunOnce,
+            Some("run_all") => MissedRunPolicy::RunAll,
+            _ => MissedRunPolicy::Skip,
+        }
+    }
+}
+
+const DEFAULT_MAX_CATCH_UP_RUNS: usize = 10;
+// Upper bound for max_catch_up_runs, whatever the trigger asks for
+const MAX_CATCH_UP_RUNS: usize = 100;
+
+// Rendered inputs hold numbers as strings as often as numbers
+fn parse_max_catch_up_runs(value: &serde_json::Value) -> usize {
+    match value {
+        serde_json::Value::String(s) => s.trim().parse::<usize>().ok(),
+        other => other.as_f64().map(|max| max as usize),
+    }
+    .filter(|max| *max > 0)
+    .unwrap_or(DEFAULT_MAX_CATCH_UP_RUNS)
+}
+
+// A single firing of a trigger. Catch-up fires carry the time they were originally scheduled for.
+#[derive(Debug, Clone)]
+pub struct TriggerFire {
+    pub scheduled_for: Option<DateTime<Utc>>,
+    pub catch_up: bool,
+}
+
+// Cron schedules are evaluated in the trigger's own timezone so local times survive DST changes
+pub fn next_fire_in_timezone(cron_expression: &str, timezone: &Tz) -> Option<DateTime<Utc>> {
+    upcoming_fire_times(cron_expression, timezone, 1)
+        .into_iter()
+        .next()
+}
+
+// The next `count` times a schedule fires. Also backs the upcoming runs preview.
+pub fn upcoming_fire_times(
+    cron_expression: &str,
+    timezone: &Tz,
+    count: usize,
+) -> Vec<DateTime<Utc>> {
+    match Schedule::from_str(cron_expression) {
+        Ok(schedule) => schedule
+            .upcoming(*timezone)
+            .take(count)
+            .map(|next| next.with_timezone(&Utc))
+            .collect(),
+        Err(e) => {
+            println!("[TRIGGER_ENGINE] Error parsing cron expression: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+// Flow versions are loaded with their flow embedded as `flow`
+pub fn is_flow_paused(flow_version: &DatabaseFlowVersion) -> bool {
+    flow_version
+        .flow
+        .as_ref()
+        .and_then(|flow| flow.get("paused"))
+        .and_then(|paused| paused.as_bool())
+        .unwrap_or(false)
+}
+
+// Scheduled times between the last fire and now that never ran, trimmed down by the trigger's policy
+pub fn missed_fire_times(
+    trigger: &InMemoryTrigger,
+    last_fired: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Vec<DateTime<Utc>> {
+    let limit = match trigger.missed_run_policy {
+        MissedRunPolicy::Skip => return Vec::new(),
+        // Only the most recent missed run matters
+        MissedRunPolicy::RunOnce => 1,
+        MissedRunPolicy::RunAll => trigger.max_catch_up_runs.min(MAX_CATCH_UP_RUNS),
+    };
+
+    let schedule = match Schedule::from_str(&trigger.cron_expression) {
+        Ok(schedule) => schedule,
+        Err(e) => {
+            println!("[TRIGGER_ENGINE] Error parsing cron expression: {}", e);
+            return Vec::new();
+        }
+    };
+
+    // Walk back from now so a long outage only costs as many steps as runs we keep
+    let walk_back_from = (now + chrono::Duration::seconds(1)).with_timezone(&trigger.timezone);
+    let mut missed: Vec<DateTime<Utc>> = schedule
+        .after(&walk_back_from)
+        .rev()
+        .map(|time| time.with_timezone(&Utc))
+        .skip_while(|time| *time > now)
+        .take_while(|time| *time > last_fired)
+        .take(limit)
+        .collect();
+    missed.reverse();
+
+    missed
+}
+
+pub fn parse_trigger_timezone(timezone: Option<&str>) -> Tz {
+    match timezone.map(str::trim).filter(|tz| !tz.is_empty()) {
+        Some(tz) => tz.parse::<Tz>().unwrap_or_else(|_| {
+            println!(
+                "[TRIGGER_ENGINE] Invalid timezone {}, falling back to UTC",
+                tz
+            );
+            Tz::UTC
+        }),
+        None => Tz::UTC,
+    }
+}
+
+pub async fn cron_job_loop(state: Arc<AppState>) {
+    //(workflow_id, action_id) => trigger
+    // Shared through AppState so the upcoming runs preview sees exactly what will fire
+    let trigger_state = state.trigger_engine_triggers.clone();
+
+    // Receive info from other systems like CRUD over workflows that have triggers
+    let mut trigger_engine_signal_rx = state.trigger_engine_signal.subscribe();
+    let mut platform_events_rx = state.platform_events.subscribe();
+    let client = state.anything_client.clone();
+
+    // Only the instance holding the lease fires triggers, the rest keep their schedules warm for failover
+    let leader = Arc::new(TriggerEngineLeader::new());
+    leader::renew_leadership(&state, &leader).await;
+    tokio::spawn(leader::leader_election_loop(state.clone(), leader.clone()));
+
+    hydrate_triggers(state.clone(), &client, &trigger_state, &leader).await;
+
+    // Clone state once here for use in the loop
+    let state = Arc::new(state);
+
+    let mut postgres_listeners = PostgresListeners::new();
+
+    // Poll and RSS triggers whose last check hasn't finished yet
+    let checks_in_flight: Arc<Mutex<HashSet<TriggerKey>>> = Arc::new(Mutex::new(HashSet::new()));
+
+    loop {
+        // Leadership or the triggers may have changed since the last pass
+        postgres_listeners.sync(&state, &*trigger_state.read().await, leader.is_leader());
+
+        // Sleep exactly until the earliest trigger is due so second-level schedules fire on time
+        // Followers just wake up often enough to notice when they take over
+        let sleep_for = if leader.is_leader() {
+            time_until_next_fire(&trigger_state).await
+        } else {
+            leader.poll_interval()
+        };
+
+        tokio::select! {
+            _ = sleep(sleep_for) => {
+                if !leader.is_leader() {
+                    // Leave next_fire alone so due triggers still fire if we take over
+                    continue;
+                }
+
+                println!("[TRIGGER_ENGINE] Starting trigger check loop");
+
+                //find triggers to run
+                let triggers_to_run = {
+                    let triggers = trigger_state.read().await;
+                    triggers
+                        .iter()
+                        .filter(|(_, trigger)| should_trigger_run(trigger))
+                        .map(|(id, trigger)| (id.clone(), trigger.clone()))
+                        .collect::<Vec<_>>()
+                };
+
+                //Create tasks for triggers that should run
+                //Then update trigger to get next time to run in memory
+                for (id, mut trigger) in triggers_to_run {
+                    // The workflow may have been resumed on another instance, the flow has the final say
+                    if trigger.paused {
+                        trigger.paused = is_flow_still_paused(&state, &trigger).await;
+                    }
+                    if trigger.paused {
+                        // Paused runs are skipped, not queued up for when the workflow resumes
+                        println!("[TRIGGER_ENGINE] Skipping paused trigger: {:?}", id);
+                        if let Err(e) = skip_trigger_run(&state, &leader.instance_id, &id, &trigger, &trigger_state).await {
+                            println!("[TRIGGER_ENGINE] Error skipping paused trigger: {:?}", e);
+                        }
+                        continue;
+                    }
+
+                    println!(
+                        "[TRIGGER_ENGINE] Trigger should run for (workflow_id, action_id): {:?}",
+                        id
+                    );
+                    let fire = TriggerFire {
+                        scheduled_for: trigger.next_fire,
+                        catch_up: false,
+                    };
+                    if let Err(e) = fire_trigger(&state, &leader.instance_id, &id, &trigger, &fire, &checks_in_flight).await {
+                        println!("[TRIGGER_ENGINE] Error creating trigger task: {:?}", e);
+                    }
+                    // Always move on to the next scheduled time, a failed fire would otherwise be retried in a hot loop
+                    if let Err(e) = update_trigger_last_run(&id, &trigger, &trigger_state).await {
+                        println!("[TRIGGER_ENGINE] Error updating trigger last run: {:?}", e);
+                    }
+                    println!("[TRIGGER_ENGINE] Trigger Loop Successfully LOOPED");
+                }
+
+                println!("[TRIGGER_ENGINE] Finished trigger check loop");
+            }
+            Ok(event) = platform_events_rx.recv() => {
+                let triggers = trigger_state.read().await;
+                platform_event::handle_platform_event(&state, &triggers, event);
+            }
+            _ = trigger_engine_signal_rx.changed() => {
+                let workflow_id = trigger_engine_signal_rx.borrow().clone();
+                println!("[TRIGGER_ENGINE] Received workflow_id: {}", workflow_id);
+                if let Err(e) = update_triggers_for_workflow(&state, &client, &trigger_state, &workflow_id).await {
+                    println!("[TRIGGER_ENGINE] Error updating triggers for workflow: {:?}", e);
+                }
+            }
+        }
+    }
+}
+
+async fn time_until_next_fire(
+    triggers: &Arc<RwLock<HashMap<TriggerKey, InMemoryTrigger>>>,
+) -> Duration {
+    let triggers = triggers.read().await;
+    let now = Utc::now();
+
+    triggers
+        .values()
+        .filter_map(|trigger| trigger.next_fire)
+        .min()
+        .map(|next_fire| (next_fire - now).to_std().unwrap_or(Duration::ZERO))
+        .unwrap_or(MAX_IDLE_INTERVAL)
+        .min(MAX_IDLE_INTERVAL)
+}
+
+//From Claude and very untested so far
+//Ment to lightly update triggers so we don't need to refresh the entire memory each time we update something
+async fn update_triggers_for_workflow(
+    state: &Arc<AppState>,
+    client: &Postgrest,
+    triggers: &Arc<RwLock<HashMap<TriggerKey, InMemoryTrigger>>>,
+    workflow_id: &String,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    println!(
+        "[TRIGGER_ENGINE] Updating triggers for workflow: {}",
+        workflow_id
+    );
+
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    //Get current published workflow version
+    let response = client
+        .from("flow_versions")
+        .auth(supabase_service_role_api_key.clone())
+        .select("*, flow:flows!inner(active, paused)") // TODO: only fetch active flows
+        .eq("flow_id", workflow_id)
+        .eq("published", "true")
+        .eq("flow.active", "true")
+        .execute()
+        .await?;
+
+    let body = response.text().await?;
+    let flow_versions: Vec<DatabaseFlowVersion> = serde_json::from_str(&body)?;
+
+    let mut new_triggers = HashMap::new();
+
+    //Add new triggers to new_triggers
+    for flow_version in flow_versions {
+        let triggers_from_flow =
+            create_in_memory_triggers_from_flow_definition(state.clone(), &flow_version, client)
+                .await;
+        new_triggers.extend(triggers_from_flow);
+    }
+
+    //Delete Existing trigger for workflow_id from hashmap
+    //Can't just overwrite because the workflow update may have removed the trigger completely.
+    let mut triggers = triggers.write().await;
+    triggers.retain(|(flow_id, _), old_trigger| {
+        if flow_id == workflow_id {
+            println!("[TRIGGER_ENGINE] Removing old trigger: {:?}", old_trigger);
+            false
+        } else {
+            true
+        }
+    });
+    //Write new riggers in memory for workflow_id
+    // let mut triggers = triggers.write().await;
+    for (id, trigger) in new_triggers.into_iter() {
+        //Write New Trigger
+        let old_value = triggers.insert(id, trigger);
+        if let Some(old_trigger) = old_value {
+            println!("[TRIGGER_ENGINE] Replaced old trigger: {:?}", old_trigger);
+        } else {
+            println!("[TRIGGER_ENGINE] Added new trigger");
+        }
+    }
+
+    println!(
+        "[TRIGGER_ENGINE] Successfully updated triggers for workflow: {}",
+        workflow_id
+    );
+    Ok(())
+}
+
+pub async fn hydrate_triggers(
+    state: Arc<AppState>,
+    client: &Postgrest,
+    triggers: &Arc<RwLock<HashMap<TriggerKey, InMemoryTrigger>>>,
+    leader: &TriggerEngineLeader,
+) {
+    println!("[TRIGGER_ENGINE] Hydrating triggers from the database");
+
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    let response = match client //TODO: pagination for large number of triggers
+        .from("flow_versions")
+        .auth(supabase_service_role_api_key.clone())
+        .select("*, flow:flows!inner(active, paused)") // TODO: only fetch active flows
+        .eq("published", "true")
+        .eq("flow.active", "true")
+        .execute()
+        .await
+    {
+        Ok(response) => response,
+        Err(e) => {
+            println!("[TRIGGER_ENGINE] Error fetching flow versions: {:?}", e);
+            return;
+        }
+    };
+
+    let body = match response.text().await {
+        Ok(body) => {
+            // println!(
+            //     "[TRIGGER_ENGINE] Response body for active and published triggers: {}",
+            //     body
+            // );
+            body
+        }
+        Err(e) => {
+            println!("[TRIGGER_ENGINE] Error reading response body: {:?}", e);
+            return;
+        }
+    };
+
+    let flow_versions: Vec<DatabaseFlowVersion> = match serde_json::from_str(&body) {
+        Ok(flow_versions) => flow_versions,
+        Err(e) => {
+            println!("[TRIGGER_ENGINE] Error parsing JSON: {:?}", e);
+            return;
+        }
+    };
+
+    println!(
+        "[TRIGGER_ENGINE] Found flow_versions vector: {}",
+        flow_versions.len()
+    );
+
+    let mut new_triggers = HashMap::new();
+
+    //Add new triggers to new_triggers
+    for flow_version in flow_versions {
+        let triggers_from_flow =
+            create_in_memory_triggers_from_flow_definition(state.clone(), &flow_version, client)
+                .await;
+
+        for (trigger_key, new_trigger) in triggers_from_flow {
+            // Check if the trigger already exists in memory
+            let existing_triggers = triggers.read().await;
+            if let Some(existing_trigger) = existing_triggers.get(&trigger_key) {
+                println!("[TRIGGER_ENGINE] Trigger already exists, preserving last_fired and next_fire values");
+                new_triggers.insert(
+                    trigger_key,
+                    InMemoryTrigger {
+                        last_fired: existing_trigger.last_fired,
+                        next_fire: existing_trigger.next_fire,
+                        ..new_trigger
+                    },
+                );
+            } else {
+                println!(
+                    "[TRIGGER_ENGINE] Adding new trigger to in-memory store: {:?}",
+                    new_trigger
+                );
+                new_triggers.insert(trigger_key, new_trigger);
+            }
+        }
+    }
+
+    for (id, trigger) in new_triggers.iter() {
+        println!(
+            "[TRIGGER_ENGINE] New Trigger - ID: {:?}, Flow ID: {}, Next Fire: {:?}, Last Fired: {:?}",
+            id, trigger.flow_id, trigger.next_fire, trigger.last_fired
+        );
+    }
+
+    // Triggers we have no memory of may have missed runs while the server was down
+    // Only the leader catches up, claimed fire keys stop a new leader from repeating them
+    let now = Utc::now();
+    for (id, trigger) in new_triggers.iter_mut() {
+        if !leader.is_leader()
+            || trigger.paused
+            || trigger.last_fired.is_some()
+            || trigger.missed_run_policy == MissedRunPolicy::Skip
+        {
+            continue;
+        }
+
+        let last_fired = match fetch_persisted_last_fired(&state, trigger).await {
+            Ok(Some(last_fired)) => last_fired,
+            Ok(None) => continue,
+            Err(e) => {
+                println!(
+                    "[TRIGGER_ENGINE] Error fetching last fired time for trigger {:?}: {:?}",
+                    id, e
+                );
+                continue;
+            }
+        };
+
+        let missed = missed_fire_times(trigger, last_fired, now);
+        println!(
+            "[TRIGGER_ENGINE] Trigger {:?} missed {} runs since {}, catching up",
+            id,
+            missed.len(),
+            last_fired
+        );
+
+        for scheduled_for in missed {
+            let fire = TriggerFire {
+                scheduled_for: Some(scheduled_for),
+                catch_up: true,
+            };
+            if let Err(e) = create_trigger_task(&state, &leader.instance_id, trigger, &fire).await {
+                println!(
+                    "[TRIGGER_ENGINE] Error creating catch-up trigger task: {:?}",
+                    e
+                );
+            } else {
+                trigger.last_fired = Some(now);
+            }
+        }
+    }
+
+    let mut triggers = triggers.write().await;
+    for (id, trigger) in new_triggers.into_iter() {
+        triggers.insert(id, trigger);
+    }
+
+    println!("[TRIGGER_ENGINE] Current triggers in memory:");
+    for (id, trigger) in triggers.iter() {
+        println!(
+            "Trigger ID: {:?}, Flow ID: {}, Next Fire: {:?}, Last Fired: {:?}",
+            id, trigger.flow_id, trigger.next_fire, trigger.last_fired
+        );
+    }
+
+    println!("[TRIGGER_ENGINE] Successfully hydrated triggers from the database");
+}
+
+pub fn should_trigger_run(trigger: &InMemoryTrigger) -> bool {
+    let now = Utc::now();
+
+    println!("[TRIGGER_ENGINE] Current time: {}", now);
+    if let Some(next_fire) = trigger.next_fire {
+        println!("[TRIGGER_ENGINE] Next fire time: {}", next_fire);
+
+        if now >= next_fire {
+            println!("[TRIGGER_ENGINE] Trigger should run (now >= next_fire)");
+            return true;
+        }
+    } else {
+        println!("[TRIGGER_ENGINE] No next_fire time set, trigger should not run.");
+    }
+
+    println!("[TRIGGER_ENGINE] Trigger should not run");
+    false
+}
+
+async fn update_trigger_last_run(
+    id: &TriggerKey,
+    trigger: &InMemoryTrigger,
+    triggers: &Arc<RwLock<HashMap<TriggerKey, InMemoryTrigger>>>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    println!("[TRIGGER_ENGINE] Updating trigger last run and next_run time");
+
+    let new_next_fire = next_fire_in_timezone(&trigger.cron_expression, &trigger.timezone);
+
+    println!("[TRIGGER_ENGINE] New next fire time: {:?}", new_next_fire);
+
+    let updated_trigger = InMemoryTrigger {
+        last_fired: Some(Utc::now()),
+        next_fire: new_next_fire,
+        ..trigger.clone()
+    };
+
+    println!("[TRIGGER_ENGINE] Updated trigger: {:?}", updated_trigger);
+
+    // Use a write lock to update the trigger
+    let mut triggers = triggers.write().await;
+    println!("[TRIGGER_ENGINE] Acquired write lock on triggers map");
+    triggers.insert(id.clone(), updated_trigger);
+    println!("[TRIGGER_ENGINE] Successfully updated trigger last run and next_run time");
+
+    Ok(())
+}
+
+// Moves a trigger on to its next scheduled time without firing it.
+// The slot is still claimed so catch-up after a restart doesn't replay it.
+async fn skip_trigger_run(
+    state: &Arc<AppState>,
+    instance_id: &str,
+    id: &TriggerKey,
+    trigger: &InMemoryTrigger,
+    triggers: &Arc<RwLock<HashMap<TriggerKey, InMemoryTrigger>>>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let updated_trigger = InMemoryTrigger {
+        next_fire: next_fire_in_timezone(&trigger.cron_expression, &trigger.timezone),
+        ..trigger.clone()
+    };
+
+    let mut triggers = triggers.write().await;
+    triggers.insert(id.clone(), updated_trigger);
+    drop(triggers);
+
+    let fire = TriggerFire {
+        scheduled_for: trigger.next_fire,
+        catch_up: false,
+    };
+    claim_fire(state, instance_id, trigger, &fire).await?;
+
+    Ok(())
+}
+
+// Pausing and resuming only signal the instance they happened on, so the flow has the final say.
+// A failed lookup counts as paused.
+async fn is_flow_still_paused(state: &Arc<AppState>, trigger: &InMemoryTrigger) -> bool {
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    let paused = async {
+        let response = state
+            .anything_client
+            .from("flows")
+            .auth(supabase_service_role_api_key)
+            .eq("flow_id", &trigger.flow_id)
+            .select("paused")
+            .single()
+            .execute()
+            .await?;
+        let body = response.text().await?;
+        let flow: serde_json::Value = serde_json::from_str(&body)?;
+        Ok::<_, Box<dyn std::error::Error + Send + Sync>>(flow["paused"].as_bool().unwrap_or(true))
+    }
+    .await;
+
+    match paused {
+        Ok(paused) => paused,
+        Err(e) => {
+            println!(
+                "[TRIGGER_ENGINE] Error checking if workflow {} is paused: {:?}",
+                trigger.flow_id, e
+            );
+            true
+        }
+    }
+}
+
+// Last time this trigger fired or was skipped. Claimed fires cover skipped runs, trigger tasks
+// cover fires from before fires were claimed.
+async fn fetch_persisted_last_fired(
+    state: &Arc<AppState>,
+    trigger: &InMemoryTrigger,
+) -> Result<Option<DateTime<Utc>>, Box<dyn std::error::Error + Send + Sync>> {
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    let fires_query = state
+        .anything_client
+        .from("trigger_fires")
+        .auth(supabase_service_role_api_key.clone())
+        .select("fired_at")
+        .eq("flow_id", &trigger.flow_id)
+        .eq("action_id", &trigger.action_id);
+
+    let tasks_query = state
+        .anything_client
+        .from("tasks")
+        .auth(supabase_service_role_api_key)
+        .select("created_at")
+        .eq("flow_id", &trigger.flow_id)
+        .eq("action_id", &trigger.action_id)
+        .eq("type", "trigger")
+        .eq("stage", "production");
+
+    let (last_claimed, last_task) = tokio::join!(
+        fetch_newest_timestamp(fires_query, "fired_at"),
+        fetch_newest_timestamp(tasks_query, "created_at"),
+    );
+
+    Ok(last_claimed?.max(last_task?))
+}
+
+async fn fetch_newest_timestamp(
+    query: postgrest::Builder,
+    column: &str,
+) -> Result<Option<DateTime<Utc>>, Box<dyn std::error::Error + Send + Sync>> {
+    let response = query
+        .order(format!("{}.desc", column))
+        .limit(1)
+        .execute()
+        .await?;
+
+    let body = response.text().await?;
+    let rows: Vec<serde_json::Value> = serde_json::from_str(&body)?;
+
+    Ok(rows
+        .first()
+        .and_then(|row| row.get(column))
+        .and_then(|timestamp| timestamp.as_str())
+        .and_then(|timestamp| DateTime::parse_from_rfc3339(timestamp).ok())
+        .map(|timestamp| timestamp.with_timezone(&Utc)))
+}
+
+async fn create_trigger_task(
+    state: &Arc<AppState>,
+    instance_id: &str,
+    trigger: &InMemoryTrigger,
+    fire: &TriggerFire,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    println!("[CRON TRIGGER] Handling create task from cron trigger");
+
+    let idempotency_key = match claim_fire(state, instance_id, trigger, fire).await? {
+        Some(idempotency_key) => idempotency_key,
+        None => return Ok(()),
+    };
+
+    let result = json!({
+        "message": format!("Successfully triggered task"),
+        "created_at": Utc::now(),
+        "timezone": trigger.timezone.name(),
+        "local_time": Utc::now().with_timezone(&trigger.timezone).to_rfc3339(),
+        "scheduled_for": fire.scheduled_for,
+        "catch_up": fire.catch_up,
+        "idempotency_key": idempotency_key,
+    });
+
+    start_trigger_session(state, trigger, result).await
+}
+
+// Runs a due trigger the way its plugin expects
+async fn fire_trigger(
+    state: &Arc<AppState>,
+    instance_id: &str,
+    id: &TriggerKey,
+    trigger: &InMemoryTrigger,
+    fire: &TriggerFire,
+    checks_in_flight: &Arc<Mutex<HashSet<TriggerKey>>>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    match trigger.kind {
+        TriggerKind::Cron => create_trigger_task(state, instance_id, trigger, fire).await,
+        TriggerKind::Poll | TriggerKind::Rss => {
+            spawn_trigger_check(state, instance_id, id, trigger, fire, checks_in_flight);
+            Ok(())
+        }
+        // Never scheduled, see platform_event::handle_platform_event
+        TriggerKind::PlatformEvent => Ok(()),
+        // Never scheduled, see postgres_notify::PostgresListeners
+        TriggerKind::PostgresNotify => Ok(()),
+    }
+}
+
+// Poll and RSS checks wait on other servers so they run off the scheduler loop with a time limit
+fn spawn_trigger_check(
+    state: &Arc<AppState>,
+    instance_id: &str,
+    id: &TriggerKey,
+    trigger: &InMemoryTrigger,
+    fire: &TriggerFire,
+    checks_in_flight: &Arc<Mutex<HashSet<TriggerKey>>>,
+) {
+    // A check still running from the last slot would see the same items
+    if !checks_in_flight.lock().unwrap().insert(id.clone()) {
+        println!(
+            "[TRIGGER_ENGINE] Last check for {:?} is still running, skipping this one",
+            id
+        );
+        return;
+    }
+
+    let state = state.clone();
+    let instance_id = instance_id.to_string();
+    let id = id.clone();
+    let trigger = trigger.clone();
+    let fire = fire.clone();
+    let checks_in_flight = checks_in_flight.clone();
+
+    tokio::spawn(async move {
+        let check = async {
+            // Cron fires are stopped by the fire claim, checks have to ask the flow themselves
+            if is_flow_still_paused(&state, &trigger).await {
+                println!("[TRIGGER_ENGINE] Skipping check for paused trigger: {:?}", id);
+                return Ok(());
+            }
+            match trigger.kind {
+                TriggerKind::Poll => {
+                    poll::poll_for_new_items(&state, &instance_id, &trigger, &fire).await
+                }
+                _ => rss::check_feed_for_new_entries(&state, &instance_id, &trigger, &fire).await,
+            }
+        };
+
+        match tokio::time::timeout(MAX_TRIGGER_CHECK_DURATION, check).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => println!("[TRIGGER_ENGINE] Error checking trigger {:?}: {:?}", id, e),
+            Err(_) => println!(
+                "[TRIGGER_ENGINE] Check for trigger {:?} timed out after {:?}",
+                id, MAX_TRIGGER_CHECK_DURATION
+            ),
+        }
+
+        checks_in_flight.lock().unwrap().remove(&id);
+    });
+}
+
+// Claims the schedule slot first so a leader handover can never fire it twice.
+// Returns the idempotency key, or None when the slot was already fired or the workflow is paused.
+async fn claim_fire(
+    state: &Arc<AppState>,
+    instance_id: &str,
+    trigger: &InMemoryTrigger,
+    fire: &TriggerFire,
+) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+    let idempotency_key = leader::trigger_fire_idempotency_key(
+        &trigger.flow_id,
+        &trigger.action_id,
+        fire.scheduled_for,
+    );
+    let claimed = leader::claim_trigger_fire(
+        state,
+        instance_id,
+        &idempotency_key,
+        &trigger.flow_id,
+        &trigger.action_id,
+        fire.scheduled_for,
+    )
+    .await?;
+
+    if !claimed {
+        println!(
+            "[CRON TRIGGER] Skipping {}, it was already fired or its workflow is paused",
+            idempotency_key
+        );
+        return Ok(None);
+    }
+
+    Ok(Some(idempotency_key))
+}
+
+// Starts a new flow session with `result` as the trigger's output
+async fn start_trigger_session(
+    state: &Arc<AppState>,
+    trigger: &InMemoryTrigger,
+    result: serde_json::Value,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    start_trigger_session_with_id(state, trigger, Uuid::new_v4(), result).await
+}
+
+async fn start_trigger_session_with_id(
+    state: &Arc<AppState>,
+    trigger: &InMemoryTrigger,
+    flow_session_id: Uuid,
+    result: serde_json::Value,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+      //Super User Access
+      dotenv().ok();
+      let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+          .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+  
+      // Get flow version from database
+      println!("[WEBHOOK API] Fetching flow version from database");
+      let response = match state
+          .anything_client
+          .from("flow_versions")
+          .eq("flow_id", trigger.flow_id.clone())
+          .eq("flow_version_id", trigger.flow_version_id.clone())
+          .auth(supabase_service_role_api_key.clone())
+          .select("*")
+          .single()
+          .execute()
+          .await
+      {
+          Ok(response) => response,
+          Err(err) => {
+              println!("[CRON TRIGGER] Failed to fetch flow version: {:?}", err);
+              return Err(Box::new(std::io::Error::new(
+                  std::io::ErrorKind::Other,
+                  format!("Failed to fetch flow version: {}", err),
+              )));
+          }
+      };
+  
+      let response_body = match response.text().await {
+          Ok(body) => {
+              println!("[CRON TRIGGER] Response body: {}", body);
+              body
+          }
+          Err(err) => {
+              println!("[CRON TRIGGER] Failed to read response body: {:?}", err);
+              return Err(Box::new(std::io::Error::new(
+                  std::io::ErrorKind::Other,
+                  format!("Failed to read response body: {}", err),
+              )));
+          }
+      };
+  
+      let workflow_version: DatabaseFlowVersion = match serde_json::from_str(&response_body) {
+          Ok(version) => version,
+          Err(_) => {
+              println!("[CRON TRIGGER] No published workflow found");
+              return Err(Box::new(std::io::Error::new(
+                  std::io::ErrorKind::Other,
+                  format!("Unpublished Workflow. To use this endpoint you must publish your workflow."),
+              )));
+          }
+      };
+
+    let task = match Task::builder()
+        .account_id(Uuid::parse_str(&trigger.account_id).unwrap())
+        .flow_id(Uuid::parse_str(&trigger.flow_id).unwrap())
+        .flow_version_id(Uuid::parse_str(&trigger.flow_version_id).unwrap())
+        .action_label(trigger.action_label.clone())
+        .trigger_id(trigger.action_id.clone())
+        .flow_session_id(flow_session_id)
+        .action_id(trigger.action_id.clone())
+        .r#type(ActionType::Trigger)
+        .plugin_name(trigger.plugin_name.clone())
+        .plugin_version(trigger.plugin_version.clone())
+        .stage(Stage::Production)
+        .config(trigger.config.clone())
+        .result(result)
+        .build()
+    {
+        Ok(task) => task,
+        Err(e) => panic!("Failed to build task: {}", e),
+    };
+
+    println!("[CRON TRIGGER] Creating processor message -> TODO: Fix ! Not implemented");
+    // Send message to processor
+    //TODO: add back once we determine this pattern works.
+    let processor_message = ProcessorMessage {
+        workflow_id: Uuid::parse_str(&trigger.flow_id).unwrap(),
+        workflow_version: workflow_version,
+        flow_session_id: task.flow_session_id.clone(),
+        trigger_session_id: task.trigger_session_id.clone(),
+        trigger_task: Some(task),
+    };
+
+    if let Err(e) = state.processor_sender.send(processor_message).await {
+        println!(
+            "[TRIGGER_ENGINE] Failed to send message to processor: {}",
+            e
+        );
+        return Err(Box::new(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            format!("Failed to send message to processor: {}", e),
+        )));
+    }
+
+    println!("Successfully created trigger task");
+
+    Ok(())
+}
+
+pub async fn create_in_memory_triggers_from_flow_definition(
+    state: Arc<AppState>,
+    flow_version: &DatabaseFlowVersion,
+    client: &Postgrest,
+) -> HashMap<TriggerKey, InMemoryTrigger> {
+    let mut triggers = HashMap::new();
+
+    println!(
+        "[TRIGGER_ENGINE] Processing flow_version: {:?}",
+        flow_version
+    );
+    let paused = is_flow_paused(flow_version);
+    let (flow_id, flow_version_id, flow_definition, account_id) = (
+        flow_version.flow_id.to_string(),
+        flow_version.flow_version_id.to_string(),
+        flow_version.flow_definition.clone(),
+        flow_version.account_id.to_string(),
+    );
+
+    let actions = flow_definition.actions;
+    for action in actions {
+        let (plugin_name, r#type, action_id) = (
+            action.plugin_name.clone(),
+            action.r#type.clone(),
+            action.action_id.clone(),
+        );
+        let kind =
+            TriggerKind::from_plugin_name(&plugin_name).filter(|_| r#type == ActionType::Trigger);
+        if let Some(kind) = kind {
+            println!(
+                "[TRIGGER ENGINE] Processing trigger action with ID: {}",
+                action_id
+            );
+
+            let inputs = action.inputs.clone();
+            let inputs_schema = action.inputs_schema.clone();
+            let plugin_config = action.plugin_config.clone();
+            let plugin_config_schema = action.plugin_config_schema.clone();
+
+            println!("[TRIGGER ENGINE] Trigger input: {:?}", inputs);
+            println!("[TRIGGER ENGINE] Trigger input schema: {:?}", inputs_schema);
+
+            let task_config: TaskConfig = TaskConfig {
+                inputs: Some(inputs.clone().unwrap()),
+                inputs_schema: Some(inputs_schema.clone().unwrap()),
+                plugin_config: Some(plugin_config.clone()),
+                plugin_config_schema: Some(plugin_config_schema.clone()),
+            };
+
+            //Run the templater over the variables and results from last session
+            //Return the templated variables and inputs
+            println!("[TRIGGER ENGINE] Attempting to bundle variables for trigger");
+            let rendered_input = match bundle_context_from_parts(
+                state.clone(),
+                client,
+                &account_id,
+                &Uuid::new_v4().to_string(),
+                Some(&inputs.clone().unwrap()),
+                Some(&inputs_schema.clone().unwrap()),
+                Some(&plugin_config.clone()),
+                Some(&plugin_config_schema.clone()),
+                false,
+            )
+            .await
+            {
+                Ok(vars) => {
+                    println!(
+                        "[TRIGGER ENGINE] Successfully bundled variables: {:?}",
+                        vars
+                    );
+                    vars
+                }
+                Err(e) => {
+                    println!("[TRIGGER ENGINE] Failed to bundle variables: {:?}", e);
+                    continue;
+                }
+            };
+
+            let cron_expression = rendered_input["cron_expression"]
+                .as_str()
+                .unwrap_or("* * * * *");
+
+            let timezone = parse_trigger_timezone(rendered_input["timezone"].as_str());
+            let missed_run_policy =
+                MissedRunPolicy::from_config(rendered_input["missed_run_policy"].as_str());
+            let max_catch_up_runs = parse_max_catch_up_runs(&rendered_input["max_catch_up_runs"]);
+
+            println!(
+                "[TRIGGER ENGINE] Using cron expression: {} in timezone: {}",
+                cron_expression, timezone
+            );
+
+            // Event triggers have no schedule
+            let (next_fire, event_filter, notify_config) = match kind {
+                TriggerKind::PlatformEvent => (
+                    None,
+                    Some(PlatformEventFilter::from_config(&rendered_input)),
+                    None,
+                ),
+                TriggerKind::PostgresNotify => {
+                    let notify_config = PostgresNotifyConfig::from_config(&rendered_input);
+                    if notify_config.is_none() {
+                        println!(
+                            "[TRIGGER ENGINE] Postgres notify trigger is missing a secret or channel"
+                        );
+                    }
+                    (None, None, notify_config)
+                }
+                _ => (next_fire_in_timezone(cron_expression, &timezone), None, None),
+            };
+            println!("[TRIGGER ENGINE] Calculated next fire time: {:?}", next_fire);
+
+            let trigger = InMemoryTrigger {
+                kind,
+                action_id: action_id.to_string(),
+                account_id: account_id.to_string(),
+                plugin_name: plugin_name.clone(),
+                plugin_version: action.plugin_version.clone(),
+                flow_id: flow_id.to_string(),
+                action_label: action.label.clone(),
+                flow_version_id: flow_version_id.to_string(),
+                config: task_config, // figure out how this is used and where to fix it
+                last_fired: None,
+                next_fire,
+                cron_expression: cron_expression.to_string(),
+                timezone,
+                missed_run_policy,
+                max_catch_up_runs,
+                paused,
+                event_filter,
+                notify_config,
+            };
+
+            triggers.insert((flow_id.to_string(), action_id.to_string()), trigger);
+        } else {
+            println!("[TRIGGER_ENGINE] Found an action that's not a scheduled trigger.");
+        }
+    }
+
+    triggers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn trigger(cron_expression: &str, timezone: Tz, policy: MissedRunPolicy) -> InMemoryTrigger {
+        InMemoryTrigger {
+            kind: TriggerKind::Cron,
+            account_id: Uuid::nil().to_string(),
+            action_id: "cron_trigger".to_string(),
+            plugin_name: PluginName::new("@anything/cron".to_string()).unwrap(),
+            plugin_version: Version::parse("0.1.0").unwrap(),
+            flow_id: Uuid::nil().to_string(),
+            action_label: "Cron".to_string(),
+            flow_version_id: Uuid::nil().to_string(),
+            config: TaskConfig {
+                inputs: None,
+                inputs_schema: None,
+                plugin_config: None,
+                plugin_config_schema: None,
+            },
+            last_fired: None,
+            next_fire: None,
+            cron_expression: cron_expression.to_string(),
+            timezone,
+            missed_run_policy: policy,
+            max_catch_up_runs: DEFAULT_MAX_CATCH_UP_RUNS,
+            paused: false,
+            event_filter: None,
+            notify_config: None,
+        }
+    }
+
+    fn utc(year: i32, month: u32, day: u32, hour: u32, min: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, hour, min, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_max_catch_up_runs_from_numbers_and_strings() {
+        use serde_json::json;
+
+        assert_eq!(parse_max_catch_up_runs(&json!(5)), 5);
+        assert_eq!(parse_max_catch_up_runs(&json!("25")), 25);
+        assert_eq!(parse_max_catch_up_runs(&json!(" 7 ")), 7);
+        assert_eq!(parse_max_catch_up_runs(&json!("0")), DEFAULT_MAX_CATCH_UP_RUNS);
+        assert_eq!(parse_max_catch_up_runs(&json!("many")), DEFAULT_MAX_CATCH_UP_RUNS);
+        assert_eq!(parse_max_catch_up_runs(&json!(null)), DEFAULT_MAX_CATCH_UP_RUNS);
+    }
+
+    #[test]
+    fn test_missed_fire_times_by_policy() {
+        let last_fired = utc(2024, 6, 1, 0, 0);
+        let now = utc(2024, 6, 1, 5, 30);
+        let hourly = "0 0 * * * *";
+
+        let skip = trigger(hourly, Tz::UTC, MissedRunPolicy::Skip);
+        assert!(missed_fire_times(&skip, last_fired, now).is_empty());
+
+        let run_once = trigger(hourly, Tz::UTC, MissedRunPolicy::RunOnce);
+        assert_eq!(
+            missed_fire_times(&run_once, last_fired, now),
+            vec![utc(2024, 6, 1, 5, 0)]
+        );
+
+        let run_all = trigger(hourly, Tz::UTC, MissedRunPolicy::RunAll);
+        assert_eq!(
+            missed_fire_times(&run_all, last_fired, now),
+            (1..=5)
+                .map(|hour| utc(2024, 6, 1, hour, 0))
+                .collect::<Vec<_>>()
+        );
+
+        // Over the cap only the most recent runs are kept
+        let capped = InMemoryTrigger {
+            max_catch_up_runs: 2,
+            ..run_all
+        };
+        assert_eq!(
+            missed_fire_times(&capped, last_fired, now),
+            vec![utc(2024, 6, 1, 4, 0), utc(2024, 6, 1, 5, 0)]
+        );
+
+        // Nothing was missed
+        assert!(missed_fire_times(&run_once, now, now).is_empty());
+    }
+
+    #[test]
+    fn test_missed_fire_times_across_dst() {
+        // 09:00 in New York is 14:00 UTC before the March 10th 2024 change and 13:00 UTC after it
+        let daily = trigger(
+            "0 0 9 * * *",
+            "America/New_York".parse().unwrap(),
+            MissedRunPolicy::RunAll,
+        );
+
+        assert_eq!(
+            missed_fire_times(&daily, utc(2024, 3, 8, 15, 0), utc(2024, 3, 11, 15, 0)),
+            vec![
+                utc(2024, 3, 9, 14, 0),
+                utc(2024, 3, 10, 13, 0),
+                utc(2024, 3, 11, 13, 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_missed_fire_times_large_gap() {
+        let every_second = InMemoryTrigger {
+            max_catch_up_runs: usize::MAX,
+            ..trigger("* * * * * *", Tz::UTC, MissedRunPolicy::RunAll)
+        };
+        let now = utc(2024, 6, 1, 0, 0);
+
+        let missed = missed_fire_times(&every_second, utc(2023, 6, 1, 0, 0), now);
+        assert_eq!(missed.len(), MAX_CATCH_UP_RUNS);
+        assert_eq!(missed.last(), Some(&now));
+        assert!(missed.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+}
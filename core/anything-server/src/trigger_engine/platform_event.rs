@@ -0,0 +1,132 @@
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::platform_events::{PlatformEvent, PlatformEventType, MAX_EVENT_CHAIN_DEPTH};
+use crate::AppState;
+
+use super::{start_trigger_session_with_id, InMemoryTrigger, TriggerKey};
+
+// Which platform events an `@anything/platform_event` trigger reacts to
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlatformEventFilter {
+    pub event_type: String, // workflow_finished, task_failed or account_refresh_failed
+    pub workflow_id: Option<String>,
+    pub status: String, // any, completed or failed. Only used for workflow_finished.
+}
+
+impl PlatformEventFilter {
+    pub fn from_config(config: &Value) -> Self {
+        let get = |key: &str| {
+            config
+                .get(key)
+                .and_then(Value::as_str)
+                .map(|value| value.trim().to_lowercase())
+                .filter(|value| !value.is_empty())
+        };
+
+        PlatformEventFilter {
+            event_type: get("event_type").unwrap_or_else(|| "workflow_finished".to_string()),
+            workflow_id: get("workflow_id"),
+            status: get("status").unwrap_or_else(|| "any".to_string()),
+        }
+    }
+
+    pub fn matches(&self, trigger: &InMemoryTrigger, event: &PlatformEvent) -> bool {
+        // Events never cross accounts
+        if event.account_id.to_string() != trigger.account_id {
+            return false;
+        }
+
+        // A workflow reacting to its own events would only ever loop
+        if event.flow_id.map(|flow_id| flow_id.to_string()) == Some(trigger.flow_id.clone()) {
+            return false;
+        }
+
+        if let Some(workflow_id) = &self.workflow_id {
+            if event.flow_id.map(|flow_id| flow_id.to_string()) != Some(workflow_id.clone()) {
+                return false;
+            }
+        }
+
+        match (self.event_type.as_str(), &event.event_type) {
+            ("workflow_finished", PlatformEventType::WorkflowCompleted) => {
+                self.status == "any" || self.status == "completed"
+            }
+            ("workflow_finished", PlatformEventType::WorkflowFailed) => {
+                self.status == "any" || self.status == "failed"
+            }
+            ("task_failed", PlatformEventType::TaskFailed) => true,
+            ("account_refresh_failed", PlatformEventType::AccountRefreshFailed) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Starts a session for every platform event trigger the event matches.
+/// Every instance handles the events it produced itself, so this is not limited to the leader.
+pub fn handle_platform_event(
+    state: &Arc<AppState>,
+    triggers: &HashMap<TriggerKey, InMemoryTrigger>,
+    event: PlatformEvent,
+) {
+    if event.chain_depth >= MAX_EVENT_CHAIN_DEPTH {
+        println!(
+            "[PLATFORM EVENT TRIGGER] Dropping {} from session {:?}, event chain is {} deep",
+            event.event_type.as_str(),
+            event.flow_session_id,
+            event.chain_depth
+        );
+        return;
+    }
+
+    for (id, trigger) in triggers {
+        let matches = trigger
+            .event_filter
+            .as_ref()
+            .map_or(false, |filter| filter.matches(trigger, &event));
+        if !matches || trigger.paused {
+            continue;
+        }
+
+        println!(
+            "[PLATFORM EVENT TRIGGER] {} matched trigger {:?}",
+            event.event_type.as_str(),
+            id
+        );
+
+        let flow_session_id = Uuid::new_v4();
+        let chain_depth = event.chain_depth + 1;
+
+        // Register before the session starts so its own events can't outrun it
+        state
+            .platform_events
+            .set_chain_depth(flow_session_id, chain_depth);
+
+        let result = json!({
+            "event_type": event.event_type,
+            "account_id": event.account_id,
+            "flow_id": event.flow_id,
+            "flow_session_id": event.flow_session_id,
+            "data": event.data,
+            "occurred_at": event.occurred_at,
+            "chain_depth": chain_depth,
+        });
+
+        let state = state.clone();
+        let trigger = trigger.clone();
+        tokio::spawn(async move {
+            if let Err(e) =
+                start_trigger_session_with_id(&state, &trigger, flow_session_id, result).await
+            {
+                println!(
+                    "[PLATFORM EVENT TRIGGER] Failed to start session for {:?}: {:?}",
+                    (&trigger.flow_id, &trigger.action_id),
+                    e
+                );
+                state.platform_events.forget_session(&flow_session_id);
+            }
+        });
+    }
+}
@@ -0,0 +1,272 @@
+use chrono::Utc;
+use dotenv::dotenv;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::env;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    bundler::bundle_context_from_parts,
+    system_plugins::http::{client_cache::request_timeout, http_plugin::process_http_task},
+    AppState,
+};
+
+use super::{claim_fire, start_trigger_session, InMemoryTrigger, TriggerFire};
+
+// Only the most recent keys are remembered, older items are assumed to never come back
+pub const MAX_SEEN_KEYS: usize = 1000;
+
+// For triggers saved before they had a timeout, a hung endpoint would hold the check open
+const DEFAULT_POLL_TIMEOUT_SECONDS: u64 = 30;
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct PollState {
+    pub cursor: Option<String>,
+    pub seen_keys: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct PollStateRow<'a> {
+    flow_id: &'a str,
+    action_id: &'a str,
+    account_id: &'a str,
+    cursor: Option<&'a str>,
+    seen_keys: &'a [String],
+    last_polled_at: chrono::DateTime<Utc>,
+    updated_at: chrono::DateTime<Utc>,
+}
+
+/// Calls the trigger's endpoint and starts one flow session for every item it has not seen before.
+/// The first poll only records what is already there so existing items don't all fire at once.
+pub async fn poll_for_new_items(
+    state: &Arc<AppState>,
+    instance_id: &str,
+    trigger: &InMemoryTrigger,
+    fire: &TriggerFire,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    println!("[POLL TRIGGER] Polling for trigger {}", trigger.action_id);
+
+    let idempotency_key = match claim_fire(state, instance_id, trigger, fire).await? {
+        Some(idempotency_key) => idempotency_key,
+        None => return Ok(()),
+    };
+
+    // Render fresh every poll so OAuth tokens from connected accounts are current
+    let mut config = bundle_context_from_parts(
+        state.clone(),
+        &state.anything_client,
+        &trigger.account_id,
+        &Uuid::new_v4().to_string(),
+        trigger.config.inputs.as_ref(),
+        trigger.config.inputs_schema.as_ref(),
+        trigger.config.plugin_config.as_ref(),
+        trigger.config.plugin_config_schema.as_ref(),
+        true,
+    )
+    .await?;
+
+    let saved_state = fetch_poll_state(state, trigger).await?;
+    let first_poll = saved_state.is_none();
+    let mut poll_state = saved_state.unwrap_or_default();
+
+    if let Some(cursor) = &poll_state.cursor {
+        let cursor_param = config_str(&config, "cursor_param").unwrap_or("cursor");
+        let url = config_str(&config, "url").unwrap_or_default();
+        let url = with_query_param(url, cursor_param, cursor)?;
+        config["url"] = Value::String(url);
+    }
+
+    if request_timeout(&config)?.is_none() {
+        config["timeout_seconds"] = json!(DEFAULT_POLL_TIMEOUT_SECONDS);
+    }
+
+    let response = process_http_task(state, &trigger.account_id, &config)
+        .await?
+        .ok_or("Polling request returned no response")?;
+
+    let status_code = response["status_code"].as_u64().unwrap_or(0);
+    if !(200..300).contains(&status_code) {
+        return Err(format!("Polling request failed with status {}", status_code).into());
+    }
+
+    if response["body"]["type"] != "json" {
+        return Err("Polling response body is not JSON".into());
+    }
+    let body = &response["body"]["data"];
+
+    let items = match value_at_path(body, config_str(&config, "items_path").unwrap_or_default()) {
+        Some(Value::Array(items)) => items.clone(),
+        _ => return Err("Items path did not point at a list in the polling response".into()),
+    };
+
+    let item_key_path = config_str(&config, "item_key_path").unwrap_or_default();
+    let mut new_items = Vec::new();
+    for item in items {
+        let item_key = item_key(&item, item_key_path);
+        if poll_state.seen_keys.contains(&item_key) {
+            continue;
+        }
+        poll_state.seen_keys.push(item_key.clone());
+        new_items.push((item_key, item));
+    }
+
+    let overflow = poll_state.seen_keys.len().saturating_sub(MAX_SEEN_KEYS);
+    poll_state.seen_keys.drain(..overflow);
+
+    if let Some(cursor_path) = config_str(&config, "cursor_path").filter(|path| !path.is_empty()) {
+        // Keep the old cursor when the source stops returning one so we don't start over
+        if let Some(cursor) = value_at_path(body, cursor_path).and_then(cursor_to_string) {
+            poll_state.cursor = Some(cursor);
+        }
+    }
+
+    // Save before starting sessions, a crash in between drops items instead of running them twice
+    save_poll_state(state, trigger, &poll_state).await?;
+
+    if first_poll {
+        println!(
+            "[POLL TRIGGER] First poll for {} recorded {} existing items",
+            trigger.action_id,
+            new_items.len()
+        );
+        return Ok(());
+    }
+
+    println!(
+        "[POLL TRIGGER] Found {} new items for {}",
+        new_items.len(),
+        trigger.action_id
+    );
+
+    for (item_key, item) in new_items {
+        let result = json!({
+            "item": item,
+            "item_key": item_key,
+            "cursor": poll_state.cursor,
+            "polled_at": Utc::now(),
+            "scheduled_for": fire.scheduled_for,
+            "idempotency_key": format!("{}:{}", idempotency_key, item_key),
+        });
+
+        if let Err(e) = start_trigger_session(state, trigger, result).await {
+            println!(
+                "[POLL TRIGGER] Failed to start session for item {}: {:?}",
+                item_key, e
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn config_str<'a>(config: &'a Value, key: &str) -> Option<&'a str> {
+    config.get(key).and_then(Value::as_str)
+}
+
+// Dot separated path into a JSON value, numeric parts index into arrays. An empty path is the value itself.
+pub fn value_at_path<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .filter(|part| !part.is_empty())
+        .try_fold(value, |current, part| match current {
+            Value::Array(items) => items.get(part.parse::<usize>().ok()?),
+            _ => current.get(part),
+        })
+}
+
+// Items without a key at the configured path are identified by a hash of their contents
+fn item_key(item: &Value, item_key_path: &str) -> String {
+    if !item_key_path.is_empty() {
+        if let Some(key) = value_at_path(item, item_key_path).and_then(cursor_to_string) {
+            return key;
+        }
+    }
+
+    format!("{:x}", Sha256::digest(item.to_string().as_bytes()))
+}
+
+fn cursor_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) if !s.is_empty() => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+fn with_query_param(
+    url: &str,
+    name: &str,
+    value: &str,
+) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    let mut url = Url::parse(url)?;
+    let existing: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(key, _)| key != name)
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+
+    url.query_pairs_mut()
+        .clear()
+        .extend_pairs(existing)
+        .append_pair(name, value);
+
+    Ok(url.to_string())
+}
+
+pub async fn fetch_poll_state(
+    state: &Arc<AppState>,
+    trigger: &InMemoryTrigger,
+) -> Result<Option<PollState>, Box<dyn std::error::Error + Send + Sync>> {
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    let response = state
+        .anything_client
+        .from("trigger_poll_states")
+        .auth(supabase_service_role_api_key)
+        .select("cursor, seen_keys")
+        .eq("flow_id", &trigger.flow_id)
+        .eq("action_id", &trigger.action_id)
+        .execute()
+        .await?;
+
+    let body = response.text().await?;
+    let mut rows: Vec<PollState> = serde_json::from_str(&body)?;
+
+    Ok(rows.pop())
+}
+
+pub async fn save_poll_state(
+    state: &Arc<AppState>,
+    trigger: &InMemoryTrigger,
+    poll_state: &PollState,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    dotenv().ok();
+    let supabase_service_role_api_key = env::var("SUPABASE_SERVICE_ROLE_API_KEY")
+        .expect("SUPABASE_SERVICE_ROLE_API_KEY must be set");
+
+    let now = Utc::now();
+    let row = PollStateRow {
+        flow_id: &trigger.flow_id,
+        action_id: &trigger.action_id,
+        account_id: &trigger.account_id,
+        cursor: poll_state.cursor.as_deref(),
+        seen_keys: &poll_state.seen_keys,
+        last_polled_at: now,
+        updated_at: now,
+    };
+
+    state
+        .anything_client
+        .from("trigger_poll_states")
+        .auth(supabase_service_role_api_key)
+        .upsert(serde_json::to_string(&row)?)
+        .on_conflict("flow_id,action_id")
+        .execute()
+        .await?;
+
+    Ok(())
+}
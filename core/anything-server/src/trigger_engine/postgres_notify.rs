@@ -0,0 +1,224 @@
+use chrono::Utc;
+use futures::stream::{self, StreamExt};
+use native_tls::TlsConnector;
+use postgres_native_tls::MakeTlsConnector;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, Duration, Instant};
+use tokio_postgres::AsyncMessage;
+
+use crate::bundler::secrets::get_decrypted_secrets;
+use crate::AppState;
+
+use super::{start_trigger_session, InMemoryTrigger, TriggerKey};
+
+const INITIAL_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
+// A connection has to stay up this long before the backoff starts over, so a server that accepts
+// and then drops every connection isn't hammered once a second
+const HEALTHY_CONNECTION_UPTIME: Duration = Duration::from_secs(30);
+
+// Where an `@anything/postgres_notify` trigger listens
+#[derive(Debug, Clone, PartialEq)]
+pub struct PostgresNotifyConfig {
+    pub connection_secret_name: String, // Account secret holding the connection string
+    pub channel: String,
+}
+
+impl PostgresNotifyConfig {
+    pub fn from_config(config: &Value) -> Option<Self> {
+        let get = |key: &str| {
+            config
+                .get(key)
+                .and_then(Value::as_str)
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+
+        Some(PostgresNotifyConfig {
+            connection_secret_name: get("connection_secret_name")?,
+            channel: get("channel")?,
+        })
+    }
+}
+
+struct RunningListener {
+    flow_version_id: String,
+    config: PostgresNotifyConfig,
+    handle: JoinHandle<()>,
+}
+
+/// Keeps one LISTEN connection per postgres_notify trigger. Only the leader listens so every
+/// notification starts a single session.
+#[derive(Default)]
+pub struct PostgresListeners {
+    running: HashMap<TriggerKey, RunningListener>,
+}
+
+impl PostgresListeners {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Starts listeners for new or changed triggers and stops the ones that went away
+    pub fn sync(
+        &mut self,
+        state: &Arc<AppState>,
+        triggers: &HashMap<TriggerKey, InMemoryTrigger>,
+        is_leader: bool,
+    ) {
+        self.running.retain(|id, listener| {
+            let keep = is_leader
+                && triggers.get(id).map_or(false, |trigger| {
+                    !trigger.paused
+                        && trigger.flow_version_id == listener.flow_version_id
+                        && trigger.notify_config.as_ref() == Some(&listener.config)
+                });
+            if !keep {
+                println!("[POSTGRES NOTIFY] Stopping listener for {:?}", id);
+                listener.handle.abort();
+            }
+            keep
+        });
+
+        if !is_leader {
+            return;
+        }
+
+        for (id, trigger) in triggers {
+            let config = match &trigger.notify_config {
+                Some(config) if !trigger.paused => config.clone(),
+                _ => continue,
+            };
+            if self.running.contains_key(id) {
+                continue;
+            }
+
+            println!(
+                "[POSTGRES NOTIFY] Starting listener for {:?} on channel {}",
+                id, config.channel
+            );
+            let handle = tokio::spawn(listen_forever(
+                state.clone(),
+                trigger.clone(),
+                config.clone(),
+            ));
+            self.running.insert(
+                id.clone(),
+                RunningListener {
+                    flow_version_id: trigger.flow_version_id.clone(),
+                    config,
+                    handle,
+                },
+            );
+        }
+    }
+}
+
+// Reconnects with backoff whenever the connection drops. Stopped by aborting the task.
+async fn listen_forever(
+    state: Arc<AppState>,
+    trigger: InMemoryTrigger,
+    config: PostgresNotifyConfig,
+) {
+    let mut delay = INITIAL_RECONNECT_DELAY;
+
+    loop {
+        match listen(&state, &trigger, &config).await {
+            Ok(listened_for) => {
+                if listened_for >= HEALTHY_CONNECTION_UPTIME {
+                    delay = INITIAL_RECONNECT_DELAY;
+                }
+                println!(
+                    "[POSTGRES NOTIFY] Connection for channel {} closed after {:?}, reconnecting in {:?}",
+                    config.channel, listened_for, delay
+                );
+            }
+            Err(e) => {
+                println!(
+                    "[POSTGRES NOTIFY] Listener for channel {} failed, retrying in {:?}: {:?}",
+                    config.channel, delay, e
+                );
+            }
+        }
+
+        sleep(delay).await;
+        delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+    }
+}
+
+// Returns how long it was listening for once the connection closes
+async fn listen(
+    state: &Arc<AppState>,
+    trigger: &InMemoryTrigger,
+    config: &PostgresNotifyConfig,
+) -> Result<Duration, Box<dyn std::error::Error + Send + Sync>> {
+    // Looked up on every connect so a rotated secret is picked up
+    let secrets =
+        get_decrypted_secrets(state.clone(), &state.anything_client, &trigger.account_id).await?;
+    let connection_string = secrets
+        .into_iter()
+        .find(|secret| secret.secret_name == config.connection_secret_name)
+        .map(|secret| secret.secret_value)
+        .ok_or_else(|| format!("Secret {} not found", config.connection_secret_name))?;
+
+    let tls = MakeTlsConnector::new(TlsConnector::builder().build()?);
+    let (client, mut connection) = tokio_postgres::connect(&connection_string, tls).await?;
+
+    // The connection has to be polled for notifications to come through
+    let (notification_tx, mut notification_rx) = mpsc::unbounded_channel();
+    let mut messages = stream::poll_fn(move |cx| connection.poll_message(cx));
+    let connection_task = tokio::spawn(async move {
+        while let Some(message) = messages.next().await {
+            match message {
+                Ok(AsyncMessage::Notification(notification)) => {
+                    if notification_tx.send(notification).is_err() {
+                        break;
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    println!("[POSTGRES NOTIFY] Connection error: {:?}", e);
+                    break;
+                }
+            }
+        }
+    });
+
+    client
+        .batch_execute(&format!("LISTEN {}", quote_identifier(&config.channel)))
+        .await?;
+    println!("[POSTGRES NOTIFY] Listening on channel {}", config.channel);
+    let listening_since = Instant::now();
+
+    while let Some(notification) = notification_rx.recv().await {
+        let payload = notification.payload();
+        let result = json!({
+            "channel": notification.channel(),
+            // JSON payloads (e.g. from row_to_json) come through as objects
+            "payload": serde_json::from_str::<Value>(payload)
+                .unwrap_or_else(|_| Value::String(payload.to_string())),
+            "process_id": notification.process_id(),
+            "received_at": Utc::now(),
+        });
+
+        if let Err(e) = start_trigger_session(state, trigger, result).await {
+            println!(
+                "[POSTGRES NOTIFY] Failed to start session for channel {}: {:?}",
+                config.channel, e
+            );
+        }
+    }
+
+    connection_task.abort();
+    Ok(listening_since.elapsed())
+}
+
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
@@ -0,0 +1,181 @@
+use chrono::Utc;
+use feed_rs::model::{Entry, Feed, Person};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::{bundler::bundle_context_from_parts, AppState};
+
+use super::poll::{fetch_poll_state, save_poll_state, MAX_SEEN_KEYS};
+use super::{claim_fire, start_trigger_session, InMemoryTrigger, TriggerFire};
+
+// Feeds bigger than this are almost certainly not feeds
+const MAX_FEED_SIZE: usize = 10 * 1024 * 1024; // 10MB
+
+// Covers connecting and reading the whole feed, a slow feed is tried again on the next check
+const FEED_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Fetches the trigger's feed and starts one flow session for every entry it has not seen before.
+/// Seen GUIDs share storage with polling triggers. The first check only records existing entries.
+pub async fn check_feed_for_new_entries(
+    state: &Arc<AppState>,
+    instance_id: &str,
+    trigger: &InMemoryTrigger,
+    fire: &TriggerFire,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    println!(
+        "[RSS TRIGGER] Checking feed for trigger {}",
+        trigger.action_id
+    );
+
+    if claim_fire(state, instance_id, trigger, fire)
+        .await?
+        .is_none()
+    {
+        return Ok(());
+    }
+
+    let config = bundle_context_from_parts(
+        state.clone(),
+        &state.anything_client,
+        &trigger.account_id,
+        &Uuid::new_v4().to_string(),
+        trigger.config.inputs.as_ref(),
+        trigger.config.inputs_schema.as_ref(),
+        trigger.config.plugin_config.as_ref(),
+        trigger.config.plugin_config_schema.as_ref(),
+        false,
+    )
+    .await?;
+
+    let feed_url = config
+        .get("feed_url")
+        .and_then(Value::as_str)
+        .filter(|url| !url.is_empty())
+        .ok_or("RSS trigger is missing a feed URL")?;
+
+    let mut response = state
+        .http_client
+        .get(feed_url)
+        .header(
+            "Accept",
+            "application/rss+xml, application/atom+xml, application/xml, text/xml",
+        )
+        .timeout(FEED_REQUEST_TIMEOUT)
+        .send()
+        .await?
+        .error_for_status()?;
+
+    if response
+        .content_length()
+        .is_some_and(|length| length > MAX_FEED_SIZE as u64)
+    {
+        return Err(format!("Feed is larger than {} bytes", MAX_FEED_SIZE).into());
+    }
+
+    let bytes = read_limited_body(&mut response, MAX_FEED_SIZE).await?;
+    let feed = feed_rs::parser::parse(&bytes[..])?;
+
+    let saved_state = fetch_poll_state(state, trigger).await?;
+    let first_check = saved_state.is_none();
+    let mut poll_state = saved_state.unwrap_or_default();
+
+    // Feeds list newest first, run sessions oldest first
+    let mut new_entries = Vec::new();
+    for entry in feed.entries.iter().rev() {
+        if poll_state.seen_keys.contains(&entry.id) {
+            continue;
+        }
+        poll_state.seen_keys.push(entry.id.clone());
+        new_entries.push(entry);
+    }
+
+    let overflow = poll_state.seen_keys.len().saturating_sub(MAX_SEEN_KEYS);
+    poll_state.seen_keys.drain(..overflow);
+
+    save_poll_state(state, trigger, &poll_state).await?;
+
+    if first_check {
+        println!(
+            "[RSS TRIGGER] First check for {} recorded {} existing entries",
+            trigger.action_id,
+            new_entries.len()
+        );
+        return Ok(());
+    }
+
+    println!(
+        "[RSS TRIGGER] Found {} new entries for {}",
+        new_entries.len(),
+        trigger.action_id
+    );
+
+    for entry in new_entries {
+        if let Err(e) = start_trigger_session(state, trigger, normalize_entry(&feed, entry)).await {
+            println!(
+                "[RSS TRIGGER] Failed to start session for entry {}: {:?}",
+                entry.id, e
+            );
+        }
+    }
+
+    Ok(())
+}
+
+// Content-Length can be missing or wrong, so stop reading as soon as the body goes over the limit
+async fn read_limited_body(
+    response: &mut reqwest::Response,
+    limit: usize,
+) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if body.len() + chunk.len() > limit {
+            return Err(format!("Feed is larger than {} bytes", limit).into());
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+// Same shape for RSS 2.0 and Atom so templates don't care which one the feed uses
+pub fn normalize_entry(feed: &Feed, entry: &Entry) -> Value {
+    let content = entry
+        .content
+        .as_ref()
+        .and_then(|content| content.body.clone())
+        .or_else(|| {
+            entry
+                .summary
+                .as_ref()
+                .map(|summary| summary.content.clone())
+        });
+
+    json!({
+        "guid": entry.id,
+        "title": entry.title.as_ref().map(|title| title.content.clone()),
+        "link": entry.links.first().map(|link| link.href.clone()),
+        "published": entry.published.or(entry.updated),
+        "content": content,
+        "summary": entry.summary.as_ref().map(|summary| summary.content.clone()),
+        "author": entry.authors.first().map(author_name),
+        "categories": entry
+            .categories
+            .iter()
+            .map(|category| category.term.clone())
+            .collect::<Vec<_>>(),
+        "feed": {
+            "title": feed.title.as_ref().map(|title| title.content.clone()),
+            "link": feed.links.first().map(|link| link.href.clone()),
+        },
+        "received_at": Utc::now(),
+    })
+}
+
+// RSS <author> only carries an email, which the parser files under a placeholder name
+fn author_name(author: &Person) -> String {
+    match (&author.email, author.name.as_str()) {
+        (Some(email), "author") => email.clone(),
+        _ => author.name.clone(),
+    }
+}
@@ -47,11 +47,52 @@ pub enum InputFieldType {
     Unknown,
 }
 
+impl InputFieldType {
+    // How values interpolated into a field of this type are escaped unless set on the field
+    pub fn escape_mode(&self) -> Option<EscapeMode> {
+        match self {
+            InputFieldType::HtmlOrVariable | InputFieldType::XmlOrVariable => {
+                Some(EscapeMode::Html)
+            }
+            InputFieldType::ObjectOrVariable => Some(EscapeMode::Json),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum EscapeMode {
+    Html,
+    Url,
+    Json,
+    Shell,
+    Raw,
+}
+
+impl EscapeMode {
+    pub fn from_content_type(content_type: &str) -> Option<EscapeMode> {
+        let media_type = content_type.split(';').next().unwrap_or("").trim();
+        match media_type.to_lowercase().as_str() {
+            "text/html" | "application/xhtml+xml" | "text/xml" | "application/xml" => {
+                Some(EscapeMode::Html)
+            }
+            "application/json" => Some(EscapeMode::Json),
+            "application/x-www-form-urlencoded" => Some(EscapeMode::Url),
+            "application/x-sh" | "text/x-shellscript" => Some(EscapeMode::Shell),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct ValidationField {
     pub r#type: ValidationFieldType,
     #[serde(default = "default_strict")]
     pub strict: bool,
+    // Escaping for values interpolated into this field, defaults from the input type or content type
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub escape: Option<EscapeMode>,
 }
 
 // Add this function to provide the default value for strict
@@ -64,6 +105,7 @@ impl Default for ValidationField {
         ValidationField {
             r#type: ValidationFieldType::Unknown,
             strict: true,
+            escape: None,
         }
     }
 }
@@ -95,6 +137,8 @@ pub struct JsonSchemaProperty {
     pub one_of: Option<Vec<serde_json::Value>>,
     #[serde(rename = "x-jsf-presentation")]
     pub x_jsf_presentation: Option<PresentationField>,
+    #[serde(rename = "contentMediaType")]
+    pub content_media_type: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
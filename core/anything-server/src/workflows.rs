@@ -1,5 +1,5 @@
 use axum::{
-    extract::{Extension, Path, State},
+    extract::{Extension, Path, Query, State},
     http::{HeaderMap, StatusCode},
     response::IntoResponse,
     Json,
@@ -9,6 +9,7 @@ use serde_json::Value;
 use std::sync::Arc;
 
 use crate::supabase_jwt_middleware::User;
+use crate::trigger_engine::{upcoming_fire_times, TriggerKind};
 use crate::types::workflow_types::WorkflowVersionDefinition;
 use crate::AppState;
 use uuid::Uuid;
@@ -60,6 +61,19 @@ pub struct UpdateWorkflowInput {
     description: Option<String>,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PauseWorkflowInput {
+    paused_response: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpcomingTriggerRunsQuery {
+    count: Option<usize>,
+}
+
+const DEFAULT_UPCOMING_TRIGGER_RUNS: usize = 5;
+const MAX_UPCOMING_TRIGGER_RUNS: usize = 50;
+
 pub async fn get_workflows(
     Path(account_id): Path<String>,
     State(state): State<Arc<AppState>>,
@@ -665,6 +679,135 @@ pub async fn update_workflow(
     Json(body).into_response()
 }
 
+pub async fn pause_workflow(
+    Path((account_id, flow_id)): Path<(String, String)>,
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    payload: Option<Json<PauseWorkflowInput>>,
+) -> impl IntoResponse {
+    println!("[WORKFLOWS] Pausing workflow: {}", flow_id);
+
+    let paused_response = payload.and_then(|Json(payload)| payload.paused_response);
+
+    let update = serde_json::json!({
+        "paused": true,
+        "paused_at": Utc::now(),
+        "paused_response": paused_response,
+    });
+
+    set_workflow_paused(&state, user, &account_id, flow_id, update).await
+}
+
+pub async fn resume_workflow(
+    Path((account_id, flow_id)): Path<(String, String)>,
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+) -> impl IntoResponse {
+    println!("[WORKFLOWS] Resuming workflow: {}", flow_id);
+
+    let update = serde_json::json!({
+        "paused": false,
+        "paused_at": null,
+    });
+
+    set_workflow_paused(&state, user, &account_id, flow_id, update).await
+}
+
+async fn set_workflow_paused(
+    state: &Arc<AppState>,
+    user: User,
+    account_id: &str,
+    flow_id: String,
+    update: Value,
+) -> axum::response::Response {
+    let response = match state
+        .anything_client
+        .from("flows")
+        .auth(user.jwt)
+        .eq("flow_id", &flow_id)
+        .eq("account_id", account_id)
+        .update(update.to_string())
+        .execute()
+        .await
+    {
+        Ok(response) => response,
+        Err(err) => {
+            eprintln!("Error: {:?}", err);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to execute request",
+            )
+                .into_response();
+        }
+    };
+
+    let body = match response.text().await {
+        Ok(body) => body,
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to read response body",
+            )
+                .into_response()
+        }
+    };
+
+    // Let the trigger engine reload the workflow so its cron triggers pick up the paused flag
+    if let Err(err) = state.trigger_engine_signal.send(flow_id) {
+        println!("Failed to send trigger signal: {:?}", err);
+    }
+
+    Json(body).into_response()
+}
+
+// Next fire times for every cron trigger on the published version, worked out by the trigger engine itself
+// Reads the trigger engine's own triggers so the preview matches what will actually fire
+pub async fn get_upcoming_trigger_runs(
+    Path((account_id, flow_id)): Path<(String, String)>,
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<UpcomingTriggerRunsQuery>,
+) -> impl IntoResponse {
+    let count = query
+        .count
+        .unwrap_or(DEFAULT_UPCOMING_TRIGGER_RUNS)
+        .clamp(1, MAX_UPCOMING_TRIGGER_RUNS);
+
+    let in_memory_triggers = state.trigger_engine_triggers.read().await;
+    let mut triggers = Vec::new();
+    for trigger in in_memory_triggers.values() {
+        if trigger.flow_id != flow_id || trigger.account_id != account_id {
+            continue;
+        }
+
+        // Event triggers don't run on a schedule
+        if matches!(
+            trigger.kind,
+            TriggerKind::PlatformEvent | TriggerKind::PostgresNotify
+        ) {
+            continue;
+        }
+
+        triggers.push(serde_json::json!({
+            "flow_id": trigger.flow_id,
+            "flow_version_id": trigger.flow_version_id,
+            "action_id": trigger.action_id,
+            "action_label": trigger.action_label,
+            "cron_expression": trigger.cron_expression,
+            "timezone": trigger.timezone.name(),
+            "paused": trigger.paused,
+            "last_fired": trigger.last_fired,
+            "next_fire": trigger.next_fire,
+            "upcoming_runs": upcoming_fire_times(
+                &trigger.cron_expression,
+                &trigger.timezone,
+                count
+            ),
+        }));
+    }
+
+    Json(serde_json::json!({ "triggers": triggers })).into_response()
+}
+
 pub async fn update_workflow_version(
     Path((_account_id, workflow_id, workflow_version_id)): Path<(String, String, String)>,
     State(state): State<Arc<AppState>>,
@@ -0,0 +1,121 @@
+-- Only one anything-server instance may fire cron triggers at a time.
+-- Instances race to hold a short lease row and keep renewing it. If the leader dies the lease
+-- expires and another instance picks it up on its next renewal.
+CREATE TABLE IF NOT EXISTS anything.trigger_engine_leases
+(
+    lease_name TEXT NOT NULL primary key,
+    holder_id TEXT NOT NULL,
+    expires_at timestamp with time zone NOT NULL,
+    updated_at timestamp with time zone NOT NULL DEFAULT now()
+);
+
+-- Every cron fire is recorded under an idempotency key so the same schedule slot can never run twice
+CREATE TABLE IF NOT EXISTS anything.trigger_fires
+(
+    idempotency_key TEXT NOT NULL primary key,
+    flow_id uuid NOT NULL,
+    action_id TEXT NOT NULL,
+    scheduled_for timestamp with time zone,
+    holder_id TEXT NOT NULL,
+    fired_at timestamp with time zone NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS trigger_fires_flow_action_idx
+    ON anything.trigger_fires (flow_id, action_id, fired_at DESC);
+
+CREATE INDEX IF NOT EXISTS trigger_fires_fired_at_idx
+    ON anything.trigger_fires (fired_at);
+
+-- enable RLS on the tables. No policies, these are only used by the server with the service role
+ALTER TABLE anything.trigger_engine_leases ENABLE ROW LEVEL SECURITY;
+ALTER TABLE anything.trigger_fires ENABLE ROW LEVEL SECURITY;
+
+-- Take or renew the lease. Returns true when p_holder_id holds it afterwards.
+CREATE OR REPLACE FUNCTION anything.acquire_trigger_engine_lease(p_lease_name text, p_holder_id text, p_ttl_seconds integer)
+RETURNS boolean
+LANGUAGE plpgsql
+SECURITY INVOKER
+AS $$
+DECLARE
+    current_holder text;
+BEGIN
+    IF current_setting('role', true) IS DISTINCT FROM 'service_role' THEN
+        RAISE EXCEPTION 'authentication required';
+    END IF;
+
+    INSERT INTO anything.trigger_engine_leases AS l (lease_name, holder_id, expires_at, updated_at)
+    VALUES (p_lease_name, p_holder_id, now() + make_interval(secs => p_ttl_seconds), now())
+    ON CONFLICT (lease_name) DO UPDATE
+        SET holder_id = EXCLUDED.holder_id,
+            expires_at = EXCLUDED.expires_at,
+            updated_at = now()
+        WHERE l.holder_id = EXCLUDED.holder_id
+           OR l.expires_at < now()
+    RETURNING holder_id INTO current_holder;
+
+    RETURN current_holder IS NOT DISTINCT FROM p_holder_id;
+END;
+$$;
+
+CREATE OR REPLACE FUNCTION anything.release_trigger_engine_lease(p_lease_name text, p_holder_id text, p_ttl_seconds integer)
+RETURNS boolean
+LANGUAGE plpgsql
+SECURITY INVOKER
+AS $$
+BEGIN
+    IF current_setting('role', true) IS DISTINCT FROM 'service_role' THEN
+        RAISE EXCEPTION 'authentication required';
+    END IF;
+
+    DELETE FROM anything.trigger_engine_leases
+    WHERE lease_name = p_lease_name
+      AND holder_id = p_holder_id;
+
+    RETURN FOUND;
+END;
+$$;
+
+-- Returns false when this idempotency key was already fired or the workflow is paused.
+-- Paused slots are still recorded so they count as skipped and aren't caught up on resume.
+-- Also drops fires older than p_retention_days, except the newest one of each trigger.
+CREATE OR REPLACE FUNCTION anything.claim_trigger_fire(p_idempotency_key text, p_flow_id uuid, p_action_id text, p_scheduled_for timestamp with time zone, p_holder_id text, p_retention_days integer)
+RETURNS boolean
+LANGUAGE plpgsql
+SECURITY INVOKER
+AS $$
+DECLARE
+    claimed boolean;
+BEGIN
+    IF current_setting('role', true) IS DISTINCT FROM 'service_role' THEN
+        RAISE EXCEPTION 'authentication required';
+    END IF;
+
+    INSERT INTO anything.trigger_fires (idempotency_key, flow_id, action_id, scheduled_for, holder_id)
+    VALUES (p_idempotency_key, p_flow_id, p_action_id, p_scheduled_for, p_holder_id)
+    ON CONFLICT (idempotency_key) DO NOTHING;
+
+    claimed := FOUND;
+
+    -- Pausing only signals the instance it happened on, the leader may still have the trigger scheduled
+    IF claimed AND EXISTS (
+        SELECT 1
+        FROM anything.flows
+        WHERE flow_id = p_flow_id
+          AND paused
+    ) THEN
+        claimed := false;
+    END IF;
+
+    DELETE FROM anything.trigger_fires f
+    WHERE f.fired_at < now() - make_interval(days => p_retention_days)
+      AND EXISTS (
+          SELECT 1
+          FROM anything.trigger_fires newer
+          WHERE newer.flow_id = f.flow_id
+            AND newer.action_id = f.action_id
+            AND newer.fired_at > f.fired_at
+      );
+
+    RETURN claimed;
+END;
+$$;
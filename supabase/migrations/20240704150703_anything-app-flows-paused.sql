@@ -0,0 +1,7 @@
+-- Paused workflows stay published but none of their triggers fire.
+-- Webhooks to a paused workflow answer with paused_response instead of starting a run.
+-- paused_response looks like { "status_code": 503, "headers": { ... }, "body": ... }
+ALTER TABLE anything.flows
+    ADD COLUMN IF NOT EXISTS paused boolean NOT NULL DEFAULT false,
+    ADD COLUMN IF NOT EXISTS paused_at timestamp with time zone,
+    ADD COLUMN IF NOT EXISTS paused_response jsonb;
@@ -0,0 +1,16 @@
+-- What a polling or feed trigger has already seen, so each new item only starts one flow session.
+-- cursor is the last cursor the source returned, seen_keys the most recent item keys.
+CREATE TABLE IF NOT EXISTS anything.trigger_poll_states
+(
+    flow_id uuid NOT NULL references anything.flows(flow_id),
+    action_id TEXT NOT NULL,
+    account_id uuid NOT NULL references basejump.accounts(id),
+    cursor TEXT,
+    seen_keys jsonb NOT NULL DEFAULT '[]'::jsonb,
+    last_polled_at timestamp with time zone,
+    updated_at timestamp with time zone NOT NULL DEFAULT now(),
+    primary key (flow_id, action_id)
+);
+
+-- enable RLS on the table. No policies, this is only used by the server with the service role
+ALTER TABLE anything.trigger_poll_states ENABLE ROW LEVEL SECURITY;
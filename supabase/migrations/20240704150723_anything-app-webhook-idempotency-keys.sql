@@ -0,0 +1,61 @@
+-- Webhook senders retry. Requests carrying an idempotency key are recorded here so a retry returns
+-- the original flow session (or its stored response) instead of starting another run.
+CREATE TABLE IF NOT EXISTS anything.webhook_idempotency_keys
+(
+    flow_id uuid NOT NULL references anything.flows(flow_id),
+    idempotency_key TEXT NOT NULL,
+    account_id uuid NOT NULL references basejump.accounts(id),
+    flow_session_id uuid NOT NULL,
+    response jsonb,
+    created_at timestamp with time zone NOT NULL DEFAULT now(),
+    expires_at timestamp with time zone NOT NULL,
+    primary key (flow_id, idempotency_key)
+);
+
+CREATE INDEX IF NOT EXISTS webhook_idempotency_keys_expires_at_idx
+    ON anything.webhook_idempotency_keys (expires_at);
+
+-- enable RLS on the table. No policies, this is only used by the server with the service role
+ALTER TABLE anything.webhook_idempotency_keys ENABLE ROW LEVEL SECURITY;
+
+-- Claims the key for p_flow_session_id. Returns {"claimed": true} for a new key, otherwise the
+-- original run as {"claimed": false, "flow_session_id": ..., "response": ...}. Expired keys are reused.
+CREATE OR REPLACE FUNCTION anything.claim_webhook_idempotency_key(p_flow_id uuid, p_idempotency_key text, p_flow_session_id uuid, p_window_seconds integer)
+RETURNS jsonb
+LANGUAGE plpgsql
+SECURITY INVOKER
+AS $$
+DECLARE
+    existing anything.webhook_idempotency_keys%ROWTYPE;
+BEGIN
+    IF current_setting('role', true) IS DISTINCT FROM 'service_role' THEN
+        RAISE EXCEPTION 'authentication required';
+    END IF;
+
+    DELETE FROM anything.webhook_idempotency_keys
+    WHERE flow_id = p_flow_id
+      AND idempotency_key = p_idempotency_key
+      AND expires_at < now();
+
+    INSERT INTO anything.webhook_idempotency_keys (flow_id, idempotency_key, account_id, flow_session_id, expires_at)
+    SELECT flow_id, p_idempotency_key, account_id, p_flow_session_id, now() + make_interval(secs => p_window_seconds)
+    FROM anything.flows
+    WHERE flow_id = p_flow_id
+    ON CONFLICT (flow_id, idempotency_key) DO NOTHING;
+
+    IF FOUND THEN
+        RETURN jsonb_build_object('claimed', true);
+    END IF;
+
+    SELECT * INTO existing
+    FROM anything.webhook_idempotency_keys
+    WHERE flow_id = p_flow_id
+      AND idempotency_key = p_idempotency_key;
+
+    RETURN jsonb_build_object(
+        'claimed', false,
+        'flow_session_id', existing.flow_session_id,
+        'response', existing.response
+    );
+END;
+$$;
@@ -0,0 +1,93 @@
+-- The last requests sent to each workflow's webhook, including the ones that were rejected, so
+-- "we sent the webhook" can be checked and replayed. Only the newest rows per flow are kept.
+CREATE TABLE IF NOT EXISTS anything.webhook_requests
+(
+    webhook_request_id uuid primary key default gen_random_uuid(),
+    flow_id uuid NOT NULL references anything.flows(flow_id),
+    account_id uuid NOT NULL references basejump.accounts(id),
+    flow_version_id uuid,
+    respond boolean NOT NULL DEFAULT false,
+    method TEXT NOT NULL,
+    path TEXT NOT NULL,
+    query TEXT,
+    headers jsonb NOT NULL DEFAULT '{}'::jsonb,
+    remote_addr TEXT,
+    body_preview TEXT,
+    body_size bigint NOT NULL DEFAULT 0,
+    -- base64, only kept for bodies small enough to replay
+    body TEXT,
+    response_status integer NOT NULL,
+    flow_session_id uuid,
+    replay_of uuid,
+    created_at timestamp with time zone NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS webhook_requests_flow_id_created_at_idx
+    ON anything.webhook_requests (flow_id, created_at DESC);
+
+-- enable RLS on the table. The server writes with the service role, account members can read
+ALTER TABLE anything.webhook_requests ENABLE ROW LEVEL SECURITY;
+
+create policy "Account members can select" on anything.webhook_requests
+    for select
+    to authenticated
+    using (
+    (account_id IN ( SELECT basejump.get_accounts_with_role()))
+    );
+
+-- The replayable body is kept as sent and can hold credentials, so only the server reads it.
+-- Members get the preview, which has credential-looking fields redacted.
+REVOKE SELECT ON anything.webhook_requests FROM anon, authenticated;
+GRANT SELECT (
+    webhook_request_id, flow_id, account_id, flow_version_id, respond, method, path, query, headers,
+    remote_addr, body_preview, body_size, response_status, flow_session_id, replay_of, created_at
+) ON anything.webhook_requests TO authenticated;
+
+-- Records a request and drops everything but the newest p_keep requests for the flow.
+-- Requests for flows that don't exist are ignored.
+CREATE OR REPLACE FUNCTION anything.record_webhook_request(
+    p_flow_id uuid,
+    p_flow_version_id uuid,
+    p_respond boolean,
+    p_method text,
+    p_path text,
+    p_query text,
+    p_headers jsonb,
+    p_remote_addr text,
+    p_body_preview text,
+    p_body_size bigint,
+    p_body text,
+    p_response_status integer,
+    p_flow_session_id uuid,
+    p_replay_of uuid,
+    p_keep integer
+)
+RETURNS void
+LANGUAGE plpgsql
+SECURITY INVOKER
+AS $$
+BEGIN
+    IF current_setting('role', true) IS DISTINCT FROM 'service_role' THEN
+        RAISE EXCEPTION 'authentication required';
+    END IF;
+
+    INSERT INTO anything.webhook_requests (
+        flow_id, account_id, flow_version_id, respond, method, path, query, headers, remote_addr,
+        body_preview, body_size, body, response_status, flow_session_id, replay_of
+    )
+    SELECT flow_id, account_id, p_flow_version_id, p_respond, p_method, p_path, p_query, p_headers, p_remote_addr,
+        p_body_preview, p_body_size, p_body, p_response_status, p_flow_session_id, p_replay_of
+    FROM anything.flows
+    WHERE flow_id = p_flow_id;
+
+    DELETE FROM anything.webhook_requests
+    WHERE flow_id = p_flow_id
+      AND webhook_request_id NOT IN (
+          SELECT webhook_request_id
+          FROM anything.webhook_requests
+          WHERE flow_id = p_flow_id
+          ORDER BY created_at DESC
+          LIMIT p_keep
+      );
+END;
+$$;